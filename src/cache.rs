@@ -0,0 +1,313 @@
+//! # Resolver Cache
+//!
+//! Caches resolved DID documents so repeated resolution of the same DID does
+//! not re-fetch and re-parse the document.
+//!
+//! Cache entries expire after the cache's configured time-to-live or at the
+//! document's `nextUpdate` metadata time, whichever comes first. HTTP caching
+//! directives (such as `Cache-Control: max-age`) for `did:web` documents are
+//! not visible through [`DidResolver`] and should be applied by the resolver
+//! implementation.
+
+use std::collections::HashMap;
+use std::future::{self, Future};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+
+use crate::DidResolver;
+use crate::method::MethodRegistry;
+use crate::resolution::{self, Options, Resolved};
+
+/// [`ResolverCache`] is used by implementers to provide storage for resolved
+/// DID documents, keyed by DID.
+pub trait ResolverCache: Send + Sync {
+    /// Retrieve the cached resolution result for the DID, if it exists and
+    /// has not expired.
+    fn get(&self, did: &str) -> impl Future<Output = Option<Resolved>> + Send;
+
+    /// Cache the resolution result for the DID.
+    fn put(&self, did: &str, resolved: &Resolved) -> impl Future<Output = ()> + Send;
+}
+
+/// Resolve a DID to a DID document, using the cache where possible.
+///
+/// The cache holds the DID method's resolution of the DID, before the
+/// requested representation is applied and `alsoKnownAs` links are followed,
+/// so one cached entry serves every representation of the document.
+///
+/// The cache is bypassed when the `no_cache` option is set, a specific version
+/// of the document is requested, or a custom `did:web` URL template is used.
+/// Only successful resolutions are cached.
+///
+/// # Errors
+///
+/// Returns a [DID resolution](https://www.w3.org/TR/did-core/#did-resolution-metadata)
+/// error as specified.
+pub async fn resolve_cached(
+    did: &str, opts: Option<Options>, resolver: impl DidResolver, cache: &impl ResolverCache,
+) -> crate::Result<Resolved> {
+    let use_cache = opts.as_ref().is_none_or(|o| {
+        o.no_cache != Some(true)
+            && o.version_id.is_none()
+            && o.version_time.is_none()
+            && o.web_path_template.is_none()
+    });
+
+    let registry = MethodRegistry::new();
+    let result = if use_cache && let Some(resolved) = cache.get(did).await {
+        Ok(resolved)
+    } else {
+        let result = registry.resolve(did, opts.clone(), resolver.clone()).await;
+        if use_cache
+            && let Ok(resolved) = &result
+            && resolved.document.is_some()
+            && resolved.metadata.error.is_none()
+        {
+            cache.put(did, resolved).await;
+        }
+        result
+    };
+
+    Ok(resolution::complete_resolution(result, opts.as_ref(), resolver, &registry).await)
+}
+
+/// An in-memory, least-recently-used [`ResolverCache`].
+#[derive(Debug)]
+pub struct MemoryCache {
+    capacity: usize,
+    ttl: Duration,
+    entries: Mutex<Entries>,
+    now: fn() -> DateTime<Utc>,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    tick: u64,
+    items: HashMap<String, Entry>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    resolved: Resolved,
+    expires: DateTime<Utc>,
+    last_used: u64,
+}
+
+impl MemoryCache {
+    /// Create a cache holding up to `capacity` documents, each for no longer
+    /// than `ttl`.
+    #[must_use]
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            capacity,
+            ttl,
+            entries: Mutex::new(Entries::default()),
+            now: Utc::now,
+        }
+    }
+
+    fn lookup(&self, did: &str) -> Option<Resolved> {
+        let mut entries = self.entries.lock().ok()?;
+        entries.tick += 1;
+        let tick = entries.tick;
+
+        let entry = entries.items.get_mut(did)?;
+        if entry.expires <= (self.now)() {
+            entries.items.remove(did);
+            return None;
+        }
+        entry.last_used = tick;
+        let resolved = entry.resolved.clone();
+        drop(entries);
+
+        Some(resolved)
+    }
+
+    fn insert(&self, did: &str, resolved: &Resolved) {
+        if self.capacity == 0 {
+            return;
+        }
+        let Ok(mut entries) = self.entries.lock() else {
+            return;
+        };
+
+        // expire no later than the document's next update
+        let mut expires = (self.now)() + self.ttl;
+        if let Some(next_update) = resolved.document_metadata.as_ref().and_then(|md| md.next_update)
+        {
+            expires = expires.min(next_update);
+        }
+
+        // evict the least recently used entry when full
+        if !entries.items.contains_key(did)
+            && entries.items.len() >= self.capacity
+            && let Some(lru) =
+                entries.items.iter().min_by_key(|(_, e)| e.last_used).map(|(k, _)| k.clone())
+        {
+            entries.items.remove(&lru);
+        }
+
+        entries.tick += 1;
+        let last_used = entries.tick;
+        entries.items.insert(
+            did.to_string(),
+            Entry {
+                resolved: resolved.clone(),
+                expires,
+                last_used,
+            },
+        );
+    }
+}
+
+impl Default for MemoryCache {
+    fn default() -> Self {
+        Self::new(1000, Duration::from_secs(3600))
+    }
+}
+
+impl ResolverCache for MemoryCache {
+    fn get(&self, did: &str) -> impl Future<Output = Option<Resolved>> + Send {
+        future::ready(self.lookup(did))
+    }
+
+    fn put(&self, did: &str, resolved: &Resolved) -> impl Future<Output = ()> + Send {
+        self.insert(did, resolved);
+        future::ready(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicI64, AtomicUsize, Ordering};
+
+    use anyhow::anyhow;
+    use chrono::TimeDelta;
+
+    use super::*;
+    use crate::document::{Document, DocumentMetadata};
+    use crate::resolution::ContentType;
+
+    const DID: &str = "did:web:demo.credibil.io";
+
+    // Counts the number of times a document is fetched.
+    #[derive(Clone, Default)]
+    struct CountingResolver {
+        count: Arc<AtomicUsize>,
+        next_update: Option<DateTime<Utc>>,
+    }
+    impl DidResolver for CountingResolver {
+        async fn resolve(&self, _url: &str) -> anyhow::Result<Document> {
+            self.count.fetch_add(1, Ordering::SeqCst);
+            let mut document: Document =
+                serde_json::from_slice(include_bytes!("web/did-ecdsa.json"))
+                    .map_err(|e| anyhow!("issue deserializing document: {e}"))?;
+            if let Some(next_update) = self.next_update {
+                document.did_document_metadata = Some(DocumentMetadata {
+                    next_update: Some(next_update),
+                    ..DocumentMetadata::default()
+                });
+            }
+            Ok(document)
+        }
+    }
+
+    #[tokio::test]
+    async fn cache_hit() {
+        let resolver = CountingResolver::default();
+        let cache = MemoryCache::default();
+
+        let first =
+            resolve_cached(DID, None, resolver.clone(), &cache).await.expect("should resolve");
+        let second =
+            resolve_cached(DID, None, resolver.clone(), &cache).await.expect("should resolve");
+        assert_eq!(first, second);
+        assert_eq!(resolver.count.load(Ordering::SeqCst), 1);
+
+        // bypass the cache when requested
+        let opts = Options {
+            no_cache: Some(true),
+            ..Options::default()
+        };
+        resolve_cached(DID, Some(opts), resolver.clone(), &cache).await.expect("should resolve");
+        assert_eq!(resolver.count.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn representations() {
+        let resolver = CountingResolver::default();
+        let cache = MemoryCache::default();
+
+        // a did+json lookup does not strip @context from later did+ld+json lookups
+        let json = Options {
+            accept: Some(ContentType::DidJson),
+            ..Options::default()
+        };
+        let resolved = resolve_cached(DID, Some(json), resolver.clone(), &cache)
+            .await
+            .expect("should resolve");
+        let document = resolved.document.expect("should have document");
+        assert_eq!(document.context, []);
+
+        let ld_json = Options {
+            accept: Some(ContentType::DidLdJson),
+            ..Options::default()
+        };
+        let resolved = resolve_cached(DID, Some(ld_json), resolver.clone(), &cache)
+            .await
+            .expect("should resolve");
+        assert_eq!(resolved.metadata.content_type, ContentType::DidLdJson);
+        let document = resolved.document.expect("should have document");
+        assert!(!document.context.is_empty());
+
+        // and both were served by a single fetch
+        assert_eq!(resolver.count.load(Ordering::SeqCst), 1);
+    }
+
+    // A clock advanced by the test rather than by waiting.
+    static ELAPSED_MS: AtomicI64 = AtomicI64::new(0);
+    fn test_clock() -> DateTime<Utc> {
+        DateTime::UNIX_EPOCH + TimeDelta::milliseconds(ELAPSED_MS.load(Ordering::SeqCst))
+    }
+
+    #[tokio::test]
+    async fn ttl_expiry() {
+        let resolver = CountingResolver::default();
+        let cache = MemoryCache {
+            now: test_clock,
+            ..MemoryCache::new(10, Duration::from_millis(10))
+        };
+
+        resolve_cached(DID, None, resolver.clone(), &cache).await.expect("should resolve");
+        ELAPSED_MS.fetch_add(20, Ordering::SeqCst);
+        resolve_cached(DID, None, resolver.clone(), &cache).await.expect("should resolve");
+        assert_eq!(resolver.count.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn next_update() {
+        let resolver = CountingResolver {
+            next_update: Some(Utc::now()),
+            ..CountingResolver::default()
+        };
+        let cache = MemoryCache::default();
+
+        // the document is due for update, so is never served from the cache
+        resolve_cached(DID, None, resolver.clone(), &cache).await.expect("should resolve");
+        resolve_cached(DID, None, resolver.clone(), &cache).await.expect("should resolve");
+        assert_eq!(resolver.count.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn evict_lru() {
+        let cache = MemoryCache::new(1, Duration::from_secs(60));
+        cache.put("did:example:1", &Resolved::default()).await;
+        cache.put("did:example:2", &Resolved::default()).await;
+
+        assert!(cache.get("did:example:1").await.is_none());
+        assert!(cache.get("did:example:2").await.is_some());
+    }
+}
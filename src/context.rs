@@ -0,0 +1,400 @@
+//! # JSON-LD Context
+//!
+//! Loading of JSON-LD context documents and expansion of DID documents for
+//! JSON-LD processing.
+//!
+//! [`StaticContextLoader`] embeds the contexts commonly referenced by DID
+//! documents so that documents can be expanded, and their terms checked,
+//! without network access.
+
+use std::collections::HashMap;
+use std::future::{self, Future};
+
+use anyhow::anyhow;
+use serde_json::{Map, Value, json};
+
+use crate::document::Document;
+use crate::error::Error;
+
+/// Contexts embedded by [`StaticContextLoader`].
+const CONTEXTS: [(&str, &str); 4] = [
+    ("https://www.w3.org/ns/did/v1", include_str!("contexts/did-v1.jsonld")),
+    ("https://w3id.org/security/multikey/v1", include_str!("contexts/multikey-v1.jsonld")),
+    (
+        "https://w3id.org/security/data-integrity/v1",
+        include_str!("contexts/data-integrity-v1.jsonld"),
+    ),
+    ("https://w3id.org/security/jwk/v1", include_str!("contexts/jwk-v1.jsonld")),
+];
+
+/// [`ContextLoader`] is used by implementers to provide JSON-LD context
+/// documents referenced by URL.
+pub trait ContextLoader: Send + Sync {
+    /// Load the context document for the specified URL.
+    fn load(&self, url: &str) -> impl Future<Output = anyhow::Result<Value>> + Send;
+}
+
+/// A [`ContextLoader`] preloaded with the standard DID and security contexts.
+///
+/// Unknown context URLs return an error rather than being fetched.
+#[derive(Clone, Debug, Default)]
+pub struct StaticContextLoader;
+
+impl ContextLoader for StaticContextLoader {
+    fn load(&self, url: &str) -> impl Future<Output = anyhow::Result<Value>> + Send {
+        let context = CONTEXTS.iter().find(|(u, _)| *u == url).map_or_else(
+            || Err(anyhow!("context not available offline: {url}")),
+            |(_, context)| Ok(serde_json::from_str(context)?),
+        );
+        future::ready(context)
+    }
+}
+
+impl Document {
+    /// Expand the document to JSON-LD expanded form using contexts provided
+    /// by the loader.
+    ///
+    /// Supports the subset of JSON-LD expansion used by DID documents: term
+    /// and compact IRI definitions, type coercion, and property- and
+    /// type-scoped contexts.
+    ///
+    /// # Errors
+    ///
+    /// Will fail if a referenced context cannot be loaded.
+    pub async fn expand(&self, loader: &impl ContextLoader) -> crate::Result<Value> {
+        let document = serde_json::to_value(self)
+            .map_err(|e| Error::Other(anyhow!("issue serializing document: {e}")))?;
+
+        // load referenced contexts, including those referenced by other contexts
+        let mut contexts = HashMap::new();
+        let mut pending = context_urls(&document);
+        while let Some(url) = pending.pop() {
+            if contexts.contains_key(&url) {
+                continue;
+            }
+            let context = loader.load(&url).await.map_err(Error::Other)?;
+            pending.extend(context_urls(&context));
+            contexts.insert(url, context);
+        }
+
+        let Value::Object(node) = &document else {
+            return Err(Error::Other(anyhow!("document is not an object")));
+        };
+        let expanded = ActiveContext::default().expand_node(node, &contexts)?;
+
+        Ok(Value::Array(vec![Value::Object(expanded)]))
+    }
+
+    /// Check the types of the document's verification methods and services
+    /// are defined by the document's contexts, using contexts provided by the
+    /// loader. [`StaticContextLoader`] can be used to check documents offline
+    /// against the standard DID and security contexts.
+    ///
+    /// # Errors
+    ///
+    /// Will fail if a referenced context cannot be loaded. Returns an
+    /// [`Error::InvalidInput`] if a type is not defined by any context.
+    pub async fn check_terms(&self, loader: &impl ContextLoader) -> crate::Result<()> {
+        let expanded = self.expand(loader).await?;
+
+        // undefined types are not expanded to an IRI
+        let mut undefined = vec![];
+        undefined_types(&expanded, &mut undefined);
+        if !undefined.is_empty() {
+            return Err(Error::InvalidInput(format!("undefined terms: {}", undefined.join(", "))));
+        }
+        Ok(())
+    }
+}
+
+// Collect the expanded `@type` values that are not IRIs.
+fn undefined_types(value: &Value, undefined: &mut Vec<String>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                if key == "@type"
+                    && let Value::Array(types) = value
+                {
+                    for t in types.iter().filter_map(Value::as_str) {
+                        if !t.contains(':') && !undefined.iter().any(|u| u == t) {
+                            undefined.push(t.to_string());
+                        }
+                    }
+                } else {
+                    undefined_types(value, undefined);
+                }
+            }
+        }
+        Value::Array(items) => items.iter().for_each(|item| undefined_types(item, undefined)),
+        _ => {}
+    }
+}
+
+// Find the context URLs referenced by the value.
+fn context_urls(value: &Value) -> Vec<String> {
+    let mut urls = vec![];
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                if key == "@context" {
+                    match value {
+                        Value::String(url) => urls.push(url.clone()),
+                        Value::Array(items) => {
+                            urls.extend(items.iter().filter_map(Value::as_str).map(String::from));
+                        }
+                        _ => {}
+                    }
+                }
+                urls.extend(context_urls(value));
+            }
+        }
+        Value::Array(items) => urls.extend(items.iter().flat_map(context_urls)),
+        _ => {}
+    }
+    urls
+}
+
+#[derive(Clone, Debug, Default)]
+struct ActiveContext {
+    terms: HashMap<String, Term>,
+}
+
+#[derive(Clone, Debug)]
+struct Term {
+    id: String,
+    type_: Option<String>,
+    context: Option<Value>,
+}
+
+impl ActiveContext {
+    // Process a local context, returning the resulting active context.
+    fn apply(&self, local: &Value, contexts: &HashMap<String, Value>) -> crate::Result<Self> {
+        match local {
+            Value::Null => Ok(Self::default()),
+            Value::String(url) => {
+                let Some(context) = contexts.get(url).and_then(|c| c.get("@context")) else {
+                    return Err(Error::Other(anyhow!(
+                        "context document for {url} has no @context"
+                    )));
+                };
+                self.apply(context, contexts)
+            }
+            Value::Array(items) => {
+                items.iter().try_fold(self.clone(), |active, item| active.apply(item, contexts))
+            }
+            Value::Object(definitions) => {
+                let mut active = self.clone();
+                for (key, definition) in definitions.iter().filter(|(k, _)| !k.starts_with('@')) {
+                    let term = match definition {
+                        Value::Null => {
+                            active.terms.remove(key);
+                            continue;
+                        }
+                        Value::String(id) => Term {
+                            id: active.expand_iri(id).unwrap_or_else(|| id.clone()),
+                            type_: None,
+                            context: None,
+                        },
+                        Value::Object(def) => {
+                            let id = def.get("@id").and_then(Value::as_str).unwrap_or(key);
+                            Term {
+                                id: active.expand_iri(id).unwrap_or_else(|| id.to_string()),
+                                type_: def
+                                    .get("@type")
+                                    .and_then(Value::as_str)
+                                    .map(|t| active.expand_iri(t).unwrap_or_else(|| t.to_string())),
+                                context: def.get("@context").cloned(),
+                            }
+                        }
+                        _ => return Err(Error::Other(anyhow!("invalid term definition: {key}"))),
+                    };
+                    active.terms.insert(key.clone(), term);
+                }
+                Ok(active)
+            }
+            _ => Err(Error::Other(anyhow!("invalid local context"))),
+        }
+    }
+
+    // Expand a term, compact IRI, or keyword to an IRI. Returns `None` for
+    // undefined terms.
+    fn expand_iri(&self, value: &str) -> Option<String> {
+        if value.starts_with('@') {
+            return Some(value.to_string());
+        }
+        if let Some(term) = self.terms.get(value) {
+            return Some(term.id.clone());
+        }
+        let (prefix, suffix) = value.split_once(':')?;
+        if let Some(term) = self.terms.get(prefix)
+            && !suffix.starts_with("//")
+        {
+            return Some(format!("{}{suffix}", term.id));
+        }
+        Some(value.to_string())
+    }
+
+    fn expand_node(
+        &self, node: &Map<String, Value>, contexts: &HashMap<String, Value>,
+    ) -> crate::Result<Map<String, Value>> {
+        let active = match node.get("@context") {
+            Some(local) => self.apply(local, contexts)?,
+            None => self.clone(),
+        };
+
+        // type-scoped contexts apply to the node's own properties only
+        let mut types = node
+            .iter()
+            .find(|(k, _)| active.expand_iri(k).as_deref() == Some("@type"))
+            .map_or_else(Vec::new, |(_, v)| match v {
+                Value::Array(items) => items.iter().filter_map(Value::as_str).collect(),
+                v => v.as_str().into_iter().collect(),
+            });
+        types.sort_unstable();
+
+        let mut scoped = active.clone();
+        for t in &types {
+            if let Some(context) = active.terms.get(*t).and_then(|term| term.context.as_ref()) {
+                scoped = scoped.apply(context, contexts)?;
+            }
+        }
+
+        let mut expanded = Map::new();
+        for (key, value) in node {
+            let Some(iri) = scoped.expand_iri(key) else {
+                continue;
+            };
+            match iri.as_str() {
+                "@context" => {}
+                "@id" => {
+                    expanded.insert(iri, value.clone());
+                }
+                "@type" => {
+                    let types = types
+                        .iter()
+                        .map(|t| Value::String(active.expand_iri(t).unwrap_or_else(|| (*t).into())))
+                        .collect();
+                    expanded.insert(iri, Value::Array(types));
+                }
+                _ if iri.starts_with('@') => {}
+                _ => {
+                    // property-scoped contexts apply to the value
+                    let term = scoped.terms.get(key);
+                    let value_context = match term.and_then(|t| t.context.as_ref()) {
+                        Some(context) => active.apply(context, contexts)?,
+                        None => active.clone(),
+                    };
+                    let coercion = term.and_then(|t| t.type_.as_deref());
+
+                    let entries = match value {
+                        Value::Array(items) => items.iter().collect(),
+                        value => vec![value],
+                    };
+                    let mut items = vec![];
+                    for value in entries {
+                        items.push(value_context.expand_value(coercion, value, contexts)?);
+                    }
+
+                    if let Some(Value::Array(existing)) = expanded.get_mut(&iri) {
+                        existing.extend(items);
+                    } else {
+                        expanded.insert(iri, Value::Array(items));
+                    }
+                }
+            }
+        }
+
+        Ok(expanded)
+    }
+
+    fn expand_value(
+        &self, type_: Option<&str>, value: &Value, contexts: &HashMap<String, Value>,
+    ) -> crate::Result<Value> {
+        let expanded = match (type_, value) {
+            (Some("@json"), value) => json!({"@value": value, "@type": "@json"}),
+            (_, Value::Object(node)) => Value::Object(self.expand_node(node, contexts)?),
+            (Some("@id"), Value::String(id)) => json!({"@id": id}),
+            (Some("@vocab"), Value::String(id)) => {
+                json!({"@id": self.expand_iri(id).unwrap_or_else(|| id.clone())})
+            }
+            (Some(type_), value) => json!({"@value": value, "@type": type_}),
+            (None, value) => json!({"@value": value}),
+        };
+        Ok(expanded)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::core::{Kind, Quota};
+    use crate::document::{Context, CreateOptions, Service, jwk_to_multikey};
+    use crate::key::DidKey;
+    use crate::testing::MemoryKeyRing;
+
+    #[tokio::test]
+    async fn expand_key() {
+        let keyring = MemoryKeyRing::ed25519();
+        let document = DidKey::create(&keyring, CreateOptions::default()).expect("should create");
+
+        let expanded = document.expand(&StaticContextLoader).await.expect("should expand");
+        let node = &expanded[0];
+        assert_eq!(node["@id"], document.id);
+
+        let vm = &node["https://w3id.org/security#verificationMethod"][0];
+        assert_eq!(vm["@type"], json!(["https://w3id.org/security#Multikey"]));
+        assert_eq!(vm["https://w3id.org/security#controller"], json!([{"@id": document.id}]));
+        let multikey = jwk_to_multikey(&keyring.public_key()).expect("should encode");
+        assert_eq!(
+            vm["https://w3id.org/security#publicKeyMultibase"],
+            json!([{"@value": multikey, "@type": "https://w3id.org/security#multibase"}])
+        );
+
+        let authentication = &node["https://w3id.org/security#authenticationMethod"];
+        assert_eq!(authentication, &json!([{"@id": vm["@id"]}]));
+    }
+
+    #[tokio::test]
+    async fn check_terms() {
+        let keyring = MemoryKeyRing::ed25519();
+        let document = DidKey::create(&keyring, CreateOptions::default()).expect("should create");
+        document.check_terms(&StaticContextLoader).await.expect("should define terms");
+
+        // the DID context does not define service types
+        let mut document = Document {
+            context: vec![Context::from("https://www.w3.org/ns/did/v1")],
+            id: "did:example:123".into(),
+            service: Some(vec![Service {
+                id: "did:example:123#domain".into(),
+                type_: "LinkedDomains".into(),
+                service_endpoint: Quota::One(Kind::String("https://example.com".into())),
+            }]),
+            ..Document::default()
+        };
+        let Err(Error::InvalidInput(msg)) = document.check_terms(&StaticContextLoader).await else {
+            panic!("should not define LinkedDomains");
+        };
+        assert_eq!(msg, "undefined terms: LinkedDomains");
+
+        // an inline context can define the term
+        let definition = json!({
+            "LinkedDomains": "https://identity.foundation/.well-known/resources/did-configuration/#LinkedDomains"
+        });
+        let Value::Object(definition) = definition else {
+            panic!("should be an object");
+        };
+        document.add_context(Context::Inline(definition));
+        document.check_terms(&StaticContextLoader).await.expect("should define terms");
+    }
+
+    #[tokio::test]
+    async fn unknown_context() {
+        let document = Document {
+            context: vec![Context::from("https://example.com/context/v1")],
+            id: "did:example:123".into(),
+            ..Document::default()
+        };
+        let err = document.expand(&StaticContextLoader).await.expect_err("should fail");
+        assert!(err.to_string().contains("https://example.com/context/v1"));
+    }
+}
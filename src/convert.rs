@@ -0,0 +1,188 @@
+//! # DID Method Conversion
+//!
+//! Converts between `did:key` and `did:jwk` DIDs for the same public key, for
+//! bridging systems that only accept one of the methods.
+//!
+//! The key material is decoded and re-encoded without change, so the converted
+//! DID identifies the same key as the original.
+//!
+//! [`parse_did_key`] decodes a `did:key` to its raw key, and
+//! [`did_key_from_jwk`] encodes a JWK as a `did:key`, for callers that do not
+//! need a DID document.
+
+use base64ct::{Base64UrlUnpadded, Encoding};
+
+use crate::did_url::method_specific_id;
+use crate::document::{jwk_to_multikey, multikey_to_jwk};
+use crate::error::Error;
+use crate::multicodec::decode_multikey;
+use crate::{Curve, DidKey, KeyType, PublicKeyJwk};
+
+/// Converts a `did:key` DID to the `did:jwk` DID for the same public key.
+///
+/// # Errors
+///
+/// Returns an error if the DID is not a valid `did:key` or its key cannot be
+/// represented as a JWK.
+pub fn convert_did_key_to_jwk(did: &str) -> crate::Result<String> {
+    let jwk = multikey_to_jwk(DidKey::multikey(did)?)?;
+
+    let serialized = serde_json::to_vec(&jwk)
+        .map_err(|e| Error::InvalidPublicKey(format!("issue serializing key: {e}")))?;
+    Ok(format!("did:jwk:{}", Base64UrlUnpadded::encode_string(&serialized)))
+}
+
+/// Converts a `did:jwk` DID to the `did:key` DID for the same public key.
+///
+/// # Errors
+///
+/// Returns an error if the DID is not a valid `did:jwk` or its key has no
+/// registered multicodec, as is the case for curves `did:key` does not
+/// support.
+pub fn convert_did_jwk_to_key(did: &str) -> crate::Result<String> {
+    let decoded = Base64UrlUnpadded::decode_vec(method_specific_id(did, "jwk")?)
+        .map_err(|e| Error::InvalidDid(format!("issue decoding key: {e}")))?;
+    let jwk: PublicKeyJwk = serde_json::from_slice(&decoded)
+        .map_err(|e| Error::UnsupportedPublicKeyType(format!("unsupported key: {e}")))?;
+
+    did_key_from_jwk(&jwk)
+}
+
+/// Returns the `did:key` DID for a public key, without creating a DID
+/// document.
+///
+/// # Errors
+///
+/// Returns an error if the key cannot be decoded or has no registered
+/// multicodec. Ed25519, X25519, and secp256k1 keys are supported.
+pub fn did_key_from_jwk(jwk: &PublicKeyJwk) -> crate::Result<String> {
+    Ok(format!("did:key:{}", jwk_to_multikey(jwk)?))
+}
+
+/// Parses a `did:key` DID into the key type, curve, and raw public key bytes
+/// of its key, without creating a DID document.
+///
+/// # Errors
+///
+/// Returns an [`Error::InvalidDid`] if the DID is not a valid `did:key`, its
+/// multicodec prefix is not a supported key type, or its key is not the
+/// length of the curve's public keys. Ed25519, X25519, and secp256k1 keys are
+/// supported.
+pub fn parse_did_key(did: &str) -> crate::Result<(KeyType, Curve, Vec<u8>)> {
+    decode_multikey(DidKey::multikey(did)?).map_err(|e| match e {
+        Error::InvalidPublicKeyLength(msg) => Error::InvalidDid(format!("invalid did:key: {msg}")),
+        e => Error::InvalidDid(format!("unsupported did:key: {}", e.message())),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::DidKey;
+
+    #[test]
+    fn round_trip() {
+        let did_key = "did:key:z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK";
+
+        let did_jwk = convert_did_key_to_jwk(did_key).expect("should convert");
+        assert!(did_jwk.starts_with("did:jwk:"));
+        assert_eq!(convert_did_jwk_to_key(&did_jwk).expect("should convert"), did_key);
+
+        // both DIDs identify the same key
+        let key_doc = DidKey::resolve(did_key).expect("should resolve").document;
+        let key_jwk = key_doc.expect("should have document").verification_method.unwrap()[0]
+            .method_type
+            .to_jwk()
+            .expect("should convert");
+        let encoded = did_jwk.strip_prefix("did:jwk:").expect("should have prefix");
+        let decoded = Base64UrlUnpadded::decode_vec(encoded).expect("should decode");
+        let jwk: PublicKeyJwk = serde_json::from_slice(&decoded).expect("should deserialize");
+        assert_eq!(jwk, key_jwk);
+    }
+
+    #[test]
+    fn from_jwk() {
+        for did in [
+            "did:key:z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK",
+            "did:key:zQ3shokFTS3brHcDQrn82RUDfCZESWL1ZdCEJwekUDPQiYBme",
+        ] {
+            let identifier = did.strip_prefix("did:key:").expect("should have prefix");
+            let jwk = multikey_to_jwk(identifier).expect("should convert");
+            assert_eq!(did_key_from_jwk(&jwk).expect("should encode"), did);
+        }
+
+        // secp256k1 keys need a y coordinate
+        let jwk = PublicKeyJwk {
+            kty: KeyType::Ec,
+            crv: Curve::Es256K,
+            x: "JJzPi4qy2rvKSVO9F2-05VWeX2ohswX7STo87MGqqPw".into(),
+            ..PublicKeyJwk::default()
+        };
+        let Err(Error::InvalidPublicKey(_)) = did_key_from_jwk(&jwk) else {
+            panic!("should reject key without y");
+        };
+    }
+
+    #[test]
+    fn parse() {
+        let (key_type, curve, key) =
+            parse_did_key("did:key:z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK")
+                .expect("should parse");
+        assert_eq!((key_type, curve, key.len()), (KeyType::Okp, Curve::Ed25519, 32));
+
+        let (key_type, curve, key) =
+            parse_did_key("did:key:z6LSj72tK8brWgZja8NLRwPigth2T9QRiG1uH9oKZuKjdh9p")
+                .expect("should parse");
+        assert_eq!((key_type, curve, key.len()), (KeyType::Okp, Curve::X25519, 32));
+
+        let (key_type, curve, key) =
+            parse_did_key("did:key:zQ3shokFTS3brHcDQrn82RUDfCZESWL1ZdCEJwekUDPQiYBme")
+                .expect("should parse");
+        assert_eq!((key_type, curve, key.len()), (KeyType::Ec, Curve::Es256K, 33));
+
+        // the parsed key is the DID document's key
+        let did = "did:key:z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK";
+        let (.., key) = parse_did_key(did).expect("should parse");
+        let jwk = convert_did_key_to_jwk(did).expect("should convert");
+        let jwk = jwk.strip_prefix("did:jwk:").expect("should have prefix");
+        let jwk: PublicKeyJwk =
+            serde_json::from_slice(&Base64UrlUnpadded::decode_vec(jwk).expect("should decode"))
+                .expect("should deserialize");
+        assert_eq!(Base64UrlUnpadded::decode_vec(&jwk.x).expect("should decode"), key);
+
+        // P-256 keys are not supported
+        let Err(Error::InvalidDid(msg)) =
+            parse_did_key("did:key:zDnaerDaTF5BXEavCrfRZEk316dpbLsfPDZ3WJ5hRTPFU2169")
+        else {
+            panic!("should not parse a P-256 key");
+        };
+        assert!(msg.starts_with("unsupported did:key"));
+
+        let Err(Error::InvalidDid(_)) = parse_did_key("did:jwk:eyJrdHkiOiJPS1AifQ") else {
+            panic!("should not parse a did:jwk");
+        };
+
+        // truncated keys are invalid
+        let (_, bytes) = multibase::decode("z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK")
+            .expect("should decode");
+        let truncated = multibase::encode(multibase::Base::Base58Btc, &bytes[..bytes.len() - 1]);
+        let Err(Error::InvalidDid(msg)) = parse_did_key(&format!("did:key:{truncated}")) else {
+            panic!("should not parse a truncated key");
+        };
+        assert!(msg.starts_with("invalid did:key"));
+    }
+
+    #[test]
+    fn unsupported_curve() {
+        // P-256 key
+        let jwk = r#"{"kty":"EC","crv":"P-256","x":"acbIQiuMs3i8_uszEjJ2tpTtRM4EU3yz91PH6CdH2V0","y":"_KcyLj9vWMptnmKtm46GqDz8wf74I5LKgrl2GzH3nSE"}"#;
+        let did = format!("did:jwk:{}", Base64UrlUnpadded::encode_string(jwk.as_bytes()));
+        let Err(Error::UnsupportedPublicKeyType(_)) = convert_did_jwk_to_key(&did) else {
+            panic!("should not convert a P-256 key");
+        };
+
+        let Err(Error::InvalidDid(_)) = convert_did_key_to_jwk("did:web:example.com") else {
+            panic!("should not convert a did:web");
+        };
+    }
+}
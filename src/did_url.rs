@@ -0,0 +1,309 @@
+//! # DID URL
+//!
+//! A DID URL is a network location identifier for a specific resource. It can
+//! be used to retrieve things like representations of DID subjects,
+//! verification methods, services, specific parts of a DID document, or other
+//! resources.
+//!
+//! See <https://www.w3.org/TR/did-core/#did-url-syntax>
+
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+use serde_json::{Map, Value};
+
+use crate::error::Error;
+use crate::resolution::Parameters;
+
+/// A parsed DID URL.
+///
+/// For example, `did:web:example.com:u:bob/path?service=files#key-0` parses
+/// to method `web`, method-specific identifier `example.com:u:bob`, path
+/// `/path`, query `service=files`, and fragment `key-0`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DidUrl {
+    /// The DID method name. For example, `key` or `web`.
+    pub method: String,
+
+    /// The method-specific identifier.
+    pub id: String,
+
+    /// The path component, including the leading `/`.
+    pub path: Option<String>,
+
+    /// The query component, excluding the leading `?`. Use
+    /// [`DidUrl::parameters`] to access the parsed DID parameters.
+    pub query: Option<String>,
+
+    /// The fragment component, excluding the leading `#`.
+    pub fragment: Option<String>,
+}
+
+impl DidUrl {
+    /// The DID portion of the DID URL (without path, query, or fragment).
+    #[must_use]
+    pub fn did(&self) -> String {
+        format!("did:{}:{}", self.method, self.id)
+    }
+
+    /// Parse the query component into DID parameters.
+    ///
+    /// # Errors
+    ///
+    /// Will fail if the query contains invalid parameter values.
+    pub fn parameters(&self) -> crate::Result<Option<Parameters>> {
+        let Some(query) = &self.query else {
+            return Ok(None);
+        };
+
+        let mut params = Map::new();
+        for (key, value) in url::form_urlencoded::parse(query.as_bytes()) {
+            params.insert(key.into_owned(), Value::String(value.into_owned()));
+        }
+        let parameters = serde_json::from_value(Value::Object(params))
+            .map_err(|e| Error::InvalidDidUrl(format!("issue parsing query: {e}")))?;
+
+        Ok(Some(parameters))
+    }
+}
+
+impl FromStr for DidUrl {
+    type Err = Error;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        // the DID ends at the first path, query, or fragment delimiter
+        let did_len = s.find(['/', '?', '#']).unwrap_or(s.len());
+        validate_did(&s[..did_len]).map_err(|e| Error::InvalidDidUrl(e.to_string()))?;
+
+        // split off fragment, query, and path (in that order)
+        let rest = &s["did:".len()..];
+        let (rest, fragment) = rest
+            .split_once('#')
+            .map_or((rest, None), |(rest, frag)| (rest, Some(frag.to_string())));
+        let (rest, query) = rest
+            .split_once('?')
+            .map_or((rest, None), |(rest, query)| (rest, Some(query.to_string())));
+        let (did, path) = rest
+            .find('/')
+            .map_or((rest, None), |idx| (&rest[..idx], Some(rest[idx..].to_string())));
+        let Some((method, id)) = did.split_once(':') else {
+            return Err(Error::InvalidDidUrl("DID URL has no method-specific identifier".into()));
+        };
+
+        Ok(Self {
+            method: method.to_string(),
+            id: id.to_string(),
+            path,
+            query,
+            fragment,
+        })
+    }
+}
+
+/// A DID that does not conform to the
+/// [DID Syntax](https://www.w3.org/TR/did-core/#did-syntax), as reported by
+/// [`validate_did`].
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+#[error("{reason} at offset {offset} in '{did}'")]
+pub struct DidSyntaxError {
+    /// The invalid DID.
+    pub did: String,
+
+    /// Why the DID is invalid. For example, `invalid character '!' in
+    /// method-specific identifier`.
+    pub reason: String,
+
+    /// The byte offset of the first invalid character, or of the position
+    /// where a missing component was expected.
+    pub offset: usize,
+}
+
+impl From<DidSyntaxError> for Error {
+    fn from(err: DidSyntaxError) -> Self {
+        Self::InvalidDid(err.to_string())
+    }
+}
+
+/// Validates DID syntax, as defined by
+/// [DID Syntax](https://www.w3.org/TR/did-core/#did-syntax).
+///
+/// # Errors
+///
+/// Returns a [`DidSyntaxError`] locating the problem if the DID does not
+/// start with `did:`, the method name is missing or invalid, or the
+/// method-specific identifier is empty or contains an invalid character.
+pub fn validate_did(did: &str) -> Result<(), DidSyntaxError> {
+    let invalid = |reason: &str, offset: usize| {
+        Err(DidSyntaxError {
+            did: did.to_string(),
+            reason: reason.to_string(),
+            offset,
+        })
+    };
+    let Some(rest) = did.strip_prefix("did:") else {
+        return invalid("DID must start with 'did:'", 0);
+    };
+    let method_start = "did:".len();
+
+    // method-name = 1*method-char
+    let Some(method_len) = rest.find(':') else {
+        return invalid("missing method-specific identifier", did.len());
+    };
+    if method_len == 0 {
+        return invalid("missing method name", method_start);
+    }
+    if let Some((idx, c)) = rest[..method_len]
+        .char_indices()
+        .find(|(_, c)| !(c.is_ascii_lowercase() || c.is_ascii_digit()))
+    {
+        return invalid(&format!("invalid character '{c}' in method name"), method_start + idx);
+    }
+
+    // method-specific-id = *( *idchar ":" ) 1*idchar
+    let id_start = method_start + method_len + 1;
+    let id = &did[id_start..];
+    if id.is_empty() {
+        return invalid("empty method-specific identifier", id_start);
+    }
+    if id.ends_with(':') {
+        return invalid("method-specific identifier must not end with ':'", did.len() - 1);
+    }
+    let mut chars = id.char_indices();
+    while let Some((idx, c)) = chars.next() {
+        if c == '%' {
+            // pct-encoded = "%" HEXDIG HEXDIG
+            let hex = chars.by_ref().take(2).filter(|(_, h)| h.is_ascii_hexdigit()).count();
+            if hex != 2 {
+                return invalid("invalid percent-encoding", id_start + idx);
+            }
+        } else if !(c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | ':')) {
+            return invalid(
+                &format!("invalid character '{c}' in method-specific identifier"),
+                id_start + idx,
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the method-specific identifier of a DID using the method. For
+/// example, `example.com` for `did:web:example.com` and method `web`.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidDid`] if the DID is not valid, as checked by
+/// [`validate_did`], or uses a different method.
+pub fn method_specific_id<'a>(did: &'a str, method: &str) -> crate::Result<&'a str> {
+    validate_did(did)?;
+    let id = did.strip_prefix("did:").and_then(|rest| rest.strip_prefix(method));
+    let Some(id) = id.and_then(|rest| rest.strip_prefix(':')) else {
+        return Err(Error::InvalidDid(format!("{did} is not a did:{method} DID")));
+    };
+    Ok(id)
+}
+
+impl Display for DidUrl {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.did())?;
+        if let Some(path) = &self.path {
+            write!(f, "{path}")?;
+        }
+        if let Some(query) = &self.query {
+            write!(f, "?{query}")?;
+        }
+        if let Some(fragment) = &self.fragment {
+            write!(f, "#{fragment}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn web_path() {
+        let url: DidUrl = "did:web:example.com:u:bob".parse().expect("should parse");
+        assert_eq!(url.method, "web");
+        assert_eq!(url.id, "example.com:u:bob");
+        assert_eq!(url.path, None);
+        assert_eq!(url.query, None);
+        assert_eq!(url.fragment, None);
+        assert_eq!(url.to_string(), "did:web:example.com:u:bob");
+    }
+
+    #[test]
+    fn query() {
+        let did_url = "did:example:123/path?service=files&relativeRef=%2Fresume.pdf";
+        let url: DidUrl = did_url.parse().expect("should parse");
+        assert_eq!(url.did(), "did:example:123");
+        assert_eq!(url.path.as_deref(), Some("/path"));
+        assert_eq!(url.query.as_deref(), Some("service=files&relativeRef=%2Fresume.pdf"));
+
+        let params = url.parameters().expect("should parse").expect("should have parameters");
+        assert_eq!(params.service.as_deref(), Some("files"));
+        assert_eq!(params.relative_ref.as_deref(), Some("/resume.pdf"));
+        assert_eq!(url.to_string(), did_url);
+    }
+
+    #[test]
+    fn fragment() {
+        let did_url = "did:key:z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK#z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK";
+        let url: DidUrl = did_url.parse().expect("should parse");
+        assert_eq!(url.method, "key");
+        assert_eq!(url.fragment.as_deref(), Some(url.id.as_str()));
+        assert_eq!(url.to_string(), did_url);
+    }
+
+    #[test]
+    fn invalid() {
+        assert!("web:example.com".parse::<DidUrl>().is_err());
+        assert!("did:web".parse::<DidUrl>().is_err());
+        assert!("did::example.com".parse::<DidUrl>().is_err());
+        assert!("did:Web:example.com".parse::<DidUrl>().is_err());
+        assert!("did:web:example.com:".parse::<DidUrl>().is_err());
+        assert!("did:web:exa mple.com".parse::<DidUrl>().is_err());
+    }
+
+    #[test]
+    fn validate() {
+        validate_did("did:web:example.com%3A8080:u:bob").expect("should be valid");
+
+        let invalid = |did: &str| {
+            let err = validate_did(did).expect_err("should be invalid");
+            assert_eq!(err.did, did);
+            (err.reason, err.offset)
+        };
+
+        assert_eq!(invalid("web:example.com"), ("DID must start with 'did:'".into(), 0));
+        assert_eq!(invalid("did::example.com"), ("missing method name".into(), 4));
+        assert_eq!(invalid("did:web"), ("missing method-specific identifier".into(), 7));
+        assert_eq!(
+            invalid("did:wEb:example.com"),
+            ("invalid character 'E' in method name".into(), 5)
+        );
+        assert_eq!(
+            invalid("did:web:example!.com"),
+            ("invalid character '!' in method-specific identifier".into(), 15)
+        );
+        assert_eq!(invalid("did:web:"), ("empty method-specific identifier".into(), 8));
+        assert_eq!(invalid("did:web:example.com%3"), ("invalid percent-encoding".into(), 19));
+
+        let err = validate_did("did:web:example!.com").expect_err("should be invalid");
+        assert_eq!(
+            err.to_string(),
+            "invalid character '!' in method-specific identifier at offset 15 in 'did:web:example!.com'"
+        );
+
+        // and convert to a DID resolution error
+        let Error::InvalidDid(_) = Error::from(err) else {
+            panic!("should be an invalid DID error");
+        };
+
+        // DID URLs report the same position
+        let err = "did:web:exa mple.com#key-0".parse::<DidUrl>().expect_err("should be invalid");
+        assert!(err.message().contains("offset 11"));
+    }
+}
@@ -6,21 +6,36 @@
 use std::collections::HashMap;
 use std::fmt::{self, Display, Formatter};
 
+use base64ct::{Base64UrlUnpadded, Encoding};
 use chrono::{DateTime, Utc};
 use credibil_infosec::jose::jwk::PublicKeyJwk;
-use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use credibil_infosec::{Curve, KeyType};
+use k256::elliptic_curve::sec1::ToEncodedPoint;
+use serde::de::{self, DeserializeOwned};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use url::Url;
 
 use crate::core::{Kind, Quota};
+use crate::did_url::DidUrl;
 use crate::error::Error;
+use crate::multicodec::HashAlgorithm;
+use crate::proof::{Proof, verify_signature};
+use crate::resolution::ContentType;
+use crate::{KeyPurpose, multicodec};
+
+/// The DID core context.
+const DID_CONTEXT: &str = "https://www.w3.org/ns/did/v1";
 
 /// DID Document
 #[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 pub struct Document {
-    /// The context of the DID document.
-    #[serde(rename = "@context")]
-    pub context: Vec<Kind<Value>>,
+    /// The context of the DID document. Empty for the `application/did+json`
+    /// representation.
+    #[serde(rename = "@context", default, skip_serializing_if = "Vec::is_empty")]
+    pub context: Vec<Context>,
 
     /// The DID for a particular DID subject.
     ///
@@ -109,241 +124,2195 @@ pub struct Document {
     /// <https://w3c.github.io/did-core/#dfn-diddocumentmetadata>
     #[serde(skip_serializing_if = "Option::is_none")]
     pub did_document_metadata: Option<DocumentMetadata>,
-}
-
-/// Services are used to express ways of communicating with the DID subject or
-/// associated entities. They can be any type of service the DID subject wants
-/// to advertise, including decentralized identity management services for
-/// further discovery, authentication, authorization, or interaction.
-///
-/// Service information is often service specific. For example, a reference to
-/// an encrypted messaging service can detail how to initiate the encrypted link
-/// before messaging begins.
-///
-/// Due to privacy concerns, revealing public information through services, such
-/// as social media accounts, personal websites, and email addresses, is
-/// discouraged.
-#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
-#[serde(rename_all = "camelCase")]
-pub struct Service {
-    /// A URI unique to the service.
-    pub id: String,
-
-    /// The service type. SHOULD be registered in the DID Specification
-    /// Registries.
-    #[serde(rename = "type")]
-    pub type_: String,
 
-    /// One or more endpoints for the service.
-    #[allow(clippy::struct_field_names)]
-    pub service_endpoint: Quota<Kind<Value>>,
+    /// A Data Integrity proof securing the document. See
+    /// [`Document::add_proof`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proof: Option<Proof>,
 }
 
-/// A DID document can express verification methods, such as cryptographic
-/// public keys, which can be used to authenticate or authorize interactions
-/// with the DID subject or associated parties. For example, a cryptographic
-/// public key can be used as a verification method with respect to a digital
-/// signature; in such usage, it verifies that the signer could use the
-/// associated cryptographic private key. Verification methods might take many
-/// parameters. An example of this is a set of five cryptographic keys from
-/// which any three are required to contribute to a cryptographic threshold
-/// signature.
-///
-/// MAY include additional properties which can be determined from the
-/// verification method as registered in the
-/// [DID Specification Registries](https://www.w3.org/TR/did-spec-registries/).
-#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
-#[serde(rename_all = "camelCase")]
-pub struct VerificationMethod {
-    /// Only used when the verification method uses terms not defined in the
-    /// containing document.
-    #[serde(rename = "@context")]
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub context: Option<Kind<Value>>,
+impl Document {
+    /// Returns a [`DocumentBuilder`] for a document with the specified DID.
+    #[must_use]
+    pub fn builder(id: impl Into<String>) -> DocumentBuilder {
+        DocumentBuilder::new(id)
+    }
 
-    /// A DID that identifies the verification method.
-    pub id: String,
+    /// Returns the distinct DIDs referenced by the document, excluding the
+    /// document's own DID.
+    ///
+    /// DIDs are collected from `controller`, `alsoKnownAs`, verification method
+    /// controllers, and verification relationship references. Any path, query,
+    /// or fragment is removed from referencing DID URLs.
+    #[must_use]
+    pub fn referenced_dids(&self) -> Vec<String> {
+        let mut urls: Vec<&str> = vec![];
 
-    /// The DID of the controller of the verification method.
-    pub controller: String,
+        match &self.controller {
+            Some(Quota::One(controller)) => urls.push(controller),
+            Some(Quota::Many(controllers)) => urls.extend(controllers.iter().map(String::as_str)),
+            None => {}
+        }
+        if let Some(also_known_as) = &self.also_known_as {
+            urls.extend(also_known_as.iter().map(String::as_str));
+        }
+        if let Some(methods) = &self.verification_method {
+            urls.extend(methods.iter().map(|vm| vm.controller.as_str()));
+        }
 
-    /// The verification method type. SHOULD be a registered type (in DID
-    /// Specification Registries).
-    #[serde(flatten)]
-    pub method_type: MethodType,
-}
+        let relationships = [
+            &self.authentication,
+            &self.assertion_method,
+            &self.key_agreement,
+            &self.capability_invocation,
+            &self.capability_delegation,
+        ];
+        for kind in relationships.into_iter().flatten().flatten() {
+            match kind {
+                Kind::String(id) => urls.push(id),
+                Kind::Object(vm) => urls.extend([vm.id.as_str(), vm.controller.as_str()]),
+            }
+        }
 
-/// Verification method types. SHOULD be registered in the [DID Specification
-/// Registries](https://www.w3.org/TR/did-spec-registries).
-#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
-#[serde(tag = "type")]
-#[serde(rename_all_fields = "camelCase")]
-pub enum MethodType {
-    /// Key is encoded as a Multibase. The Multikey data model is a specific
-    /// type of verification method that encodes key types into a single
-    /// binary stream that is then encoded as a Multibase value.
-    ///
-    /// <https://w3c.github.io/controller-document/#multikey>
-    #[serde(alias = "Ed25519VerificationKey2020")]
-    Multikey {
-        /// The public key encoded as a Multibase.
-        public_key_multibase: String,
-    },
+        let mut dids = vec![];
+        for url in urls {
+            let Ok(did_url) = url.parse::<DidUrl>() else {
+                continue;
+            };
+            let did = did_url.did();
+            if did != self.id && !dids.contains(&did) {
+                dids.push(did);
+            }
+        }
+        dids
+    }
 
-    /// Key is JWK. The JSON Web Key (JWK) data model is a specific type of
-    /// verification method that uses the JWK specification [RFC7517] to encode
-    /// key types into a set of parameters.
+    /// Returns the canonical form of the document's DID, when the DID method
+    /// defines one.
     ///
-    /// <https://w3c.github.io/controller-document/#jsonwebkey>
-    #[serde(alias = "JsonWebKey2020")]
-    #[serde(alias = "EcdsaSecp256k1VerificationKey2019")]
-    JsonWebKey {
-        /// The public key encoded as a JWK.
-        public_key_jwk: PublicKeyJwk,
-    },
-    //
-    // #[serde(alias = "Ed25519VerificationKey2018")]
-    // Base58 { public_key_base58: String },
-}
+    /// A `canonicalId` in the document's metadata takes precedence. For
+    /// `did:web`, the host is case-insensitive, so the canonical form has a
+    /// lowercase host.
+    #[must_use]
+    pub fn canonical_id(&self) -> Option<String> {
+        if let Some(canonical_id) =
+            self.did_document_metadata.as_ref().and_then(|md| md.canonical_id.clone())
+        {
+            return Some(canonical_id);
+        }
+        canonical_did(&self.id)
+    }
 
-impl Default for MethodType {
-    fn default() -> Self {
-        Self::Multikey {
-            public_key_multibase: String::new(),
+    /// Checks the key of each verification method in a verification
+    /// relationship can be used for that relationship. Referenced methods not
+    /// defined in the document are not checked.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a verification method's key cannot be used for
+    /// its relationship.
+    pub fn check_purposes(&self) -> crate::Result<()> {
+        let relationships = [
+            (KeyPurpose::Authentication, &self.authentication),
+            (KeyPurpose::AssertionMethod, &self.assertion_method),
+            (KeyPurpose::KeyAgreement, &self.key_agreement),
+            (KeyPurpose::CapabilityInvocation, &self.capability_invocation),
+            (KeyPurpose::CapabilityDelegation, &self.capability_delegation),
+        ];
+        for (purpose, kinds) in relationships {
+            for kind in kinds.iter().flatten() {
+                let vm = match kind {
+                    Kind::String(id) => {
+                        self.verification_method.iter().flatten().find(|vm| &vm.id == id)
+                    }
+                    Kind::Object(vm) => Some(vm),
+                };
+                if let Some(vm) = vm {
+                    vm.check_purpose(purpose)?;
+                }
+            }
         }
+        Ok(())
     }
-}
 
-impl MethodType {
-    /// Converts a Multibase public key to JWK format.
+    /// Checks no service shares an id with a verification method. Services
+    /// and verification methods share the document's fragment namespace, so
+    /// a shared id would make dereferencing the DID URL ambiguous.
+    ///
+    /// Both the `verificationMethod` set and methods embedded in verification
+    /// relationships are checked, with relative (`#fragment`) ids resolved
+    /// against the document's DID.
     ///
     /// # Errors
-    pub fn jwk(&self) -> crate::Result<PublicKeyJwk> {
-        match self {
-            Self::JsonWebKey { public_key_jwk } => Ok(public_key_jwk.clone()),
-            Self::Multikey { public_key_multibase } => {
-                PublicKeyJwk::from_multibase(public_key_multibase)
-                    .map_err(|e| Error::InvalidPublicKey(e.to_string()))
+    ///
+    /// Returns an error naming the first service whose id is also a
+    /// verification method id.
+    pub fn check_ids(&self) -> crate::Result<()> {
+        let mut method_ids = self
+            .verification_method
+            .iter()
+            .flatten()
+            .map(|vm| self.absolute_id(&vm.id))
+            .collect::<Vec<_>>();
+        for purpose in KeyPurpose::ALL {
+            for kind in self.relationship(purpose) {
+                if let Kind::Object(vm) = kind {
+                    method_ids.push(self.absolute_id(&vm.id));
+                }
+            }
+        }
+
+        for service in self.service.iter().flatten() {
+            let id = self.absolute_id(&service.id);
+            if method_ids.contains(&id) {
+                return Err(Error::InvalidInput(format!(
+                    "service id {id} is also a verification method id"
+                )));
             }
         }
+        Ok(())
     }
-}
 
-/// Verification method types. SHOULD be registered in the [DID Specification
-/// Registries](https://www.w3.org/TR/did-spec-registries).
-#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
-pub enum PublicKeyFormat {
-    /// Key is encoded as a Multibase. The Multikey data model is a specific
-    /// type of verification method that encodes key types into a single
-    /// binary stream that is then encoded as a Multibase value.
+    /// Adds a service, replacing any existing service with the same `id`, so
+    /// adding the same service more than once is safe to retry.
+    pub fn add_service(&mut self, service: Service) {
+        let services = self.service.get_or_insert_default();
+        if let Some(existing) = services.iter_mut().find(|s| s.id == service.id) {
+            *existing = service;
+        } else {
+            services.push(service);
+        }
+    }
+
+    /// Removes all services with any of the specified types.
+    pub fn remove_services_by_type(&mut self, types: &[String]) {
+        if let Some(services) = &mut self.service {
+            services.retain(|service| !types.contains(&service.type_));
+            if services.is_empty() {
+                self.service = None;
+            }
+        }
+    }
+
+    /// Removes all verification methods with any of the specified method
+    /// types (for example, `JsonWebKey`), including references to them from
+    /// verification relationships.
+    pub fn remove_verification_methods_by_type(&mut self, types: &[String]) {
+        let is_removed = |vm: &VerificationMethod| types.iter().any(|t| t == vm.method_type.kind());
+
+        let mut removed = vec![];
+        if let Some(methods) = &mut self.verification_method {
+            removed.extend(methods.iter().filter(|vm| is_removed(vm)).map(|vm| vm.id.clone()));
+            methods.retain(|vm| !is_removed(vm));
+            if methods.is_empty() {
+                self.verification_method = None;
+            }
+        }
+
+        let relationships = [
+            &mut self.authentication,
+            &mut self.assertion_method,
+            &mut self.key_agreement,
+            &mut self.capability_invocation,
+            &mut self.capability_delegation,
+        ];
+        for relationship in relationships {
+            if let Some(kinds) = relationship {
+                kinds.retain(|kind| match kind {
+                    Kind::String(id) => !removed.contains(id),
+                    Kind::Object(vm) => !is_removed(vm),
+                });
+                if kinds.is_empty() {
+                    *relationship = None;
+                }
+            }
+        }
+    }
+
+    /// Returns the verification methods for the verification relationship.
     ///
-    /// <https://w3c.github.io/controller-document/#multikey>
-    #[default]
-    Multikey,
+    /// Referenced methods are dereferenced against the document's
+    /// `verificationMethod` set, with relative (`#fragment`) references
+    /// resolved against the document's DID. References to methods not in the
+    /// document are skipped. For [`KeyPurpose::VerificationMethod`], all of
+    /// the document's verification methods are returned.
+    #[must_use]
+    pub fn verification_methods_for(&self, purpose: KeyPurpose) -> Vec<&VerificationMethod> {
+        let methods = self.verification_method.as_deref().unwrap_or_default();
+        if purpose == KeyPurpose::VerificationMethod {
+            return methods.iter().collect();
+        }
 
-    /// Key is JWK. The JSON Web Key (JWK) data model is a specific type of
-    /// verification method that uses the JWK specification [RFC7517] to encode
-    /// key types into a set of parameters.
+        self.relationship(purpose)
+            .iter()
+            .filter_map(|kind| match kind {
+                Kind::String(id) => {
+                    let id = self.absolute_id(id);
+                    methods.iter().find(|vm| self.absolute_id(&vm.id) == id)
+                }
+                Kind::Object(vm) => Some(vm),
+            })
+            .collect()
+    }
+
+    /// Returns the public key of the verification method identified by a JWS
+    /// `kid`, for verifying a signature.
     ///
-    /// <https://w3c.github.io/controller-document/#jsonwebkey>
-    JsonWebKey,
+    /// The `kid` may be a full DID URL (`did:example:123#key-0`) or a
+    /// fragment (`#key-0`) relative to the document's DID. Both referenced
+    /// and embedded verification methods are searched, and Multikey values
+    /// are converted to a JWK.
+    #[must_use]
+    pub fn public_key_for_kid(&self, kid: &str) -> Option<PublicKeyJwk> {
+        let kid = self.absolute_id(kid);
+        let embedded = [
+            &self.authentication,
+            &self.assertion_method,
+            &self.key_agreement,
+            &self.capability_invocation,
+            &self.capability_delegation,
+        ]
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|kind| match kind {
+            Kind::Object(vm) => Some(vm),
+            Kind::String(_) => None,
+        });
 
-    /// Key is ED2559 Verification Key.
+        let vm = self
+            .verification_method
+            .iter()
+            .flatten()
+            .chain(embedded)
+            .find(|vm| self.absolute_id(&vm.id) == kid)?;
+        vm.method_type.to_jwk().ok()
+    }
+
+    /// Returns the public key of every verification method in the document as
+    /// `(method id, JWK)` pairs, whether the key is encoded as a `Multikey` or
+    /// a `JsonWebKey`.
     ///
-    /// <https://w3id.org/security/suites/ed25519-2020/v1>
-    Ed25519VerificationKey2020,
+    /// Referenced and embedded verification methods are included, in document
+    /// order, once each. Method ids are returned as absolute DID URLs.
+    /// Methods of an unsupported type (see [`MethodType::Unsupported`]) are
+    /// skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a Multikey value cannot be decoded or uses an
+    /// unsupported multicodec prefix.
+    pub fn all_public_keys(&self) -> crate::Result<Vec<(String, PublicKeyJwk)>> {
+        self.all_methods()
+            .into_iter()
+            .filter(|vm| vm.method_type.is_supported())
+            .map(|vm| Ok((self.absolute_id(&vm.id), vm.method_type.to_jwk()?)))
+            .collect()
+    }
 
-    /// X25519 Key Agreement Key, 2020 version
-    X25519KeyAgreementKey2020,
-}
+    /// Verifies a compact-serialized JWS signed by one of the document's
+    /// verification methods, returning the decoded payload.
+    ///
+    /// The signing method is identified by the JWS header's `kid`, as for
+    /// [`Document::public_key_for_kid`]. Ed25519 (`EdDSA`) and secp256k1
+    /// (`ES256K`) signatures are supported.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidInput`] if the JWS is malformed and
+    /// [`Error::InvalidProof`] if the signature is invalid. Also fails if its
+    /// `kid` does not identify a verification method in the document, the
+    /// method's key is an X25519 key, or its `alg` does not match the method's
+    /// key.
+    pub fn verify_jws(&self, jws: &str) -> crate::Result<Vec<u8>> {
+        let malformed = |reason: &str| Error::InvalidInput(format!("malformed JWS: {reason}"));
+        let decode =
+            |part: &str| Base64UrlUnpadded::decode_vec(part).map_err(|e| malformed(&e.to_string()));
 
-// TODO: set context based on key format:
-// - Ed25519VerificationKey2020	https://w3id.org/security/suites/ed25519-2020/v1
-// - JsonWebKey2020	https://w3id.org/security/suites/jws-2020/v1
+        let [header, payload, signature] = jws.split('.').collect::<Vec<_>>()[..] else {
+            return Err(malformed("expected three parts"));
+        };
+        let header: Value =
+            serde_json::from_slice(&decode(header)?).map_err(|e| malformed(&e.to_string()))?;
+        let Some(kid) = header.get("kid").and_then(Value::as_str) else {
+            return Err(malformed("no kid in header"));
+        };
+        let Some(jwk) = self.public_key_for_kid(kid) else {
+            return Err(Error::NotFound(format!("verification method {kid} not found")));
+        };
 
-impl Display for PublicKeyFormat {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        match self {
-            Self::Multikey => write!(f, "Multikey"),
-            Self::Ed25519VerificationKey2020 => write!(f, "Ed25519VerificationKey2020"),
-            Self::X25519KeyAgreementKey2020 => write!(f, "X25519KeyAgreementKey2020"),
-            Self::JsonWebKey => write!(f, "JsonWebKey"),
+        let alg = header.get("alg").and_then(Value::as_str).unwrap_or_default();
+        let expected = match jwk.crv {
+            Curve::Ed25519 => "EdDSA",
+            Curve::Es256K => "ES256K",
+            Curve::X25519 => {
+                return Err(Error::InvalidPublicKey(format!(
+                    "{kid} is an X25519 key agreement key and cannot verify signatures"
+                )));
+            }
+        };
+        if alg != expected {
+            return Err(Error::InvalidPublicKey(format!(
+                "JWS algorithm '{alg}' cannot be verified with {kid}"
+            )));
+        }
+
+        let signing_input = &jws[..jws.len() - signature.len() - 1];
+        if !verify_signature(&jwk, signing_input.as_bytes(), &decode(signature)?)? {
+            return Err(Error::InvalidProof("JWS signature is invalid".into()));
         }
+        decode(payload)
     }
-}
 
-/// DID document metadata. This typically does not change unless the DID
-/// document changes.
-#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
-#[serde(rename_all = "camelCase")]
-#[allow(clippy::module_name_repetitions)]
-pub struct DocumentMetadata {
-    /// Timestamp of the Create operation.
-    /// An XMLSCHEMA11-2 (RFC3339) e.g. 2010-01-01T19:23:24Z.
-    created: DateTime<Utc>,
+    // The verification methods in a verification relationship. Empty for
+    // `KeyPurpose::VerificationMethod`, which is not a relationship.
+    fn relationship(&self, purpose: KeyPurpose) -> &[Kind<VerificationMethod>] {
+        let kinds = match purpose {
+            KeyPurpose::VerificationMethod => return &[],
+            KeyPurpose::Authentication => &self.authentication,
+            KeyPurpose::AssertionMethod => &self.assertion_method,
+            KeyPurpose::KeyAgreement => &self.key_agreement,
+            KeyPurpose::CapabilityInvocation => &self.capability_invocation,
+            KeyPurpose::CapabilityDelegation => &self.capability_delegation,
+        };
+        kinds.as_deref().unwrap_or_default()
+    }
 
-    /// Timestamp of the last Update operation. Omitted if an Update operation
-    /// has never been performed. May be the same value as the `created`
-    /// property when the difference between the two timestamps is less than
-    /// one second. An XMLSCHEMA11-2 (RFC3339) e.g. 2010-01-01T19:23:24Z.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    updated: Option<DateTime<Utc>>,
+    // Resolve a relative (`#fragment`) DID URL against the document's DID.
+    fn absolute_id(&self, id: &str) -> String {
+        absolute_id(&self.id, id)
+    }
 
-    /// MUST be set to true if the DID has been deactivated. Optional if the DID
-    /// has not been deactivated.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    deactivated: Option<bool>,
+    /// Returns the verification relationships that reference or embed the
+    /// verification method.
+    ///
+    /// Relative (`#fragment`) ids, whether in `id` or in the document, are
+    /// resolved against the document's DID, so a method matches however it
+    /// is referenced.
+    #[must_use]
+    pub fn key_purposes(&self, id: &str) -> Vec<KeyPurpose> {
+        let id = self.absolute_id(id);
+        let relationships = [
+            (KeyPurpose::Authentication, &self.authentication),
+            (KeyPurpose::AssertionMethod, &self.assertion_method),
+            (KeyPurpose::KeyAgreement, &self.key_agreement),
+            (KeyPurpose::CapabilityInvocation, &self.capability_invocation),
+            (KeyPurpose::CapabilityDelegation, &self.capability_delegation),
+        ];
+        relationships
+            .into_iter()
+            .filter(|(_, kinds)| {
+                kinds.iter().flatten().any(|kind| match kind {
+                    Kind::String(ref_id) => self.absolute_id(ref_id) == id,
+                    Kind::Object(vm) => self.absolute_id(&vm.id) == id,
+                })
+            })
+            .map(|(purpose, _)| purpose)
+            .collect()
+    }
 
-    /// May be set if the document version is not the latest. Indicates the
-    /// timestamp of the next Update operation as an XMLSCHEMA11-2
-    /// (RFC3339).
-    next_update: Option<DateTime<Utc>>,
+    /// Replaces a verification method with a new one, giving the new method
+    /// the same verification relationships as the one it replaces.
+    ///
+    /// Referenced methods are replaced in `verificationMethod` and references
+    /// updated to the new method's id. Embedded methods are replaced in place.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the method to replace is not in the document, or
+    /// the new method's key cannot be used for the old method's relationships.
+    pub fn rotate_key(&mut self, old_id: &str, new_vm: &VerificationMethod) -> crate::Result<()> {
+        let purposes = self.key_purposes(old_id);
+        let did = self.id.clone();
+        let old_id = &absolute_id(&did, old_id);
+        let is_old = |id: &str| &absolute_id(&did, id) == old_id;
 
-    /// Used to indicate the version of the last Update operation. SHOULD be
-    /// set.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    version_id: Option<String>,
+        let referenced = self.verification_method.iter().flatten().any(|vm| is_old(&vm.id));
+        if !referenced && purposes.is_empty() {
+            return Err(Error::NotFound(format!("verification method {old_id} not found")));
+        }
+        for purpose in &purposes {
+            new_vm.check_purpose(*purpose)?;
+        }
 
-    /// MAY be set if the document version is not the latest. It indicates the
-    /// version of the next Update operation.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    next_version_id: Option<String>,
+        if let Some(vm) = self.verification_method.iter_mut().flatten().find(|vm| is_old(&vm.id)) {
+            vm.clone_from(new_vm);
+        }
 
-    /// Used when a DID method needs to define different forms of a DID that are
-    /// logically equivalent. For example, when a DID takes one form prior to
-    /// registration in a verifiable data registry and another form after such
-    /// registration.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub equivalent_id: Option<Vec<String>>,
+        let relationships = [
+            &mut self.authentication,
+            &mut self.assertion_method,
+            &mut self.key_agreement,
+            &mut self.capability_invocation,
+            &mut self.capability_delegation,
+        ];
+        for kind in relationships.into_iter().flatten().flatten() {
+            match kind {
+                Kind::String(id) if is_old(id) => id.clone_from(&new_vm.id),
+                Kind::Object(vm) if is_old(&vm.id) => vm.clone_from(new_vm),
+                _ => {}
+            }
+        }
 
-    /// Identical to the `equivalent_id` property except that it is a single
-    /// value AND the DID is the canonical ID for the DID subject within the
-    /// containing DID document.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub canonical_id: Option<String>,
-}
+        Ok(())
+    }
 
-/// Options that can be provided when creating a DID document.
-#[derive(Clone, Debug, Deserialize, Serialize)]
-#[serde(rename_all = "camelCase")]
-pub struct CreateOptions {
-    /// Format to use for the the public key.
-    pub public_key_format: PublicKeyFormat,
+    /// Applies a series of changes to the document as a single update. The
+    /// changes are made to a copy of the document, which replaces the
+    /// document only if every change succeeds, so a failed update leaves the
+    /// document untouched.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error returned by `update`.
+    pub fn try_update(
+        &mut self, update: impl FnOnce(&mut Self) -> crate::Result<()>,
+    ) -> crate::Result<()> {
+        let mut updated = self.clone();
+        update(&mut updated)?;
+        *self = updated;
+        Ok(())
+    }
 
-    /// Default context for the DID document. SHOULD be set to
-    /// `"https://www.w3.org/ns/did/v1"`.
-    pub default_context: String,
+    /// Sets the verification relationships of a verification method to
+    /// exactly `purposes`, granting and revoking relationships without
+    /// replacing the method or its key material.
+    ///
+    /// The method is referenced from newly granted relationships. A method
+    /// only embedded in relationships is moved to `verificationMethod` so it
+    /// remains in the document when those relationships are revoked.
+    /// [`KeyPurpose::VerificationMethod`] is ignored as the method is always
+    /// kept in `verificationMethod`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the method is not in the document, or its key
+    /// cannot be used for one of the purposes.
+    pub fn set_key_purposes(&mut self, id: &str, purposes: &[KeyPurpose]) -> crate::Result<()> {
+        let did = self.id.clone();
+        let id = absolute_id(&did, id);
+        let is_key = |vm_id: &str| absolute_id(&did, vm_id) == id;
+        let is_member = |kind: &Kind<VerificationMethod>| match kind {
+            Kind::String(ref_id) => is_key(ref_id),
+            Kind::Object(vm) => is_key(&vm.id),
+        };
 
-    /// Enable experimental public key types. SHOULD be set to "false".
-    pub enable_experimental_public_key_types: bool,
+        let referenced = self.verification_method.iter().flatten().find(|vm| is_key(&vm.id));
+        let embedded = || {
+            KeyPurpose::ALL.into_iter().flat_map(|p| self.relationship(p)).find_map(|kind| {
+                match kind {
+                    Kind::Object(vm) if is_key(&vm.id) => Some(vm),
+                    _ => None,
+                }
+            })
+        };
+        let Some(vm) = referenced.or_else(embedded).cloned() else {
+            return Err(Error::NotFound(format!("verification method {id} not found")));
+        };
+        for purpose in purposes {
+            vm.check_purpose(*purpose)?;
+        }
 
-    /// Will add a `keyAgreement` object to the DID document.
-    pub enable_encryption_key_derivation: bool,
+        if !self.verification_method.iter().flatten().any(|m| is_key(&m.id)) {
+            self.verification_method.get_or_insert_default().push(vm.clone());
+        }
 
-    // service_endpoints: Vec<Value>,
-    // verification_methods: Vec<Value>,
-    // authentication: Vec<Value>,
-    /// Additional options.
+        let relationships = [
+            (KeyPurpose::Authentication, &mut self.authentication),
+            (KeyPurpose::AssertionMethod, &mut self.assertion_method),
+            (KeyPurpose::KeyAgreement, &mut self.key_agreement),
+            (KeyPurpose::CapabilityInvocation, &mut self.capability_invocation),
+            (KeyPurpose::CapabilityDelegation, &mut self.capability_delegation),
+        ];
+        for (purpose, kinds) in relationships {
+            let mut members = kinds.take().unwrap_or_default();
+            if !purposes.contains(&purpose) {
+                members.retain(|kind| !is_member(kind));
+            } else if !members.iter().any(is_member) {
+                members.push(Kind::String(vm.id.clone()));
+            }
+            *kinds = (!members.is_empty()).then_some(members);
+        }
+
+        Ok(())
+    }
+
+    /// Replaces references in verification relationships with embedded copies
+    /// of the referenced verification methods.
+    ///
+    /// Embedded methods are removed from `verificationMethod`, while methods
+    /// not used in any relationship are kept. References to methods not in
+    /// the document are left unchanged.
+    pub fn embed_relationships(&mut self) {
+        let did = self.id.clone();
+        let absolute = |id: &str| absolute_id(&did, id);
+        let mut methods = self.verification_method.take().unwrap_or_default();
+        let mut embedded = vec![];
+
+        let relationships = [
+            &mut self.authentication,
+            &mut self.assertion_method,
+            &mut self.key_agreement,
+            &mut self.capability_invocation,
+            &mut self.capability_delegation,
+        ];
+        for kind in relationships.into_iter().flatten().flatten() {
+            let Kind::String(id) = kind else {
+                continue;
+            };
+            let id = absolute(id);
+            if let Some(vm) = methods.iter().find(|vm| absolute(&vm.id) == id) {
+                embedded.push(vm.id.clone());
+                *kind = Kind::Object(vm.clone());
+            }
+        }
+
+        methods.retain(|vm| !embedded.contains(&vm.id));
+        self.verification_method = (!methods.is_empty()).then_some(methods);
+    }
+
+    /// Replaces verification methods embedded in verification relationships
+    /// with references, adding the methods to `verificationMethod`.
+    ///
+    /// A method embedded in more than one relationship is added once. When
+    /// `verificationMethod` already has a method with the same id, that method
+    /// is kept.
+    pub fn reference_relationships(&mut self) {
+        let did = self.id.clone();
+        let absolute = |id: &str| absolute_id(&did, id);
+        let mut methods = self.verification_method.take().unwrap_or_default();
+
+        let relationships = [
+            &mut self.authentication,
+            &mut self.assertion_method,
+            &mut self.key_agreement,
+            &mut self.capability_invocation,
+            &mut self.capability_delegation,
+        ];
+        for kind in relationships.into_iter().flatten().flatten() {
+            let Kind::Object(vm) = kind else {
+                continue;
+            };
+            let id = absolute(&vm.id);
+            if !methods.iter().any(|existing| absolute(&existing.id) == id) {
+                methods.push(vm.clone());
+            }
+            *kind = Kind::String(vm.id.clone());
+        }
+
+        self.verification_method = (!methods.is_empty()).then_some(methods);
+    }
+
+    /// Returns the document in the specified representation.
+    ///
+    /// The `application/did+json` representation has no `@context`. The
+    /// `application/did+ld+json` representation always includes the DID core
+    /// context as its first entry, with duplicate entries removed.
+    #[must_use]
+    pub fn to_representation(&self, media_type: &ContentType) -> Self {
+        let mut document = self.clone();
+        match media_type {
+            ContentType::DidJson => document.context.clear(),
+            ContentType::DidLdJson => document.normalize_context(),
+        }
+        document
+    }
+
+    /// Normalizes the document's `@context` so the DID core context is the
+    /// first entry and duplicate entries are removed. The order of other
+    /// entries is preserved.
+    pub fn normalize_context(&mut self) {
+        let context = std::mem::replace(&mut self.context, vec![Context::from(DID_CONTEXT)]);
+        for context in context {
+            self.add_context(context);
+        }
+    }
+
+    /// Whether the document's `@context` includes the context URL.
+    #[must_use]
+    pub fn has_context(&self, url: &str) -> bool {
+        self.context.iter().any(|context| context.url() == Some(url))
+    }
+
+    /// Adds an entry to the document's `@context`, unless it is already
+    /// present.
+    pub fn add_context(&mut self, context: impl Into<Context>) {
+        let context = context.into();
+        if !self.context.contains(&context) {
+            self.context.push(context);
+        }
+    }
+
+    /// The entries that appear more than once in the document's `@context`,
+    /// once each. URL entries are equal when their URLs are, and inline
+    /// entries when their definitions are, regardless of member order.
+    #[must_use]
+    pub fn duplicate_contexts(&self) -> Vec<&Context> {
+        let mut duplicates: Vec<&Context> = vec![];
+        for (i, context) in self.context.iter().enumerate() {
+            if self.context[..i].contains(context) && !duplicates.contains(&context) {
+                duplicates.push(context);
+            }
+        }
+        duplicates
+    }
+
+    /// Removes repeated entries from the document's `@context`, keeping the
+    /// first occurrence of each. Unlike [`Document::normalize_context`], the
+    /// order of entries is unchanged and no entries are added.
+    pub fn dedup_contexts(&mut self) {
+        let context = std::mem::take(&mut self.context);
+        for context in context {
+            self.add_context(context);
+        }
+    }
+
+    /// Sorts verification methods, and the entries of each verification
+    /// relationship, by id.
+    ///
+    /// Serialized output (and any signature over it) is then the same
+    /// regardless of the order in which methods were added.
+    pub fn sort_by_id(&mut self) {
+        if let Some(methods) = &mut self.verification_method {
+            methods.sort_by(|a, b| a.id.cmp(&b.id));
+        }
+        let did = self.id.clone();
+        for relationship in [
+            &mut self.authentication,
+            &mut self.assertion_method,
+            &mut self.key_agreement,
+            &mut self.capability_invocation,
+            &mut self.capability_delegation,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            relationship.sort_by_cached_key(|kind| match kind {
+                Kind::String(id) => absolute_id(&did, id),
+                Kind::Object(vm) => absolute_id(&did, &vm.id),
+            });
+        }
+    }
+
+    /// Sorts services by id, with relative (`#fragment`) ids resolved against
+    /// the document's DID.
+    ///
+    /// Services are otherwise kept in the order they were added, so sorting
+    /// keeps a hosted document byte-stable across equivalent updates.
+    pub fn sort_services_by_id(&mut self) {
+        let did = self.id.clone();
+        if let Some(services) = &mut self.service {
+            services.sort_by_cached_key(|service| absolute_id(&did, &service.id));
+        }
+    }
+
+    /// Returns a minimal, logically equivalent form of the document.
+    ///
+    /// Empty sets are removed, duplicate `@context` and controller entries
+    /// are removed, and single-element controller and service endpoint sets
+    /// are reduced to a single value.
+    #[must_use]
+    pub fn compact(&self) -> Self {
+        let mut document = self.clone();
+
+        document.context = distinct(&document.context);
+        document.controller =
+            document.controller.take().and_then(|c| compact_quota(distinct(quota_items(&c))));
+
+        if let Some(services) = &mut document.service {
+            for service in services {
+                let endpoints = quota_items(&service.service_endpoint).to_vec();
+                if let Some(endpoint) = compact_quota(endpoints) {
+                    service.service_endpoint = endpoint;
+                }
+            }
+        }
+
+        document.also_known_as = document.also_known_as.filter(|v| !v.is_empty());
+        document.service = document.service.filter(|v| !v.is_empty());
+        document.verification_method = document.verification_method.filter(|v| !v.is_empty());
+        for relationship in [
+            &mut document.authentication,
+            &mut document.assertion_method,
+            &mut document.key_agreement,
+            &mut document.capability_invocation,
+            &mut document.capability_delegation,
+        ] {
+            *relationship = relationship.take().filter(|v| !v.is_empty());
+        }
+
+        document
+    }
+
+    /// Compares two documents for logical equality.
+    ///
+    /// Verification methods, verification relationships, services, and other
+    /// set-valued properties are compared without regard to ordering, and a
+    /// single value is equal to a one-element array. The order of `@context`
+    /// entries is significant, though repeated entries are ignored. Document
+    /// metadata is not compared.
+    #[must_use]
+    pub fn semantically_eq(&self, other: &Self) -> bool {
+        let services_eq = |a: &Service, b: &Service| {
+            a.id == b.id && a.type_ == b.type_ && set_eq(&a.endpoints(), &b.endpoints())
+        };
+        let services = self.service.as_deref().unwrap_or_default();
+        let other_services = other.service.as_deref().unwrap_or_default();
+        let services_match =
+            services.iter().all(|a| other_services.iter().any(|b| services_eq(a, b)))
+                && other_services.iter().all(|b| services.iter().any(|a| services_eq(a, b)));
+
+        let relationships = [
+            (&self.authentication, &other.authentication),
+            (&self.assertion_method, &other.assertion_method),
+            (&self.key_agreement, &other.key_agreement),
+            (&self.capability_invocation, &other.capability_invocation),
+            (&self.capability_delegation, &other.capability_delegation),
+        ];
+
+        distinct(&self.context) == distinct(&other.context)
+            && self.id == other.id
+            && set_eq(
+                self.controller.as_ref().map_or(&[], quota_items),
+                other.controller.as_ref().map_or(&[], quota_items),
+            )
+            && opt_set_eq(self.also_known_as.as_deref(), other.also_known_as.as_deref())
+            && services_match
+            && opt_set_eq(self.verification_method.as_deref(), other.verification_method.as_deref())
+            && relationships.iter().all(|(a, b)| opt_set_eq(a.as_deref(), b.as_deref()))
+    }
+
+    /// Computes the changes that transform this document into `other`:
+    /// verification methods and services added and removed, and any change
+    /// to `controller` or `alsoKnownAs`.
+    ///
+    /// Methods and services are matched by id, so a method or service whose
+    /// content changed is reported as removed and re-added. Referenced and
+    /// embedded verification methods are both compared.
+    #[must_use]
+    pub fn diff(&self, other: &Self) -> DocumentDiff {
+        let methods = self.all_methods();
+        let other_methods = other.all_methods();
+        let method_eq = |a: &VerificationMethod, b: &VerificationMethod| {
+            let (id, other_id) = (self.absolute_id(&a.id), other.absolute_id(&b.id));
+            id == other_id && a.controller == b.controller && a.method_type == b.method_type
+        };
+
+        let services = self.service.as_deref().unwrap_or_default();
+        let other_services = other.service.as_deref().unwrap_or_default();
+        let service_eq = |a: &Service, b: &Service| {
+            let (id, other_id) = (self.absolute_id(&a.id), other.absolute_id(&b.id));
+            id == other_id && a.type_ == b.type_ && set_eq(&a.endpoints(), &b.endpoints())
+        };
+
+        let controllers = |document: &Self| {
+            document.controller.as_ref().map_or(&[] as &[String], quota_items).to_vec()
+        };
+        let (controller, other_controller) = (controllers(self), controllers(other));
+        let also_known_as = self.also_known_as.as_deref().unwrap_or_default();
+        let other_also_known_as = other.also_known_as.as_deref().unwrap_or_default();
+
+        DocumentDiff {
+            added_methods: other_methods
+                .iter()
+                .filter(|b| !methods.iter().any(|a| method_eq(a, b)))
+                .map(|vm| (*vm).clone())
+                .collect(),
+            removed_methods: methods
+                .iter()
+                .filter(|a| !other_methods.iter().any(|b| method_eq(a, b)))
+                .map(|vm| self.absolute_id(&vm.id))
+                .collect(),
+            added_services: other_services
+                .iter()
+                .filter(|b| !services.iter().any(|a| service_eq(a, b)))
+                .cloned()
+                .collect(),
+            removed_services: services
+                .iter()
+                .filter(|a| !other_services.iter().any(|b| service_eq(a, b)))
+                .map(|service| self.absolute_id(&service.id))
+                .collect(),
+            controller: (!set_eq(&controller, &other_controller)).then_some(other_controller),
+            also_known_as: (!set_eq(also_known_as, other_also_known_as))
+                .then(|| other_also_known_as.to_vec()),
+        }
+    }
+
+    // Referenced and embedded verification methods, once each.
+    fn all_methods(&self) -> Vec<&VerificationMethod> {
+        let mut methods: Vec<&VerificationMethod> = vec![];
+        let embedded =
+            KeyPurpose::ALL.into_iter().flat_map(|p| self.relationship(p)).filter_map(|kind| {
+                match kind {
+                    Kind::Object(vm) => Some(vm),
+                    Kind::String(_) => None,
+                }
+            });
+        for vm in self.verification_method.iter().flatten().chain(embedded) {
+            let id = self.absolute_id(&vm.id);
+            if !methods.iter().any(|m| self.absolute_id(&m.id) == id) {
+                methods.push(vm);
+            }
+        }
+        methods
+    }
+}
+
+/// The changes between two versions of a DID document, as computed by
+/// [`Document::diff`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DocumentDiff {
+    /// Verification methods in the new document that are not in the old.
+    pub added_methods: Vec<VerificationMethod>,
+
+    /// The ids of verification methods in the old document that are not in
+    /// the new.
+    pub removed_methods: Vec<String>,
+
+    /// Services in the new document that are not in the old.
+    pub added_services: Vec<Service>,
+
+    /// The ids of services in the old document that are not in the new.
+    pub removed_services: Vec<String>,
+
+    /// The new document's controllers, when they changed.
+    pub controller: Option<Vec<String>>,
+
+    /// The new document's `alsoKnownAs` identifiers, when they changed.
+    pub also_known_as: Option<Vec<String>>,
+}
+
+impl DocumentDiff {
+    /// Whether the documents are unchanged.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+// The items of a single value or set of values.
+fn quota_items<T>(quota: &Quota<T>) -> &[T] {
+    match quota {
+        Quota::One(item) => std::slice::from_ref(item),
+        Quota::Many(items) => items.as_slice(),
+    }
+}
+
+/// Resolves a relative (`#fragment`) DID URL against a DID. Other DID URLs
+/// are returned unchanged. For example, `#key-0` resolves against
+/// `did:web:example.com` to `did:web:example.com#key-0`.
+#[must_use]
+pub fn absolute_id(did: &str, id: &str) -> String {
+    if id.starts_with('#') { format!("{did}{id}") } else { id.to_string() }
+}
+
+// The distinct items of a list, in order of first occurrence.
+fn distinct<T: Clone + PartialEq>(items: &[T]) -> Vec<T> {
+    let mut distinct = vec![];
+    for item in items {
+        if !distinct.contains(item) {
+            distinct.push(item.clone());
+        }
+    }
+    distinct
+}
+
+// A set of items as a single value when it has one item, or `None` when it
+// is empty.
+fn compact_quota<T>(mut items: Vec<T>) -> Option<Quota<T>> {
+    match items.len() {
+        0 => None,
+        1 => items.pop().map(Quota::One),
+        _ => Some(Quota::Many(items)),
+    }
+}
+
+// Unordered comparison of two sets of values.
+fn set_eq<T: PartialEq>(a: &[T], b: &[T]) -> bool {
+    a.iter().all(|item| b.contains(item)) && b.iter().all(|item| a.contains(item))
+}
+
+// Unordered comparison of two optional sets, where `None` is an empty set.
+fn opt_set_eq<T: PartialEq>(a: Option<&[T]>, b: Option<&[T]>) -> bool {
+    set_eq(a.unwrap_or_default(), b.unwrap_or_default())
+}
+
+/// A builder for constructing a [`Document`].
+///
+/// Verification methods are added along with the verification relationships
+/// they are used for. The document's DID core context is always included.
+#[derive(Clone, Debug, Default)]
+pub struct DocumentBuilder {
+    document: Document,
+    sort_services: bool,
+}
+
+impl DocumentBuilder {
+    /// Create a builder for a document with the specified DID.
+    #[must_use]
+    pub fn new(id: impl Into<String>) -> Self {
+        Self {
+            document: Document {
+                id: id.into(),
+                ..Document::default()
+            },
+            sort_services: false,
+        }
+    }
+
+    /// Add an entry to the document's `@context`.
+    #[must_use]
+    pub fn add_context(mut self, context: impl Into<Context>) -> Self {
+        self.document.context.push(context.into());
+        self
+    }
+
+    /// Add a controller of the DID.
+    #[must_use]
+    pub fn controller(mut self, controller: impl Into<String>) -> Self {
+        let controller = controller.into();
+        self.document.controller = Some(match self.document.controller.take() {
+            None => Quota::One(controller),
+            Some(Quota::One(existing)) => Quota::Many(vec![existing, controller]),
+            Some(Quota::Many(mut existing)) => {
+                existing.push(controller);
+                Quota::Many(existing)
+            }
+        });
+        self
+    }
+
+    /// Add a verification method for the specified verification
+    /// relationships.
+    ///
+    /// When `purposes` includes [`KeyPurpose::VerificationMethod`], the method
+    /// is added to the document's `verificationMethod` set and each
+    /// relationship references it by id. Otherwise, the method is embedded in
+    /// each relationship.
+    #[must_use]
+    pub fn add_verification_method(
+        mut self, vm: VerificationMethod, purposes: &[KeyPurpose],
+    ) -> Self {
+        let shared = purposes.contains(&KeyPurpose::VerificationMethod);
+        for purpose in purposes {
+            let kind = if shared { Kind::String(vm.id.clone()) } else { Kind::Object(vm.clone()) };
+            self.add_kind(*purpose, kind);
+        }
+        if shared {
+            self.document.verification_method.get_or_insert_default().push(vm);
+        }
+        self
+    }
+
+    /// Reference a verification method from the specified verification
+    /// relationships. The method must be added to the document's
+    /// `verificationMethod` set before the document is built.
+    #[must_use]
+    pub fn add_reference(mut self, id: impl Into<String>, purposes: &[KeyPurpose]) -> Self {
+        let id = id.into();
+        for purpose in purposes {
+            self.add_kind(*purpose, Kind::String(id.clone()));
+        }
+        self
+    }
+
+    /// Add a service, replacing any service with the same `id`.
+    #[must_use]
+    pub fn add_service(mut self, service: Service) -> Self {
+        self.document.add_service(service);
+        self
+    }
+
+    /// Sort services by id when the document is built, rather than keeping
+    /// them in the order they were added.
+    #[must_use]
+    pub const fn sort_services(mut self, sort: bool) -> Self {
+        self.sort_services = sort;
+        self
+    }
+
+    /// Build the document. Verification methods and relationship entries are
+    /// sorted by id, so the document does not depend on the order they were
+    /// added. Services are also sorted when [`DocumentBuilder::sort_services`]
+    /// is set.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a verification relationship references a method
+    /// that is not in the document's `verificationMethod` set, a method's
+    /// key cannot be used for its relationship, a service endpoint is not an
+    /// absolute URI, or a service has the same id as a verification method.
+    pub fn build(self) -> crate::Result<Document> {
+        let mut document = self.document;
+        document.normalize_context();
+        document.sort_by_id();
+        if self.sort_services {
+            document.sort_services_by_id();
+        }
+
+        for service in document.service.iter().flatten() {
+            service.validate()?;
+        }
+
+        let methods = document.verification_method.as_deref().unwrap_or_default();
+        for purpose in KeyPurpose::ALL {
+            for kind in document.relationship(purpose) {
+                let Kind::String(id) = kind else {
+                    continue;
+                };
+                let id = document.absolute_id(id);
+                if !methods.iter().any(|vm| document.absolute_id(&vm.id) == id) {
+                    return Err(Error::NotFound(format!(
+                        "{purpose} references unknown verification method {id}"
+                    )));
+                }
+            }
+        }
+        document.check_purposes()?;
+        document.check_ids()?;
+
+        Ok(document)
+    }
+
+    fn add_kind(&mut self, purpose: KeyPurpose, kind: Kind<VerificationMethod>) {
+        let relationship = match purpose {
+            KeyPurpose::VerificationMethod => return,
+            KeyPurpose::Authentication => &mut self.document.authentication,
+            KeyPurpose::AssertionMethod => &mut self.document.assertion_method,
+            KeyPurpose::KeyAgreement => &mut self.document.key_agreement,
+            KeyPurpose::CapabilityInvocation => &mut self.document.capability_invocation,
+            KeyPurpose::CapabilityDelegation => &mut self.document.capability_delegation,
+        };
+        relationship.get_or_insert_default().push(kind);
+    }
+}
+
+/// An entry in a JSON-LD `@context`: either the URL of a published context or
+/// an inline context definition.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum Context {
+    /// The URL of a published context, such as
+    /// `https://www.w3.org/ns/did/v1`.
+    Url(String),
+
+    /// An inline context definition.
+    Inline(Map<String, Value>),
+}
+
+impl Context {
+    /// The context's URL, or `None` for an inline context.
+    #[must_use]
+    pub fn url(&self) -> Option<&str> {
+        match self {
+            Self::Url(url) => Some(url),
+            Self::Inline(_) => None,
+        }
+    }
+}
+
+impl From<&str> for Context {
+    fn from(url: &str) -> Self {
+        Self::Url(url.to_string())
+    }
+}
+
+impl From<String> for Context {
+    fn from(url: String) -> Self {
+        Self::Url(url)
+    }
+}
+
+/// Returns the canonical form of the DID for methods that have equivalent
+/// forms of the same DID.
+pub fn canonical_did(did: &str) -> Option<String> {
+    let url = did.parse::<DidUrl>().ok()?;
+    match url.method.as_str() {
+        "web" => {
+            let (host, path) =
+                url.id.split_once(':').map_or((url.id.as_str(), None), |(h, p)| (h, Some(p)));
+            let host = host.to_ascii_lowercase().replace("%3a", "%3A");
+            Some(path.map_or_else(|| format!("did:web:{host}"), |p| format!("did:web:{host}:{p}")))
+        }
+        _ => None,
+    }
+}
+
+/// Returns a method-specific identifier derived from the content of a genesis
+/// document, for DID methods that derive their identifier from the initial
+/// document or state.
+///
+/// The document is canonicalized using the JSON Canonicalization Scheme
+/// ([RFC 8785](https://www.rfc-editor.org/rfc/rfc8785)) and hashed with
+/// SHA-256. The identifier is the base58btc multibase encoding of the digest's
+/// multihash.
+///
+/// # Errors
+///
+/// Returns an error if the document cannot be serialized.
+pub fn content_hash_id(document: &Document) -> crate::Result<String> {
+    let value = serde_json::to_value(document)
+        .map_err(|e| Error::Other(anyhow::anyhow!("issue serializing document: {e}")))?;
+    Ok(multicodec::multihash(canonical_json(&value).as_bytes(), HashAlgorithm::Sha256))
+}
+
+/// Returns the JWK thumbprint of a public key, as defined by
+/// [RFC 7638](https://www.rfc-editor.org/rfc/rfc7638): the base64url-encoded
+/// SHA-256 digest of the key's required members in canonical form.
+///
+/// # Errors
+///
+/// Returns an error if the key cannot be serialized.
+pub fn jwk_thumbprint(jwk: &PublicKeyJwk) -> crate::Result<String> {
+    let Value::Object(mut members) = serde_json::to_value(jwk)
+        .map_err(|e| Error::InvalidPublicKey(format!("issue serializing key: {e}")))?
+    else {
+        return Err(Error::InvalidPublicKey("key is not a JSON object".into()));
+    };
+    members.retain(|name, _| ["crv", "kty", "x", "y"].contains(&name.as_str()));
+
+    let canonical = canonical_json(&Value::Object(members));
+    Ok(Base64UrlUnpadded::encode_string(&Sha256::digest(canonical.as_bytes())))
+}
+
+/// Returns a copy of a public key with only the members that define the key:
+/// `kty`, `crv`, `x`, and, for EC keys, `y`. Optional members such as `kid`,
+/// `alg`, and `use` are dropped.
+#[must_use]
+pub fn normalize_jwk(jwk: &PublicKeyJwk) -> PublicKeyJwk {
+    PublicKeyJwk {
+        kty: jwk.kty.clone(),
+        crv: jwk.crv.clone(),
+        x: jwk.x.clone(),
+        y: if jwk.kty == KeyType::Ec { jwk.y.clone() } else { None },
+        ..PublicKeyJwk::default()
+    }
+}
+
+/// Whether two public keys are the same key, comparing only the members that
+/// define the key (see [`normalize_jwk`]).
+#[must_use]
+pub fn same_key(jwk: &PublicKeyJwk, other: &PublicKeyJwk) -> bool {
+    normalize_jwk(jwk) == normalize_jwk(other)
+}
+
+/// Returns the canonical form of a JSON value, as defined by the JSON
+/// Canonicalization Scheme ([RFC 8785](https://www.rfc-editor.org/rfc/rfc8785)).
+#[must_use]
+pub fn canonical_json(value: &Value) -> String {
+    let mut canonical = String::new();
+    canonicalize(value, &mut canonical);
+    canonical
+}
+
+// Write the JCS canonical form of a JSON value. Object members are sorted by
+// the UTF-16 code units of their names.
+fn canonicalize(value: &Value, out: &mut String) {
+    match value {
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                canonicalize(item, out);
+            }
+            out.push(']');
+        }
+        Value::Object(members) => {
+            let mut members = members.iter().collect::<Vec<_>>();
+            members.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+            out.push('{');
+            for (i, (name, member)) in members.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&Value::String(name.clone()).to_string());
+                out.push(':');
+                canonicalize(member, out);
+            }
+            out.push('}');
+        }
+        Value::Number(number) => {
+            // JSON numbers are IEEE 754 doubles; serde_json always provides
+            // one unless built with `arbitrary_precision`
+            let number = number.as_f64().unwrap_or_default();
+            out.push_str(&canonical_number(number));
+        }
+        value => out.push_str(&value.to_string()),
+    }
+}
+
+// Serialize a number as ECMAScript's `Number.prototype.toString` does, as
+// required by RFC 8785, section 3.2.2.3. JSON values cannot hold NaN or
+// infinity.
+fn canonical_number(number: f64) -> String {
+    if number == 0.0 {
+        return "0".into();
+    }
+
+    // the shortest digits that round trip, and the exponent `n` for which
+    // the value is 0.digits × 10^n
+    let sci = format!("{:e}", number.abs());
+    let (mantissa, exponent) = sci.split_once('e').unwrap_or((&sci, "0"));
+    let mut digits = mantissa.replace('.', "");
+    let n = exponent.parse::<i32>().unwrap_or_default() + 1;
+
+    // when the value lies exactly between two shortest candidates, Rust
+    // rounds away from zero where ECMAScript chooses the even candidate
+    if digits.ends_with(['1', '3', '5', '7', '9']) {
+        let exact = format!("{:.800e}", number.abs());
+        let exact = exact.split_once('e').map(|(m, _)| m.replace('.', "")).unwrap_or_default();
+        let (lower, rest) = exact.split_at(digits.len().min(exact.len()));
+        let tie = rest.strip_prefix('5').is_some_and(|r| r.bytes().all(|b| b == b'0'));
+        if tie && lower != digits && format!("0.{lower}e{n}").parse() == Ok(number.abs()) {
+            digits = lower.to_string();
+        }
+    }
+    let k = i32::try_from(digits.len()).unwrap_or_default();
+
+    let unsigned = if k <= n && n <= 21 {
+        format!("{digits}{}", "0".repeat((n - k).unsigned_abs() as usize))
+    } else if 0 < n && n <= 21 {
+        let (int, frac) = digits.split_at(n.unsigned_abs() as usize);
+        format!("{int}.{frac}")
+    } else if -6 < n && n <= 0 {
+        format!("0.{}{digits}", "0".repeat(n.unsigned_abs() as usize))
+    } else {
+        let sign = if n - 1 < 0 { '-' } else { '+' };
+        let (first, rest) = digits.split_at(1);
+        let point = if rest.is_empty() { "" } else { "." };
+        format!("{first}{point}{rest}e{sign}{}", (n - 1).unsigned_abs())
+    };
+
+    if number < 0.0 { format!("-{unsigned}") } else { unsigned }
+}
+
+/// Private and symmetric key members of a JWK.
+const PRIVATE_JWK_MEMBERS: [&str; 8] = ["d", "p", "q", "dp", "dq", "qi", "oth", "k"];
+
+/// Strips private key members (`d`, `p`, `q`, etc.) from every JWK in a JSON
+/// DID document so it can be safely logged or returned.
+///
+/// A deserialized [`Document`] cannot hold private key material, since
+/// [`PublicKeyJwk`] has no private members, but the raw JSON it was parsed
+/// from can.
+///
+/// # Errors
+///
+/// Returns an error if a verification method's `publicKeyJwk` contained
+/// private key material. The document is redacted regardless.
+pub fn redact_private_keys(document: &mut Value) -> crate::Result<()> {
+    let mut methods = vec![];
+    redact(document, &mut methods);
+    if !methods.is_empty() {
+        return Err(Error::InvalidPublicKey(format!(
+            "private key material in verification method: {}",
+            methods.join(", ")
+        )));
+    }
+    Ok(())
+}
+
+// Remove private members from JWKs, recording the ids of verification methods
+// that contained them.
+fn redact(value: &mut Value, methods: &mut Vec<String>) {
+    match value {
+        Value::Object(map) => {
+            if map.contains_key("kty") {
+                for member in PRIVATE_JWK_MEMBERS {
+                    map.remove(member);
+                }
+            }
+            if let Some(Value::Object(jwk)) = map.get_mut("publicKeyJwk") {
+                let mut removed = false;
+                for member in PRIVATE_JWK_MEMBERS {
+                    removed |= jwk.remove(member).is_some();
+                }
+                if removed {
+                    let id = map.get("id").and_then(Value::as_str).unwrap_or_default();
+                    methods.push(id.to_string());
+                }
+            }
+            map.values_mut().for_each(|v| redact(v, methods));
+        }
+        Value::Array(items) => items.iter_mut().for_each(|v| redact(v, methods)),
+        _ => {}
+    }
+}
+
+/// A service associated with the DID subject.
+///
+/// Services are used to express ways of communicating with the DID subject or
+/// associated entities. They can be any type of service the DID subject wants
+/// to advertise, including decentralized identity management services for
+/// further discovery, authentication, authorization, or interaction.
+///
+/// Service information is often service specific. For example, a reference to
+/// an encrypted messaging service can detail how to initiate the encrypted link
+/// before messaging begins.
+///
+/// Due to privacy concerns, revealing public information through services, such
+/// as social media accounts, personal websites, and email addresses, is
+/// discouraged.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Service {
+    /// A URI unique to the service.
+    pub id: String,
+
+    /// The service type. SHOULD be registered in the DID Specification
+    /// Registries.
+    #[serde(rename = "type")]
+    pub type_: String,
+
+    /// One or more endpoints for the service. Each endpoint is a URI string
+    /// or a map, and a set of endpoints may mix the two.
+    #[allow(clippy::struct_field_names)]
+    #[serde(deserialize_with = "deserialize_endpoint")]
+    pub service_endpoint: Quota<Kind<Value>>,
+}
+
+// Deserialize a service endpoint, keeping each entry of an endpoint set as a
+// string or object. (An untagged `Quota<Kind<Value>>` would deserialize a set
+// as a single `Kind::Object` array.)
+fn deserialize_endpoint<'de, D>(deserializer: D) -> Result<Quota<Kind<Value>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let kind = |value: Value| match value {
+        Value::String(uri) => Kind::String(uri),
+        value => Kind::Object(value),
+    };
+    Ok(match Value::deserialize(deserializer)? {
+        Value::Array(items) => Quota::Many(items.into_iter().map(kind).collect()),
+        value => Quota::One(kind(value)),
+    })
+}
+
+/// The maximum length, in bytes, of a service id.
+pub const MAX_SERVICE_ID_LENGTH: usize = 256;
+
+impl Service {
+    /// Checks the service has an id of at most [`MAX_SERVICE_ID_LENGTH`]
+    /// bytes and a non-blank type, and each of its endpoints is an absolute
+    /// URI. For map endpoints, such as `DIDCommMessaging` endpoints, `uri`-
+    /// and `url`-like members are checked.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the id is empty or too long, the type is blank, or
+    /// an endpoint is a relative or malformed URI.
+    pub fn validate(&self) -> crate::Result<()> {
+        if self.id.is_empty() || self.id.len() > MAX_SERVICE_ID_LENGTH {
+            return Err(Error::InvalidInput(format!(
+                "service id must be 1 to {MAX_SERVICE_ID_LENGTH} bytes, got {}",
+                self.id.len()
+            )));
+        }
+        if self.type_.trim().is_empty() {
+            return Err(Error::InvalidInput(format!("service {} has no type", self.id)));
+        }
+
+        let check = |uri: &str| {
+            Url::parse(uri).map(|_| ()).map_err(|e| {
+                Error::InvalidInput(format!(
+                    "service {} has invalid endpoint '{uri}': {e}",
+                    self.id
+                ))
+            })
+        };
+
+        for endpoint in self.endpoints() {
+            match endpoint {
+                Value::String(uri) => check(&uri)?,
+                Value::Object(members) => {
+                    for (name, value) in members {
+                        let name = name.to_ascii_lowercase();
+                        if (name.ends_with("uri") || name.ends_with("url"))
+                            && let Value::String(uri) = value
+                        {
+                            check(&uri)?;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    // The service's endpoints as a flat list of values. A set of endpoints
+    // may be constructed as a single `Kind::Object` array, so arrays are
+    // flattened.
+    fn endpoints(&self) -> Vec<Value> {
+        let mut endpoints = vec![];
+        for kind in quota_items(&self.service_endpoint) {
+            match kind {
+                Kind::String(uri) => endpoints.push(Value::String(uri.clone())),
+                Kind::Object(Value::Array(items)) => endpoints.extend(items.iter().cloned()),
+                Kind::Object(value) => endpoints.push(value.clone()),
+            }
+        }
+        endpoints
+    }
+}
+
+/// A verification method, such as a cryptographic public key.
+///
+/// A DID document can express verification methods, such as cryptographic
+/// public keys, which can be used to authenticate or authorize interactions
+/// with the DID subject or associated parties. For example, a cryptographic
+/// public key can be used as a verification method with respect to a digital
+/// signature; in such usage, it verifies that the signer could use the
+/// associated cryptographic private key. Verification methods might take many
+/// parameters. An example of this is a set of five cryptographic keys from
+/// which any three are required to contribute to a cryptographic threshold
+/// signature.
+///
+/// MAY include additional properties which can be determined from the
+/// verification method as registered in the
+/// [DID Specification Registries](https://www.w3.org/TR/did-spec-registries/).
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct VerificationMethod {
+    /// Only used when the verification method uses terms not defined in the
+    /// containing document.
+    #[serde(rename = "@context")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context: Option<Quota<Context>>,
+
+    /// A DID that identifies the verification method.
+    pub id: String,
+
+    /// The DID of the controller of the verification method.
+    pub controller: String,
+
+    /// The verification method type. SHOULD be a registered type (in DID
+    /// Specification Registries).
+    #[serde(flatten)]
+    pub method_type: MethodType,
+}
+
+impl VerificationMethod {
+    /// Returns the algorithm of the verification method's public key, as
+    /// determined by the JWK curve or the Multibase key's multicodec prefix.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the key type is unknown or unsupported.
+    pub fn key_algorithm(&self) -> crate::Result<KeyAlgorithm> {
+        // the key is recovered from a signature, so is not in the method
+        if let MethodType::EcdsaSecp256k1RecoveryMethod2020 { .. } = self.method_type {
+            return Ok(KeyAlgorithm::Secp256k1);
+        }
+        let jwk = self.method_type.to_jwk()?;
+        Ok(KeyAlgorithm::from_curve(&jwk.crv))
+    }
+
+    /// Checks the verification method's key can be used for the specified
+    /// verification relationship.
+    ///
+    /// Signature keys (Ed25519 and secp256k1) cannot be used for key
+    /// agreement, and X25519 keys can only be used for key agreement.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the key type is unsupported or cannot be used for
+    /// the purpose.
+    pub fn check_purpose(&self, purpose: KeyPurpose) -> crate::Result<()> {
+        let algorithm = self.key_algorithm()?;
+        let valid = match purpose {
+            KeyPurpose::VerificationMethod => true,
+            KeyPurpose::KeyAgreement => algorithm == KeyAlgorithm::X25519,
+            _ => algorithm != KeyAlgorithm::X25519,
+        };
+        if !valid {
+            return Err(Error::InvalidPublicKey(format!(
+                "{algorithm} key {} cannot be used for {purpose}",
+                self.id
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// The algorithm of a verification method's public key.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub enum KeyAlgorithm {
+    /// Ed25519 signature (`EdDSA`) key.
+    Ed25519,
+
+    /// X25519 key agreement (ECDH) key.
+    X25519,
+
+    /// secp256k1 signature (`ES256K`) key.
+    Secp256k1,
+}
+
+impl KeyAlgorithm {
+    // The algorithm of keys on the JWK curve.
+    const fn from_curve(crv: &Curve) -> Self {
+        match crv {
+            Curve::Ed25519 => Self::Ed25519,
+            Curve::X25519 => Self::X25519,
+            Curve::Es256K => Self::Secp256k1,
+        }
+    }
+}
+
+impl Display for KeyAlgorithm {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ed25519 => write!(f, "Ed25519"),
+            Self::X25519 => write!(f, "X25519"),
+            Self::Secp256k1 => write!(f, "secp256k1"),
+        }
+    }
+}
+
+/// Verification method types. SHOULD be registered in the [DID Specification
+/// Registries](https://www.w3.org/TR/did-spec-registries).
+///
+/// Methods of an unknown type are read as [`MethodType::Unsupported`]. A
+/// method of a known type must have that type's members.
+#[derive(Clone, Debug, Serialize, Eq, PartialEq)]
+#[serde(tag = "type")]
+#[serde(rename_all_fields = "camelCase")]
+pub enum MethodType {
+    /// Key is encoded as a Multibase. The Multikey data model is a specific
+    /// type of verification method that encodes key types into a single
+    /// binary stream that is then encoded as a Multibase value.
+    ///
+    /// <https://w3c.github.io/controller-document/#multikey>
+    Multikey {
+        /// The public key encoded as a Multibase.
+        public_key_multibase: String,
+    },
+
+    /// Key is JWK. The JSON Web Key (JWK) data model is a specific type of
+    /// verification method that uses the JWK specification [RFC7517] to encode
+    /// key types into a set of parameters.
+    ///
+    /// <https://w3c.github.io/controller-document/#jsonwebkey>
+    ///
+    /// `EcdsaSecp256k1VerificationKey2019` methods are read as this type.
+    JsonWebKey {
+        /// The public key encoded as a JWK.
+        public_key_jwk: PublicKeyJwk,
+    },
+
+    /// An Ed25519 public key encoded as a Multibase.
+    ///
+    /// <https://w3id.org/security/suites/ed25519-2020/v1>
+    Ed25519VerificationKey2020 {
+        /// The public key encoded as a Multibase.
+        public_key_multibase: String,
+    },
+
+    /// An X25519 key agreement key encoded as a Multibase.
+    ///
+    /// <https://w3id.org/security/suites/x25519-2020/v1>
+    X25519KeyAgreementKey2020 {
+        /// The public key encoded as a Multibase.
+        public_key_multibase: String,
+    },
+
+    /// A public key encoded as a JWK.
+    ///
+    /// <https://w3id.org/security/suites/jws-2020/v1>
+    JsonWebKey2020 {
+        /// The public key encoded as a JWK.
+        public_key_jwk: PublicKeyJwk,
+    },
+
+    /// A secp256k1 key identified by the blockchain account derived from it,
+    /// as used by Ethereum DID tooling. The public key is recovered from a
+    /// signature rather than published in the method.
+    ///
+    /// <https://w3id.org/security/suites/secp256k1recovery-2020/v2>
+    EcdsaSecp256k1RecoveryMethod2020 {
+        /// The account, as a [CAIP-10](https://chainagnostic.org/CAIPs/caip-10)
+        /// account id. For example, `eip155:1:0xab16a96D359eC26a11e2C2b3d8f8B8942d5Bfcdb`.
+        blockchain_account_id: String,
+    },
+    //
+    // #[serde(alias = "Ed25519VerificationKey2018")]
+    // Base58 { public_key_base58: String },
+    /// A verification method type this crate does not support, such as
+    /// `Bls12381G2Key2020`. The method is kept as read, so documents using
+    /// it can still be resolved and serialized, but its key cannot be used.
+    #[serde(untagged)]
+    Unsupported {
+        /// The verification method type.
+        #[serde(rename = "type")]
+        type_: String,
+
+        /// The verification method's other properties, such as its key
+        /// material.
+        #[serde(flatten)]
+        properties: Map<String, Value>,
+    },
+}
+
+/// Summarizes the method for logging, without the full key material. For
+/// example, `Multikey z6MkhaXg…2doK` or `JsonWebKey Ed25519 11qYAYKx…Urxo`.
+impl Display for MethodType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Multikey { public_key_multibase }
+            | Self::Ed25519VerificationKey2020 { public_key_multibase }
+            | Self::X25519KeyAgreementKey2020 { public_key_multibase } => {
+                write!(f, "{} {}", self.kind(), abbreviate(public_key_multibase))
+            }
+            Self::JsonWebKey { public_key_jwk } | Self::JsonWebKey2020 { public_key_jwk } => {
+                let algorithm = KeyAlgorithm::from_curve(&public_key_jwk.crv);
+                write!(f, "{} {algorithm} {}", self.kind(), abbreviate(&public_key_jwk.x))
+            }
+            Self::EcdsaSecp256k1RecoveryMethod2020 {
+                blockchain_account_id,
+            } => {
+                write!(f, "{} {blockchain_account_id}", self.kind())
+            }
+            Self::Unsupported { .. } => write!(f, "{} (unsupported)", self.kind()),
+        }
+    }
+}
+
+// The start and end of a long value, for display.
+fn abbreviate(value: &str) -> String {
+    let chars: Vec<char> = value.chars().collect();
+    if chars.len() <= 12 {
+        return value.to_string();
+    }
+    let start: String = chars[..8].iter().collect();
+    let end: String = chars[chars.len() - 4..].iter().collect();
+    format!("{start}…{end}")
+}
+
+// Dispatch on `type` first, so only methods of an unknown type are read as
+// `Unsupported` and a known type with missing or malformed members is an
+// error.
+impl<'de> Deserialize<'de> for MethodType {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let mut properties = Map::<String, Value>::deserialize(deserializer)?;
+        let type_ = match properties.remove("type") {
+            Some(Value::String(type_)) => type_,
+            Some(_) => return Err(de::Error::custom("verification method type is not a string")),
+            None => return Err(de::Error::missing_field("type")),
+        };
+
+        let method = match type_.as_str() {
+            "Multikey" => Self::Multikey {
+                public_key_multibase: member(&mut properties, "publicKeyMultibase")?,
+            },
+            "JsonWebKey" | "EcdsaSecp256k1VerificationKey2019" => Self::JsonWebKey {
+                public_key_jwk: member(&mut properties, "publicKeyJwk")?,
+            },
+            "Ed25519VerificationKey2020" => Self::Ed25519VerificationKey2020 {
+                public_key_multibase: member(&mut properties, "publicKeyMultibase")?,
+            },
+            "X25519KeyAgreementKey2020" => Self::X25519KeyAgreementKey2020 {
+                public_key_multibase: member(&mut properties, "publicKeyMultibase")?,
+            },
+            "JsonWebKey2020" => Self::JsonWebKey2020 {
+                public_key_jwk: member(&mut properties, "publicKeyJwk")?,
+            },
+            "EcdsaSecp256k1RecoveryMethod2020" => Self::EcdsaSecp256k1RecoveryMethod2020 {
+                blockchain_account_id: member(&mut properties, "blockchainAccountId")?,
+            },
+            _ => Self::Unsupported { type_, properties },
+        };
+        Ok(method)
+    }
+}
+
+// Remove and deserialize a required member of a verification method.
+fn member<T: DeserializeOwned, E: de::Error>(
+    properties: &mut Map<String, Value>, name: &'static str,
+) -> Result<T, E> {
+    let value = properties.remove(name).ok_or_else(|| E::missing_field(name))?;
+    serde_json::from_value(value).map_err(|e| E::custom(format!("invalid {name}: {e}")))
+}
+
+impl Default for MethodType {
+    fn default() -> Self {
+        Self::Multikey {
+            public_key_multibase: String::new(),
+        }
+    }
+}
+
+impl MethodType {
+    /// The verification method type, as serialized in the method's `type`
+    /// property. For example, `Multikey` or `JsonWebKey`.
+    #[must_use]
+    pub fn kind(&self) -> &str {
+        match self {
+            Self::Multikey { .. } => "Multikey",
+            Self::JsonWebKey { .. } => "JsonWebKey",
+            Self::Ed25519VerificationKey2020 { .. } => "Ed25519VerificationKey2020",
+            Self::X25519KeyAgreementKey2020 { .. } => "X25519KeyAgreementKey2020",
+            Self::JsonWebKey2020 { .. } => "JsonWebKey2020",
+            Self::EcdsaSecp256k1RecoveryMethod2020 { .. } => "EcdsaSecp256k1RecoveryMethod2020",
+            Self::Unsupported { type_, .. } => type_,
+        }
+    }
+
+    /// Whether the verification method type is supported, so its key can be
+    /// used.
+    #[must_use]
+    pub const fn is_supported(&self) -> bool {
+        !matches!(self, Self::Unsupported { .. })
+    }
+
+    /// Converts a Multibase public key to JWK format.
+    ///
+    /// # Errors
+    ///
+    /// See [`MethodType::to_jwk`].
+    #[deprecated(note = "use `to_jwk` instead")]
+    pub fn jwk(&self) -> crate::Result<PublicKeyJwk> {
+        self.to_jwk()
+    }
+
+    /// Converts the verification method's public key to JWK format.
+    ///
+    /// Multibase keys are decoded using their multicodec prefix.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the Multibase key cannot be decoded or uses an
+    /// unsupported multicodec prefix, or the method type is unsupported or
+    /// has no public key.
+    pub fn to_jwk(&self) -> crate::Result<PublicKeyJwk> {
+        match self {
+            Self::JsonWebKey { public_key_jwk } | Self::JsonWebKey2020 { public_key_jwk } => {
+                Ok(public_key_jwk.clone())
+            }
+            Self::Multikey { public_key_multibase }
+            | Self::Ed25519VerificationKey2020 { public_key_multibase }
+            | Self::X25519KeyAgreementKey2020 { public_key_multibase } => {
+                multikey_to_jwk(public_key_multibase)
+            }
+            Self::EcdsaSecp256k1RecoveryMethod2020 { .. } => Err(Error::UnsupportedPublicKeyType(
+                "EcdsaSecp256k1RecoveryMethod2020 methods have no public key".into(),
+            )),
+            Self::Unsupported { type_, .. } => Err(Error::UnsupportedPublicKeyType(format!(
+                "unsupported verification method type: {type_}"
+            ))),
+        }
+    }
+}
+
+/// Converts a JWK to a Multibase-encoded (`Multikey`) public key.
+///
+/// The key is prefixed with the multicodec value for its curve. Supports
+/// Ed25519, X25519, and secp256k1 keys. secp256k1 keys are encoded in
+/// compressed form.
+///
+/// # Errors
+///
+/// Returns an error if the JWK's key material is not valid for its curve.
+pub fn jwk_to_multikey(jwk: &PublicKeyJwk) -> crate::Result<String> {
+    let x_bytes = Base64UrlUnpadded::decode_vec(&jwk.x)
+        .map_err(|e| Error::InvalidPublicKey(format!("issue decoding x: {e}")))?;
+    if x_bytes.len() != 32 {
+        return Err(Error::InvalidPublicKeyLength(format!("x is {} bytes", x_bytes.len())));
+    }
+
+    let key_bytes = match jwk.crv {
+        Curve::Ed25519 | Curve::X25519 => x_bytes,
+        Curve::Es256K => {
+            // compressed point: parity of y as prefix, followed by x
+            let Some(y) = &jwk.y else {
+                return Err(Error::InvalidPublicKey("secp256k1 key has no y".into()));
+            };
+            let y_bytes = Base64UrlUnpadded::decode_vec(y)
+                .map_err(|e| Error::InvalidPublicKey(format!("issue decoding y: {e}")))?;
+            let Some(last) = y_bytes.last() else {
+                return Err(Error::InvalidPublicKey("secp256k1 key has empty y".into()));
+            };
+            [[0x02 | (last & 1)].as_slice(), &x_bytes].concat()
+        }
+    };
+
+    multicodec::encode_multikey(&jwk.kty, &jwk.crv, &key_bytes)
+}
+
+/// Converts a Multibase-encoded (`Multikey`) public key to a JWK.
+pub fn multikey_to_jwk(multikey: &str) -> crate::Result<PublicKeyJwk> {
+    let (kty, crv, key_bytes) = multicodec::decode_multikey(multikey)?;
+
+    match crv {
+        Curve::Ed25519 | Curve::X25519 => {
+            if key_bytes.len() != 32 {
+                return Err(Error::InvalidPublicKeyLength(format!(
+                    "key is {} bytes, expected 32",
+                    key_bytes.len()
+                )));
+            }
+            Ok(PublicKeyJwk {
+                kty,
+                crv,
+                x: Base64UrlUnpadded::encode_string(&key_bytes),
+                ..PublicKeyJwk::default()
+            })
+        }
+        Curve::Es256K => {
+            let public_key = k256::PublicKey::from_sec1_bytes(&key_bytes)
+                .map_err(|e| Error::InvalidPublicKey(format!("invalid secp256k1 key: {e}")))?;
+            let point = public_key.to_encoded_point(false);
+            let (Some(x), Some(y)) = (point.x(), point.y()) else {
+                return Err(Error::InvalidPublicKey("secp256k1 key is the identity".into()));
+            };
+            Ok(PublicKeyJwk {
+                kty,
+                crv,
+                x: Base64UrlUnpadded::encode_string(x),
+                y: Some(Base64UrlUnpadded::encode_string(y)),
+                ..PublicKeyJwk::default()
+            })
+        }
+    }
+}
+
+/// Verification method types. SHOULD be registered in the [DID Specification
+/// Registries](https://www.w3.org/TR/did-spec-registries).
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub enum PublicKeyFormat {
+    /// Key is encoded as a Multibase. The Multikey data model is a specific
+    /// type of verification method that encodes key types into a single
+    /// binary stream that is then encoded as a Multibase value.
+    ///
+    /// <https://w3c.github.io/controller-document/#multikey>
+    #[default]
+    Multikey,
+
+    /// Key is JWK. The JSON Web Key (JWK) data model is a specific type of
+    /// verification method that uses the JWK specification [RFC7517] to encode
+    /// key types into a set of parameters.
+    ///
+    /// <https://w3c.github.io/controller-document/#jsonwebkey>
+    JsonWebKey,
+
+    /// Key is ED2559 Verification Key.
+    ///
+    /// <https://w3id.org/security/suites/ed25519-2020/v1>
+    Ed25519VerificationKey2020,
+
+    /// X25519 Key Agreement Key, 2020 version
+    X25519KeyAgreementKey2020,
+
+    /// Key is JWK, 2020 version.
+    ///
+    /// <https://w3id.org/security/suites/jws-2020/v1>
+    JsonWebKey2020,
+}
+
+/// The scheme used to name the fragment of a derived key agreement
+/// verification method.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub enum KeyAgreementIdStyle {
+    /// The X25519 key's Multibase value is used as the fragment. This is the
+    /// scheme defined by the `did:key` specification and used by most
+    /// resolvers.
+    ///
+    /// For example, `did:key:z6Mk...#z6LS...`.
+    #[default]
+    X25519Multibase,
+
+    /// The fragment is keyed off the Ed25519 identifier (the DID's
+    /// method-specific identifier) with an `-x25519` suffix. Use for
+    /// resolvers that re-derive the key agreement fragment from the signing
+    /// key rather than the derived X25519 key.
+    ///
+    /// For example, `did:key:z6Mk...#z6Mk...-x25519`.
+    Ed25519Suffix,
+}
+
+/// The scheme used to name the fragment of a created verification method.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub enum VerificationMethodId {
+    /// Methods are numbered in the order they are added: `key-0` for the
+    /// signing key and `key-1` for a derived key agreement key.
+    ///
+    /// For example, `did:web:example.com#key-0`.
+    #[default]
+    Sequential,
+
+    /// A random 8-byte hex value. Not reproducible across re-creates.
+    ///
+    /// For example, `did:web:example.com#3f9a0c17b2e4d865`.
+    Random,
+
+    /// The key's JWK thumbprint ([RFC 7638](https://www.rfc-editor.org/rfc/rfc7638)).
+    /// Reproducible across re-creates.
+    ///
+    /// For example, `did:web:example.com#kPrK_qmxVWaYVA9wwBF6Iuo3vVzz7TxHCTwXBygrS4k`.
+    JwkThumbprint,
+
+    /// A caller-supplied fragment for the signing key. A derived key
+    /// agreement key is given the fragment with an `-x25519` suffix. The
+    /// fragment must not be empty or contain `#`, `/`, or `?`.
+    ///
+    /// For example, `did:web:example.com#signing`.
+    Fragment(String),
+}
+
+impl VerificationMethodId {
+    /// Returns the fragment for the verification method with the specified
+    /// key. `index` is the position of the method in the document: 0 for the
+    /// signing key and 1 for a derived key agreement key.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the key's thumbprint cannot be computed or a
+    /// caller-supplied fragment is empty or contains a DID URL delimiter.
+    pub fn fragment(&self, jwk: &PublicKeyJwk, index: usize) -> crate::Result<String> {
+        if let Self::Fragment(fragment) = self
+            && (fragment.is_empty() || fragment.contains(['#', '/', '?']))
+        {
+            return Err(Error::InvalidInput(format!("invalid fragment: '{fragment}'")));
+        }
+
+        let fragment = match self {
+            Self::Sequential => format!("key-{index}"),
+            Self::Random => format!("{:016x}", rand::random::<u64>()),
+            Self::JwkThumbprint => jwk_thumbprint(jwk)?,
+            Self::Fragment(fragment) if index == 0 => fragment.clone(),
+            Self::Fragment(fragment) => format!("{fragment}-x25519"),
+        };
+        Ok(fragment)
+    }
+}
+
+// TODO: set context based on key format:
+// - Ed25519VerificationKey2020	https://w3id.org/security/suites/ed25519-2020/v1
+// - JsonWebKey2020	https://w3id.org/security/suites/jws-2020/v1
+
+impl Display for PublicKeyFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Multikey => write!(f, "Multikey"),
+            Self::Ed25519VerificationKey2020 => write!(f, "Ed25519VerificationKey2020"),
+            Self::X25519KeyAgreementKey2020 => write!(f, "X25519KeyAgreementKey2020"),
+            Self::JsonWebKey => write!(f, "JsonWebKey"),
+            Self::JsonWebKey2020 => write!(f, "JsonWebKey2020"),
+        }
+    }
+}
+
+impl CreateOptions {
+    /// Whether the verification relationship is selected by the options and
+    /// profile.
+    pub(crate) fn includes(&self, purpose: KeyPurpose) -> bool {
+        self.profile.includes(purpose)
+            && self.relationships.as_ref().is_none_or(|selected| selected.contains(&purpose))
+    }
+}
+
+/// Appends additional contexts to a document's contexts, skipping any already
+/// present. Used to apply [`CreateOptions::additional_contexts`].
+#[must_use]
+pub fn append_contexts(mut context: Vec<Context>, additional: Vec<Context>) -> Vec<Context> {
+    for entry in additional {
+        if !context.contains(&entry) {
+            context.push(entry);
+        }
+    }
+    context
+}
+
+/// An ecosystem profile for created DID documents. Each profile presets the
+/// public key format, contexts, and verification relationships required by
+/// that ecosystem.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub enum Profile {
+    /// Use the format and relationships set in [`CreateOptions`].
+    #[default]
+    W3cDefault,
+
+    /// JSON Web Signature 2020 profile: `JsonWebKey2020` verification methods
+    /// with the `jws-2020` context and no key agreement.
+    ///
+    /// This is not the EBSI natural person profile, which requires `did:key`
+    /// DIDs using the `jwk_jcs-pub` multicodec over P-256 keys.
+    Jws2020,
+
+    /// 2020 cryptosuite profile: `Ed25519VerificationKey2020` verification
+    /// methods with a derived `X25519KeyAgreementKey2020` key agreement
+    /// method.
+    Diddoc2020,
+}
+
+impl Profile {
+    /// Apply the profile's public key format and key agreement settings.
+    pub(crate) fn apply(&self, options: CreateOptions) -> CreateOptions {
+        match self {
+            Self::W3cDefault => options,
+            Self::Jws2020 => CreateOptions {
+                public_key_format: PublicKeyFormat::JsonWebKey2020,
+                enable_encryption_key_derivation: false,
+                ..options
+            },
+            Self::Diddoc2020 => CreateOptions {
+                public_key_format: PublicKeyFormat::Ed25519VerificationKey2020,
+                enable_encryption_key_derivation: true,
+                ..options
+            },
+        }
+    }
+
+    /// The contexts required by the profile, if any.
+    pub(crate) fn context(&self) -> Option<Vec<Context>> {
+        let urls = match self {
+            Self::W3cDefault => return None,
+            Self::Jws2020 => vec![DID_CONTEXT, "https://w3id.org/security/suites/jws-2020/v1"],
+            Self::Diddoc2020 => vec![
+                DID_CONTEXT,
+                "https://w3id.org/security/suites/ed25519-2020/v1",
+                "https://w3id.org/security/suites/x25519-2020/v1",
+            ],
+        };
+        Some(urls.into_iter().map(Context::from).collect())
+    }
+
+    /// Whether the profile includes the verification relationship.
+    pub(crate) const fn includes(&self, purpose: KeyPurpose) -> bool {
+        match self {
+            Self::W3cDefault | Self::Diddoc2020 => true,
+            Self::Jws2020 => !matches!(purpose, KeyPurpose::KeyAgreement),
+        }
+    }
+}
+
+/// DID document metadata. This typically does not change unless the DID
+/// document changes.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+#[allow(clippy::module_name_repetitions)]
+pub struct DocumentMetadata {
+    /// Timestamp of the Create operation.
+    /// An XMLSCHEMA11-2 (RFC3339) e.g. 2010-01-01T19:23:24Z.
+    pub created: DateTime<Utc>,
+
+    /// Timestamp of the last Update operation. Omitted if an Update operation
+    /// has never been performed. May be the same value as the `created`
+    /// property when the difference between the two timestamps is less than
+    /// one second. An XMLSCHEMA11-2 (RFC3339) e.g. 2010-01-01T19:23:24Z.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated: Option<DateTime<Utc>>,
+
+    /// MUST be set to true if the DID has been deactivated. Optional if the DID
+    /// has not been deactivated.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deactivated: Option<bool>,
+
+    /// May be set if the document version is not the latest. Indicates the
+    /// timestamp of the next Update operation as an XMLSCHEMA11-2
+    /// (RFC3339).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_update: Option<DateTime<Utc>>,
+
+    /// Used to indicate the version of the last Update operation. SHOULD be
+    /// set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version_id: Option<String>,
+
+    /// MAY be set if the document version is not the latest. It indicates the
+    /// version of the next Update operation.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_version_id: Option<String>,
+
+    /// Used when a DID method needs to define different forms of a DID that are
+    /// logically equivalent. For example, when a DID takes one form prior to
+    /// registration in a verifiable data registry and another form after such
+    /// registration.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub equivalent_id: Option<Vec<String>>,
+
+    /// Identical to the `equivalent_id` property except that it is a single
+    /// value AND the DID is the canonical ID for the DID subject within the
+    /// containing DID document.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub canonical_id: Option<String>,
+}
+
+/// Options that can be provided when creating a DID document.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateOptions {
+    /// Format to use for the the public key.
+    pub public_key_format: PublicKeyFormat,
+
+    /// Default context for the DID document. SHOULD be set to
+    /// `"https://www.w3.org/ns/did/v1"`.
+    pub default_context: String,
+
+    /// Enable experimental public key types. SHOULD be set to "false".
+    pub enable_experimental_public_key_types: bool,
+
+    /// Will add a `keyAgreement` object to the DID document.
+    pub enable_encryption_key_derivation: bool,
+
+    /// Populate the `kid` and `use` members of `did:jwk` verification method
+    /// JWKs, for JOSE libraries that expect them. `kid` is set to the
+    /// verification method's fragment.
+    ///
+    /// Key agreement keys also have `alg` set to `ECDH-ES`. The JWK `alg`
+    /// member only holds key management algorithms, so it is not set for
+    /// signing keys. Ignored for Multikey public key formats.
+    #[serde(default)]
+    pub populate_jwk_parameters: bool,
+
+    /// The fragment naming scheme for derived key agreement methods.
+    #[serde(default)]
+    pub key_agreement_id_style: KeyAgreementIdStyle,
+
+    /// Relationships to add a distinct, derived verification method for. The
+    /// derived methods are added to the document's `verificationMethod` set
+    /// and referenced from their relationship.
+    ///
+    /// Only [`KeyPurpose::KeyAgreement`] is derivable: an X25519 key is
+    /// derived from an Ed25519 `did:key`. Signing keys cannot be derived from
+    /// a public key, so other purposes are rejected.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub derived_methods: Vec<KeyPurpose>,
+
+    /// A separately generated X25519 key to attach to a `did:web` document as
+    /// its key agreement method, in place of a derived key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_agreement_key: Option<PublicKeyJwk>,
+
+    /// The fragment naming scheme for `did:web` verification methods.
+    #[serde(default)]
+    pub verification_method_id: VerificationMethodId,
+
+    /// Ecosystem profile to conform to. Profiles other than
+    /// [`Profile::W3cDefault`] override the public key format, context, and
+    /// verification relationships.
+    #[serde(default)]
+    pub profile: Profile,
+
+    /// The verification relationships to add the key to. When `None`, the key
+    /// is added to all relationships. Unselected relationships are omitted
+    /// from the document.
+    pub relationships: Option<Vec<KeyPurpose>>,
+
+    /// Additional JSON-LD contexts to append to the document's `@context`,
+    /// after the method-specific context. Duplicates are ignored.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub additional_contexts: Vec<Context>,
+
+    // service_endpoints: Vec<Value>,
+    // verification_methods: Vec<Value>,
+    // authentication: Vec<Value>,
+    /// Additional options.
     #[serde(flatten)]
     pub additional: Option<HashMap<String, String>>,
 }
@@ -353,9 +2322,1451 @@ impl Default for CreateOptions {
         Self {
             public_key_format: PublicKeyFormat::default(),
             enable_experimental_public_key_types: false,
-            default_context: "https://www.w3.org/ns/did/v1".to_string(),
+            default_context: DID_CONTEXT.to_string(),
             enable_encryption_key_derivation: false,
+            populate_jwk_parameters: false,
+            key_agreement_id_style: KeyAgreementIdStyle::default(),
+            derived_methods: Vec::new(),
+            key_agreement_key: None,
+            verification_method_id: VerificationMethodId::default(),
+            profile: Profile::default(),
+            relationships: None,
+            additional_contexts: Vec::new(),
             additional: None,
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use k256::SecretKey;
+    use multibase::Base;
+
+    use super::*;
+
+    #[test]
+    fn ed25519_multikey() {
+        const MULTIKEY: &str = "z6Mkj8Jr1rg3YjVWWhg7ahEYJibqhjBgZt1pDCbT4Lv7D4HX";
+
+        let method_type = MethodType::Multikey {
+            public_key_multibase: MULTIKEY.into(),
+        };
+        let jwk = method_type.to_jwk().expect("should convert");
+        assert_eq!(jwk.crv, Curve::Ed25519);
+        assert_eq!(jwk.x, "RW-Q0fO2oECyLs4rZDZZo4p6b7pu7UF2eu9JBsktDco");
+        assert_eq!(jwk_to_multikey(&jwk).expect("should convert"), MULTIKEY);
+    }
+
+    #[test]
+    fn x25519_multikey() {
+        let jwk = PublicKeyJwk {
+            kty: KeyType::Okp,
+            crv: Curve::X25519,
+            x: Base64UrlUnpadded::encode_string(&[9u8; 32]),
+            ..PublicKeyJwk::default()
+        };
+        let multikey = jwk_to_multikey(&jwk).expect("should convert");
+        assert!(multikey.starts_with("z6LS"));
+
+        let method_type = MethodType::Multikey {
+            public_key_multibase: multikey,
+        };
+        assert_eq!(method_type.to_jwk().expect("should convert"), jwk);
+    }
+
+    #[test]
+    fn secp256k1_multikey() {
+        let secret_key = SecretKey::from_slice(&[1u8; 32]).expect("should create key");
+        let point = secret_key.public_key().to_encoded_point(false);
+        let jwk = PublicKeyJwk {
+            kty: KeyType::Ec,
+            crv: Curve::Es256K,
+            x: Base64UrlUnpadded::encode_string(point.x().expect("should have x")),
+            y: Some(Base64UrlUnpadded::encode_string(point.y().expect("should have y"))),
+            ..PublicKeyJwk::default()
+        };
+
+        let multikey = jwk_to_multikey(&jwk).expect("should convert");
+        assert!(multikey.starts_with("zQ3s"));
+        let (_, multi_bytes) = multibase::decode(&multikey).expect("should decode");
+        assert_eq!(multi_bytes[2..], *secret_key.public_key().to_encoded_point(true).as_bytes());
+
+        let method_type = MethodType::Multikey {
+            public_key_multibase: multikey,
+        };
+        assert_eq!(method_type.to_jwk().expect("should convert"), jwk);
+    }
+
+    #[test]
+    fn referenced_dids() {
+        let document: Document = serde_json::from_value(serde_json::json!({
+            "@context": ["https://www.w3.org/ns/did/v1"],
+            "id": "did:web:example.com",
+            "alsoKnownAs": ["did:key:z6Mkj8Jr1rg3YjVWWhg7ahEYJibqhjBgZt1pDCbT4Lv7D4HX", "https://example.com"],
+            "controller": "did:web:controller.example.com",
+            "verificationMethod": [{
+                "id": "did:web:example.com#key-0",
+                "controller": "did:web:example.com",
+                "type": "Multikey",
+                "publicKeyMultibase": "z6Mkj8Jr1rg3YjVWWhg7ahEYJibqhjBgZt1pDCbT4Lv7D4HX"
+            }],
+            "authentication": [
+                "did:web:example.com#key-0",
+                "did:web:controller.example.com#key-0"
+            ]
+        }))
+        .expect("should deserialize");
+
+        assert_eq!(
+            document.referenced_dids(),
+            vec![
+                "did:web:controller.example.com",
+                "did:key:z6Mkj8Jr1rg3YjVWWhg7ahEYJibqhjBgZt1pDCbT4Lv7D4HX"
+            ]
+        );
+    }
+
+    #[test]
+    fn method_type_display() {
+        let multikey = MethodType::Multikey {
+            public_key_multibase: "z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK".into(),
+        };
+        assert_eq!(multikey.kind(), "Multikey");
+        assert_eq!(multikey.to_string(), "Multikey z6MkhaXg…2doK");
+
+        let jwk = MethodType::JsonWebKey {
+            public_key_jwk: PublicKeyJwk {
+                kty: KeyType::Okp,
+                crv: Curve::Ed25519,
+                x: "Lm_M42cB3HkUiODQsXRcweM6TByfzEHGO9ND274JcOY".into(),
+                ..PublicKeyJwk::default()
+            },
+        };
+        assert_eq!(jwk.kind(), "JsonWebKey");
+        assert_eq!(jwk.to_string(), "JsonWebKey Ed25519 Lm_M42cB…JcOY");
+
+        // the kind matches the serialized type
+        let value = serde_json::to_value(&jwk).expect("should serialize");
+        assert_eq!(value["type"], jwk.kind());
+    }
+
+    #[test]
+    fn key_algorithm() {
+        let mut vm = VerificationMethod {
+            method_type: MethodType::Multikey {
+                public_key_multibase: "z6Mkj8Jr1rg3YjVWWhg7ahEYJibqhjBgZt1pDCbT4Lv7D4HX".into(),
+            },
+            ..VerificationMethod::default()
+        };
+        assert_eq!(vm.key_algorithm().expect("should be supported"), KeyAlgorithm::Ed25519);
+
+        vm.method_type = MethodType::JsonWebKey {
+            public_key_jwk: PublicKeyJwk {
+                kty: KeyType::Okp,
+                crv: Curve::X25519,
+                x: Base64UrlUnpadded::encode_string(&[9u8; 32]),
+                ..PublicKeyJwk::default()
+            },
+        };
+        assert_eq!(vm.key_algorithm().expect("should be supported"), KeyAlgorithm::X25519);
+
+        let secret_key = SecretKey::from_slice(&[1u8; 32]).expect("should create key");
+        let compressed = secret_key.public_key().to_encoded_point(true);
+        vm.method_type = MethodType::Multikey {
+            public_key_multibase: multicodec::encode_multikey(
+                &KeyType::Ec,
+                &Curve::Es256K,
+                compressed.as_bytes(),
+            )
+            .expect("should encode"),
+        };
+        assert_eq!(vm.key_algorithm().expect("should be supported"), KeyAlgorithm::Secp256k1);
+
+        vm.method_type = MethodType::Multikey {
+            public_key_multibase: multibase::encode(
+                Base::Base58Btc,
+                [[0x12, 0x00].as_slice(), &[0; 32]].concat(),
+            ),
+        };
+        assert!(vm.key_algorithm().is_err());
+    }
+
+    #[test]
+    fn verification_methods_for() {
+        let document: Document = serde_json::from_value(serde_json::json!({
+            "id": "did:web:example.com",
+            "verificationMethod": [{
+                "id": "did:web:example.com#key-0",
+                "controller": "did:web:example.com",
+                "type": "Multikey",
+                "publicKeyMultibase": "z6Mkj8Jr1rg3YjVWWhg7ahEYJibqhjBgZt1pDCbT4Lv7D4HX"
+            }, {
+                "id": "#key-1",
+                "controller": "did:web:example.com",
+                "type": "Multikey",
+                "publicKeyMultibase": "z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK"
+            }],
+            "authentication": [
+                "#key-0",
+                "did:web:example.com#key-1",
+                "did:web:other.example.com#key-0"
+            ],
+            "assertionMethod": [{
+                "id": "did:web:example.com#key-2",
+                "controller": "did:web:example.com",
+                "type": "Multikey",
+                "publicKeyMultibase": "z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK"
+            }]
+        }))
+        .expect("should deserialize");
+
+        let ids = |purpose| {
+            document
+                .verification_methods_for(purpose)
+                .into_iter()
+                .map(|vm| vm.id.as_str())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ids(KeyPurpose::Authentication), vec!["did:web:example.com#key-0", "#key-1"]);
+        assert_eq!(ids(KeyPurpose::AssertionMethod), vec!["did:web:example.com#key-2"]);
+        assert_eq!(ids(KeyPurpose::KeyAgreement), Vec::<&str>::new());
+        assert_eq!(
+            ids(KeyPurpose::VerificationMethod),
+            vec!["did:web:example.com#key-0", "#key-1"]
+        );
+    }
+
+    #[test]
+    fn secp256k1_recovery_method() {
+        let json = serde_json::json!({
+            "id": "did:example:123#vm-1",
+            "controller": "did:example:123",
+            "type": "EcdsaSecp256k1RecoveryMethod2020",
+            "blockchainAccountId": "eip155:1:0xab16a96D359eC26a11e2C2b3d8f8B8942d5Bfcdb"
+        });
+        let vm: VerificationMethod =
+            serde_json::from_value(json.clone()).expect("should deserialize");
+        assert_eq!(
+            vm.method_type,
+            MethodType::EcdsaSecp256k1RecoveryMethod2020 {
+                blockchain_account_id: "eip155:1:0xab16a96D359eC26a11e2C2b3d8f8B8942d5Bfcdb".into()
+            }
+        );
+        assert_eq!(serde_json::to_value(&vm).expect("should serialize"), json);
+
+        // the key is not in the method, but can be used for signing
+        assert_eq!(vm.key_algorithm().expect("should have algorithm"), KeyAlgorithm::Secp256k1);
+        vm.check_purpose(KeyPurpose::AssertionMethod).expect("should allow assertions");
+        let Err(Error::UnsupportedPublicKeyType(_)) = vm.method_type.to_jwk() else {
+            panic!("should have no public key");
+        };
+
+        // a missing account id is malformed
+        let json = serde_json::json!({
+            "id": "did:example:123#vm-1",
+            "controller": "did:example:123",
+            "type": "EcdsaSecp256k1RecoveryMethod2020"
+        });
+        serde_json::from_value::<VerificationMethod>(json).expect_err("should be malformed");
+    }
+
+    #[test]
+    fn method_context() {
+        let json = serde_json::json!({
+            "@context": [
+                "https://w3id.org/security/multikey/v1",
+                {"revoked": "https://w3id.org/security#revoked"}
+            ],
+            "id": "did:example:123#key-0",
+            "controller": "did:example:123",
+            "type": "Multikey",
+            "publicKeyMultibase": "z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK"
+        });
+        let vm: VerificationMethod =
+            serde_json::from_value(json.clone()).expect("should deserialize");
+        let Some(Quota::Many(context)) = &vm.context else {
+            panic!("should have a context array");
+        };
+        assert_eq!(context[0], Context::from("https://w3id.org/security/multikey/v1"));
+        assert!(context[1].url().is_none());
+        assert_eq!(serde_json::to_value(&vm).expect("should serialize"), json);
+
+        // a single context is not an array
+        let json = serde_json::json!({
+            "@context": "https://w3id.org/security/multikey/v1",
+            "id": "did:example:123#key-0",
+            "controller": "did:example:123",
+            "type": "Multikey",
+            "publicKeyMultibase": "z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK"
+        });
+        let vm: VerificationMethod = serde_json::from_value(json).expect("should deserialize");
+        assert_eq!(
+            vm.context,
+            Some(Quota::One(Context::from("https://w3id.org/security/multikey/v1")))
+        );
+    }
+
+    #[test]
+    fn diff() {
+        let old: Document =
+            serde_json::from_slice(include_bytes!("web/did-eddsa.json")).expect("should parse");
+        let mut new = old.clone();
+
+        let key = VerificationMethod {
+            id: "#key-2".into(),
+            controller: old.id.clone(),
+            method_type: MethodType::Multikey {
+                public_key_multibase: "z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK".into(),
+            },
+            ..VerificationMethod::default()
+        };
+        new.verification_method.get_or_insert_default().push(key.clone());
+        new.service = Some(vec![Service {
+            id: "#messaging".into(),
+            type_: "DIDCommMessaging".into(),
+            service_endpoint: Quota::One(Kind::String("https://example.com/didcomm".into())),
+        }]);
+        let removed = Service {
+            id: format!("{}#linked-domain", old.id),
+            type_: "LinkedDomains".into(),
+            service_endpoint: Quota::One(Kind::String("https://example.com".into())),
+        };
+        let mut old = old;
+        old.service =
+            Some(vec![removed, new.service.as_ref().expect("should have service")[0].clone()]);
+
+        let diff = old.diff(&new);
+        assert_eq!(
+            diff,
+            DocumentDiff {
+                added_methods: vec![key],
+                removed_services: vec![format!("{}#linked-domain", old.id)],
+                ..DocumentDiff::default()
+            }
+        );
+
+        // controller and alsoKnownAs changes
+        new = old.clone();
+        new.controller = Some(Quota::One("did:example:controller".into()));
+        new.also_known_as = Some(vec!["https://example.com".into()]);
+        let diff = old.diff(&new);
+        assert_eq!(diff.controller, Some(vec!["did:example:controller".to_string()]));
+        assert_eq!(diff.also_known_as, Some(vec!["https://example.com".to_string()]));
+        assert!(diff.added_methods.is_empty() && diff.removed_services.is_empty());
+
+        assert!(old.diff(&old.clone()).is_empty());
+    }
+
+    #[test]
+    fn all_public_keys() {
+        let document: Document = serde_json::from_value(serde_json::json!({
+            "id": "did:web:example.com",
+            "verificationMethod": [{
+                "id": "#key-0",
+                "controller": "did:web:example.com",
+                "type": "Multikey",
+                "publicKeyMultibase": "z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK"
+            }],
+            "authentication": ["#key-0"],
+            "assertionMethod": [{
+                "id": "did:web:example.com#key-1",
+                "controller": "did:web:example.com",
+                "type": "JsonWebKey",
+                "publicKeyJwk": {
+                    "kty": "OKP",
+                    "crv": "Ed25519",
+                    "x": "11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo"
+                }
+            }],
+            "capabilityInvocation": [{
+                "id": "#key-1",
+                "controller": "did:web:example.com",
+                "type": "JsonWebKey",
+                "publicKeyJwk": {
+                    "kty": "OKP",
+                    "crv": "Ed25519",
+                    "x": "11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo"
+                }
+            }]
+        }))
+        .expect("should deserialize");
+
+        let keys = document.all_public_keys().expect("should convert keys");
+        let ids = keys.iter().map(|(id, _)| id.as_str()).collect::<Vec<_>>();
+        assert_eq!(ids, ["did:web:example.com#key-0", "did:web:example.com#key-1"]);
+
+        let multikey = multikey_to_jwk("z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK")
+            .expect("should decode");
+        assert_eq!(keys[0].1, multikey);
+        assert_eq!(keys[1].1.crv, Curve::Ed25519);
+        assert_eq!(keys[1].1.x, "11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo");
+    }
+
+    #[test]
+    fn public_key_for_kid() {
+        let document: Document = serde_json::from_value(serde_json::json!({
+            "id": "did:web:example.com",
+            "verificationMethod": [{
+                "id": "did:web:example.com#key-0",
+                "controller": "did:web:example.com",
+                "type": "Multikey",
+                "publicKeyMultibase": "z6Mkj8Jr1rg3YjVWWhg7ahEYJibqhjBgZt1pDCbT4Lv7D4HX"
+            }],
+            "assertionMethod": [{
+                "id": "#key-1",
+                "controller": "did:web:example.com",
+                "type": "JsonWebKey",
+                "publicKeyJwk": {
+                    "kty": "OKP",
+                    "crv": "Ed25519",
+                    "x": "q6rjRnEH_XK_J0fAQFpWBUKIrPxdGQnpPNwP5XJzu8s"
+                }
+            }]
+        }))
+        .expect("should deserialize");
+
+        let multikey = MethodType::Multikey {
+            public_key_multibase: "z6Mkj8Jr1rg3YjVWWhg7ahEYJibqhjBgZt1pDCbT4Lv7D4HX".into(),
+        };
+        let expected = multikey.to_jwk().expect("should convert");
+        assert_eq!(
+            document.public_key_for_kid("did:web:example.com#key-0"),
+            Some(expected.clone())
+        );
+        assert_eq!(document.public_key_for_kid("#key-0"), Some(expected));
+
+        let jwk = document.public_key_for_kid("did:web:example.com#key-1").expect("should find");
+        assert_eq!(jwk.x, "q6rjRnEH_XK_J0fAQFpWBUKIrPxdGQnpPNwP5XJzu8s");
+        assert!(document.public_key_for_kid("#key-2").is_none());
+        assert!(document.public_key_for_kid("did:web:other.example.com#key-0").is_none());
+    }
+
+    #[test]
+    fn rotate_key() {
+        let mut document: Document = serde_json::from_value(serde_json::json!({
+            "id": "did:web:example.com",
+            "verificationMethod": [{
+                "id": "did:web:example.com#key-0",
+                "controller": "did:web:example.com",
+                "type": "Multikey",
+                "publicKeyMultibase": "z6Mkj8Jr1rg3YjVWWhg7ahEYJibqhjBgZt1pDCbT4Lv7D4HX"
+            }],
+            "authentication": ["did:web:example.com#key-0"],
+            "assertionMethod": ["did:web:example.com#key-0"]
+        }))
+        .expect("should deserialize");
+
+        let new_vm = VerificationMethod {
+            id: "did:web:example.com#key-1".into(),
+            controller: "did:web:example.com".into(),
+            method_type: MethodType::Multikey {
+                public_key_multibase: "z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK".into(),
+            },
+            ..VerificationMethod::default()
+        };
+        document.rotate_key("did:web:example.com#key-0", &new_vm).expect("should rotate");
+
+        assert_eq!(document.verification_method, Some(vec![new_vm]));
+        assert_eq!(
+            document.key_purposes("did:web:example.com#key-1"),
+            vec![KeyPurpose::Authentication, KeyPurpose::AssertionMethod]
+        );
+        assert_eq!(document.key_purposes("did:web:example.com#key-0"), vec![]);
+
+        // an X25519 key cannot take over signing relationships
+        let x25519_vm = VerificationMethod {
+            id: "did:web:example.com#key-2".into(),
+            method_type: MethodType::Multikey {
+                public_key_multibase: "z6LSj72tK8brWgZja8NLRwPigth2T9QRiG1uH9oKZuKjdh9p".into(),
+            },
+            ..VerificationMethod::default()
+        };
+        let Err(Error::InvalidPublicKey(_)) =
+            document.rotate_key("did:web:example.com#key-1", &x25519_vm)
+        else {
+            panic!("should not rotate to a key agreement key");
+        };
+
+        let Err(Error::NotFound(_)) =
+            document.rotate_key("did:web:example.com#key-0", &VerificationMethod::default())
+        else {
+            panic!("should not find rotated key");
+        };
+    }
+
+    #[test]
+    fn try_update() {
+        let mut document: Document =
+            serde_json::from_slice(include_bytes!("web/did-eddsa.json")).expect("should parse");
+        let original = document.clone();
+        let old_id =
+            document.verification_method.as_ref().expect("should have methods")[0].id.clone();
+        let new_vm = VerificationMethod {
+            id: format!("{}#key-2", document.id),
+            controller: document.id.clone(),
+            method_type: MethodType::Multikey {
+                public_key_multibase: "z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK".into(),
+            },
+            ..VerificationMethod::default()
+        };
+
+        // the second change fails, so the first is not applied
+        let Err(Error::NotFound(_)) = document.try_update(|doc| {
+            doc.rotate_key(&old_id, &new_vm)?;
+            doc.rotate_key("#missing", &new_vm)
+        }) else {
+            panic!("should fail to rotate missing key");
+        };
+        assert_eq!(document, original);
+
+        document.try_update(|doc| doc.rotate_key(&old_id, &new_vm)).expect("should update");
+        assert_eq!(document.key_purposes(&new_vm.id), original.key_purposes(&old_id));
+    }
+
+    #[test]
+    fn set_key_purposes() {
+        let mut document: Document = serde_json::from_value(serde_json::json!({
+            "id": "did:web:example.com",
+            "verificationMethod": [{
+                "id": "#key-0",
+                "controller": "did:web:example.com",
+                "type": "Multikey",
+                "publicKeyMultibase": "z6Mkj8Jr1rg3YjVWWhg7ahEYJibqhjBgZt1pDCbT4Lv7D4HX"
+            }],
+            "authentication": ["#key-0"],
+            "keyAgreement": [{
+                "id": "#key-1",
+                "controller": "did:web:example.com",
+                "type": "Multikey",
+                "publicKeyMultibase": "z6LSj72tK8brWgZja8NLRwPigth2T9QRiG1uH9oKZuKjdh9p"
+            }]
+        }))
+        .expect("should deserialize");
+        let methods = document.verification_method.clone();
+
+        // grant a purpose
+        let purposes = [KeyPurpose::Authentication, KeyPurpose::AssertionMethod];
+        document.set_key_purposes("#key-0", &purposes).expect("should set purposes");
+        assert_eq!(document.key_purposes("#key-0"), purposes);
+        assert_eq!(document.verification_method, methods);
+
+        // revoke a purpose
+        document
+            .set_key_purposes("did:web:example.com#key-0", &[KeyPurpose::AssertionMethod])
+            .expect("should set purposes");
+        assert_eq!(document.key_purposes("#key-0"), vec![KeyPurpose::AssertionMethod]);
+        assert_eq!(document.authentication, None);
+
+        // revoking an embedded method's purposes keeps the method
+        document.set_key_purposes("#key-1", &[]).expect("should set purposes");
+        assert_eq!(document.key_agreement, None);
+        assert!(document.public_key_for_kid("#key-1").is_some());
+
+        // purposes must suit the key
+        let Err(Error::InvalidPublicKey(_)) =
+            document.set_key_purposes("#key-0", &[KeyPurpose::KeyAgreement])
+        else {
+            panic!("should reject keyAgreement for an Ed25519 key");
+        };
+        let Err(Error::NotFound(_)) = document.set_key_purposes("#key-2", &[]) else {
+            panic!("should not find method");
+        };
+    }
+
+    #[test]
+    fn rotate_relative_key() {
+        // relationships reference and embed methods by relative id
+        let mut document: Document = serde_json::from_value(serde_json::json!({
+            "id": "did:web:example.com",
+            "verificationMethod": [{
+                "id": "#key-0",
+                "controller": "did:web:example.com",
+                "type": "Multikey",
+                "publicKeyMultibase": "z6Mkj8Jr1rg3YjVWWhg7ahEYJibqhjBgZt1pDCbT4Lv7D4HX"
+            }],
+            "authentication": ["#key-0"],
+            "assertionMethod": [{
+                "id": "#key-0",
+                "controller": "did:web:example.com",
+                "type": "Multikey",
+                "publicKeyMultibase": "z6Mkj8Jr1rg3YjVWWhg7ahEYJibqhjBgZt1pDCbT4Lv7D4HX"
+            }],
+            "capabilityInvocation": ["did:web:example.com#key-0"]
+        }))
+        .expect("should deserialize");
+
+        let purposes = vec![
+            KeyPurpose::Authentication,
+            KeyPurpose::AssertionMethod,
+            KeyPurpose::CapabilityInvocation,
+        ];
+        assert_eq!(document.key_purposes("#key-0"), purposes);
+        assert_eq!(document.key_purposes("did:web:example.com#key-0"), purposes);
+
+        let new_vm = VerificationMethod {
+            id: "did:web:example.com#key-1".into(),
+            controller: "did:web:example.com".into(),
+            method_type: MethodType::Multikey {
+                public_key_multibase: "z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK".into(),
+            },
+            ..VerificationMethod::default()
+        };
+        document.rotate_key("did:web:example.com#key-0", &new_vm).expect("should rotate");
+
+        assert_eq!(document.verification_method, Some(vec![new_vm]));
+        assert_eq!(document.key_purposes("#key-1"), purposes);
+        assert_eq!(document.key_purposes("#key-0"), vec![]);
+    }
+
+    #[test]
+    fn embed_reference_relationships() {
+        let sample: Document =
+            serde_json::from_slice(include_bytes!("web/did-eddsa.json")).expect("should parse");
+        let ids = ["did:web:demo.credibil.io#key-0", "did:web:demo.credibil.io#key-1"];
+        let relationships_eq = |document: &Document| {
+            for id in ids {
+                assert_eq!(document.key_purposes(id), sample.key_purposes(id), "{id}");
+            }
+            for purpose in KeyPurpose::ALL.into_iter().skip(1) {
+                assert_eq!(
+                    document.verification_methods_for(purpose),
+                    sample.verification_methods_for(purpose),
+                    "{purpose}"
+                );
+            }
+        };
+
+        // all relationships reference methods in `verificationMethod`
+        let mut referenced = sample.clone();
+        referenced.reference_relationships();
+        relationships_eq(&referenced);
+        assert_eq!(referenced.verification_method.as_ref().map(Vec::len), Some(2));
+        let kinds = [&referenced.authentication, &referenced.key_agreement];
+        assert!(kinds.into_iter().flatten().flatten().all(|k| matches!(k, Kind::String(_))));
+
+        // all relationships embed their methods
+        let expected = referenced.clone();
+        let mut embedded = referenced;
+        embedded.embed_relationships();
+        relationships_eq(&embedded);
+        assert!(embedded.verification_method.is_none());
+        let kinds = [&embedded.authentication, &embedded.key_agreement];
+        assert!(kinds.into_iter().flatten().flatten().all(|k| matches!(k, Kind::Object(_))));
+
+        // and back again
+        embedded.reference_relationships();
+        assert!(embedded.semantically_eq(&expected));
+    }
+
+    #[test]
+    fn semantically_eq() {
+        let document: Document = serde_json::from_value(serde_json::json!({
+            "@context": ["https://www.w3.org/ns/did/v1"],
+            "id": "did:web:example.com",
+            "controller": "did:web:controller.example.com",
+            "verificationMethod": [{
+                "id": "did:web:example.com#key-0",
+                "controller": "did:web:example.com",
+                "type": "Multikey",
+                "publicKeyMultibase": "z6Mkj8Jr1rg3YjVWWhg7ahEYJibqhjBgZt1pDCbT4Lv7D4HX"
+            }, {
+                "id": "did:web:example.com#key-1",
+                "controller": "did:web:example.com",
+                "type": "Multikey",
+                "publicKeyMultibase": "z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK"
+            }],
+            "authentication": ["did:web:example.com#key-0", "did:web:example.com#key-1"],
+            "service": [{
+                "id": "did:web:example.com#linked-domain",
+                "type": "LinkedDomains",
+                "serviceEndpoint": "https://example.com"
+            }]
+        }))
+        .expect("should deserialize");
+
+        // reordered, with arrays in place of single values
+        let reshaped: Document = serde_json::from_value(serde_json::json!({
+            "@context": ["https://www.w3.org/ns/did/v1"],
+            "id": "did:web:example.com",
+            "controller": ["did:web:controller.example.com"],
+            "verificationMethod": [{
+                "id": "did:web:example.com#key-1",
+                "controller": "did:web:example.com",
+                "type": "Multikey",
+                "publicKeyMultibase": "z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK"
+            }, {
+                "id": "did:web:example.com#key-0",
+                "controller": "did:web:example.com",
+                "type": "Multikey",
+                "publicKeyMultibase": "z6Mkj8Jr1rg3YjVWWhg7ahEYJibqhjBgZt1pDCbT4Lv7D4HX"
+            }],
+            "authentication": ["did:web:example.com#key-1", "did:web:example.com#key-0"],
+            "service": [{
+                "id": "did:web:example.com#linked-domain",
+                "type": "LinkedDomains",
+                "serviceEndpoint": ["https://example.com"]
+            }]
+        }))
+        .expect("should deserialize");
+
+        assert_ne!(document, reshaped);
+        assert!(document.semantically_eq(&reshaped));
+        assert!(reshaped.semantically_eq(&document));
+
+        let mut changed = reshaped;
+        changed.authentication = Some(vec![Kind::String("did:web:example.com#key-0".into())]);
+        assert!(!document.semantically_eq(&changed));
+    }
+
+    #[test]
+    fn redact_private_keys() {
+        let mut json = serde_json::json!({
+            "id": "did:web:example.com",
+            "verificationMethod": [{
+                "id": "did:web:example.com#key-0",
+                "controller": "did:web:example.com",
+                "type": "JsonWebKey",
+                "publicKeyJwk": {
+                    "kty": "OKP",
+                    "crv": "Ed25519",
+                    "x": "q6rjRnEH_XK_J0fAQFpWBUKIrPxdGQnpPNwP5XJzu8s",
+                    "d": "Kb6K3_l1jiOuZHGUYvwTN6Gj_8uMCVU1VJk5LEYt-Ic"
+                }
+            }],
+            "service": [{
+                "id": "did:web:example.com#keys",
+                "type": "KeyService",
+                "serviceEndpoint": {"keys": [{"kty": "oct", "k": "c2VjcmV0"}]}
+            }]
+        });
+
+        // a deserialized document cannot hold the private key
+        let document: Document = serde_json::from_value(json.clone()).expect("should deserialize");
+        let serialized = serde_json::to_value(&document).expect("should serialize");
+        assert!(serialized["verificationMethod"][0]["publicKeyJwk"].get("d").is_none());
+
+        let Err(Error::InvalidPublicKey(e)) = super::redact_private_keys(&mut json) else {
+            panic!("should report private key");
+        };
+        assert!(e.contains("did:web:example.com#key-0"));
+        assert!(json["verificationMethod"][0]["publicKeyJwk"].get("d").is_none());
+        assert!(json["service"][0]["serviceEndpoint"]["keys"][0].get("k").is_none());
+
+        // already redacted
+        super::redact_private_keys(&mut json).expect("should be public only");
+    }
+
+    #[test]
+    fn mixed_service_endpoints() {
+        const SERVICE: &str = r#"{"id":"did:example:123#didcomm","type":"DIDCommMessaging","serviceEndpoint":["https://a.example",{"uri":"https://b.example"}]}"#;
+
+        let service: Service = serde_json::from_str(SERVICE).expect("should deserialize");
+        assert_eq!(
+            service.service_endpoint,
+            Quota::Many(vec![
+                Kind::String("https://a.example".into()),
+                Kind::Object(serde_json::json!({"uri": "https://b.example"})),
+            ])
+        );
+
+        let serialized = serde_json::to_string(&service).expect("should serialize");
+        assert_eq!(serialized, SERVICE);
+    }
+
+    #[test]
+    fn service_endpoint_map() {
+        const SERVICE: &str = r#"{"id":"did:example:123#didcomm","type":"DIDCommMessaging","serviceEndpoint":{"accept":["didcomm/v2",["nested",1]],"enabled":true,"priority":1,"uri":"https://example.com/didcomm","weight":0.75}}"#;
+
+        let service: Service = serde_json::from_str(SERVICE).expect("should deserialize");
+        let Quota::One(Kind::Object(endpoint)) = &service.service_endpoint else {
+            panic!("endpoint should be a single object");
+        };
+        assert_eq!(endpoint["priority"].as_u64(), Some(1));
+        assert_eq!(endpoint["weight"].as_f64(), Some(0.75));
+        assert_eq!(endpoint["enabled"].as_bool(), Some(true));
+        assert_eq!(endpoint["accept"][1][1].as_i64(), Some(1));
+
+        let serialized = serde_json::to_string(&service).expect("should serialize");
+        assert_eq!(serialized, SERVICE);
+    }
+
+    #[test]
+    fn unsupported_codec() {
+        let multikey =
+            multibase::encode(Base::Base58Btc, [[0x12, 0x00].as_slice(), &[0; 32]].concat());
+        let method_type = MethodType::Multikey {
+            public_key_multibase: multikey,
+        };
+        let Err(Error::UnsupportedPublicKeyType(_)) = method_type.to_jwk() else {
+            panic!("should be unsupported");
+        };
+    }
+
+    #[test]
+    fn duplicate_contexts() {
+        let mut document: Document = serde_json::from_value(serde_json::json!({
+            "@context": [
+                DID_CONTEXT,
+                {"@vocab": "https://example.com/vocab#", "@version": 1.1},
+                "https://w3id.org/security/multikey/v1",
+                DID_CONTEXT,
+                {"@version": 1.1, "@vocab": "https://example.com/vocab#"},
+                DID_CONTEXT
+            ],
+            "id": "did:example:123"
+        }))
+        .expect("should deserialize");
+
+        let vocab = Context::Inline(Map::from_iter([
+            ("@vocab".into(), Value::String("https://example.com/vocab#".into())),
+            ("@version".into(), serde_json::json!(1.1)),
+        ]));
+        assert_eq!(document.duplicate_contexts(), vec![&Context::from(DID_CONTEXT), &vocab]);
+
+        document.dedup_contexts();
+        assert_eq!(
+            document.context,
+            vec![
+                Context::from(DID_CONTEXT),
+                vocab,
+                Context::from("https://w3id.org/security/multikey/v1")
+            ]
+        );
+        assert_eq!(document.duplicate_contexts(), Vec::<&Context>::new());
+    }
+
+    #[test]
+    fn contexts() {
+        let vocab = Context::Inline(Map::from_iter([(
+            "@vocab".into(),
+            Value::String("https://example.com/vocab#".into()),
+        )]));
+
+        let mut document = Document::default();
+        document.add_context(DID_CONTEXT);
+        document.add_context("https://w3id.org/security/multikey/v1");
+        document.add_context(vocab.clone());
+
+        // duplicates are ignored
+        document.add_context(DID_CONTEXT.to_string());
+        document.add_context(vocab.clone());
+        assert_eq!(document.context.len(), 3);
+
+        assert!(document.has_context(DID_CONTEXT));
+        assert!(document.has_context("https://w3id.org/security/multikey/v1"));
+        assert!(!document.has_context("https://w3id.org/security/data-integrity/v1"));
+        assert!(!document.has_context("https://example.com/vocab#"));
+
+        // URLs and inline contexts are distinguished when deserializing
+        let json = serde_json::to_value(&document).expect("should serialize");
+        assert_eq!(
+            json["@context"],
+            serde_json::json!([
+                DID_CONTEXT,
+                "https://w3id.org/security/multikey/v1",
+                {"@vocab": "https://example.com/vocab#"}
+            ])
+        );
+        let document: Document = serde_json::from_value(json).expect("should deserialize");
+        assert_eq!(document.context[1].url(), Some("https://w3id.org/security/multikey/v1"));
+        assert_eq!(document.context[2], vocab);
+    }
+
+    #[test]
+    fn representation() {
+        let document = Document {
+            context: vec![
+                Context::from("https://w3id.org/security/multikey/v1"),
+                Context::from(DID_CONTEXT),
+            ],
+            id: "did:example:123".into(),
+            also_known_as: Some(vec!["https://example.com".into()]),
+            ..Document::default()
+        };
+
+        // application/did+json
+        let json = document.to_representation(&ContentType::DidJson);
+        let serialized = serde_json::to_value(&json).expect("should serialize");
+        assert!(serialized.get("@context").is_none());
+        let deserialized: Document =
+            serde_json::from_value(serialized).expect("should deserialize");
+        assert_eq!(deserialized, json);
+
+        // application/did+ld+json
+        let ld_json = json.to_representation(&ContentType::DidLdJson);
+        assert_eq!(ld_json.context, vec![Context::from(DID_CONTEXT)]);
+
+        let ld_json = document.to_representation(&ContentType::DidLdJson);
+        let serialized = serde_json::to_value(&ld_json).expect("should serialize");
+        assert_eq!(
+            serialized["@context"],
+            serde_json::json!([DID_CONTEXT, "https://w3id.org/security/multikey/v1"])
+        );
+        let deserialized: Document =
+            serde_json::from_value(serialized).expect("should deserialize");
+        assert_eq!(deserialized, ld_json);
+        assert_eq!(deserialized.id, document.id);
+        assert_eq!(deserialized.also_known_as, document.also_known_as);
+    }
+
+    #[test]
+    fn check_purposes() {
+        const DID: &str = "did:example:123";
+        let ed25519 = VerificationMethod {
+            id: format!("{DID}#key-0"),
+            controller: DID.into(),
+            method_type: MethodType::Multikey {
+                public_key_multibase: "z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK".into(),
+            },
+            ..VerificationMethod::default()
+        };
+        let x25519 = VerificationMethod {
+            id: format!("{DID}#key-1"),
+            controller: DID.into(),
+            method_type: MethodType::Multikey {
+                public_key_multibase: "z6LSj72tK8brWgZja8NLRwPigth2T9QRiG1uH9oKZuKjdh9p".into(),
+            },
+            ..VerificationMethod::default()
+        };
+
+        // signing key used for key agreement
+        let document = Document {
+            id: DID.into(),
+            verification_method: Some(vec![ed25519.clone()]),
+            key_agreement: Some(vec![Kind::String(ed25519.id.clone())]),
+            ..Document::default()
+        };
+        let Err(Error::InvalidPublicKey(msg)) = document.check_purposes() else {
+            panic!("should reject Ed25519 key agreement");
+        };
+        assert!(msg.contains("keyAgreement"));
+
+        // encryption key used for authentication
+        let document = Document {
+            id: DID.into(),
+            authentication: Some(vec![Kind::Object(x25519.clone())]),
+            ..Document::default()
+        };
+        let Err(Error::InvalidPublicKey(msg)) = document.check_purposes() else {
+            panic!("should reject X25519 authentication");
+        };
+        assert!(msg.contains("authentication"));
+
+        // valid mix of signing and encryption keys
+        let document = Document {
+            id: DID.into(),
+            verification_method: Some(vec![ed25519.clone()]),
+            authentication: Some(vec![Kind::String(ed25519.id.clone())]),
+            assertion_method: Some(vec![Kind::String(ed25519.id)]),
+            key_agreement: Some(vec![Kind::Object(x25519)]),
+            ..Document::default()
+        };
+        document.check_purposes().expect("should be valid");
+    }
+
+    #[test]
+    fn builder() {
+        const DID: &str = "did:web:demo.credibil.io";
+        let vm = |id: &str, multibase: &str| VerificationMethod {
+            id: format!("{DID}#{id}"),
+            controller: DID.into(),
+            method_type: MethodType::Multikey {
+                public_key_multibase: multibase.into(),
+            },
+            ..VerificationMethod::default()
+        };
+
+        let document = Document::builder(DID)
+            .add_context("https://w3id.org/security/data-integrity/v1")
+            .add_verification_method(
+                vm("key-0", "z6Mkr1NtupNezZtcUAMxJ79HPex6ZTR9RnGh8xfV257ZQdss"),
+                &[
+                    KeyPurpose::VerificationMethod,
+                    KeyPurpose::Authentication,
+                    KeyPurpose::AssertionMethod,
+                    KeyPurpose::CapabilityInvocation,
+                    KeyPurpose::CapabilityDelegation,
+                ],
+            )
+            .add_verification_method(
+                vm("key-1", "z6LSo1jPr1g7qoR9UsYWoJs66FiaoHGAK5cJzBGSvQpuVEQv"),
+                &[KeyPurpose::KeyAgreement],
+            )
+            .build()
+            .expect("should build");
+
+        let expected: Document =
+            serde_json::from_slice(include_bytes!("web/did-eddsa.json")).expect("should parse");
+        assert_eq!(document, expected);
+
+        // relationships must reference methods in the document
+        let Err(Error::NotFound(msg)) =
+            Document::builder(DID).add_reference("#key-0", &[KeyPurpose::Authentication]).build()
+        else {
+            panic!("should reject unknown verification method");
+        };
+        assert!(msg.contains("#key-0"));
+    }
+
+    #[tokio::test]
+    async fn verify_jws() {
+        use credibil_infosec::Signer;
+
+        use crate::key::DidKey;
+        use crate::testing::MemoryKeyRing;
+        use crate::testing::test::key_agreement_id;
+
+        let keyring = MemoryKeyRing::ed25519();
+        let kid = keyring.verification_method().await.expect("should have kid");
+        let did = kid.split_once('#').expect("should be DID URL").0;
+        let resolved = DidKey::resolve(did).expect("should resolve");
+        let document = resolved.document.expect("should have document");
+
+        let sign = |header: Value, payload: &[u8]| {
+            let header = serde_json::to_vec(&header).expect("should serialize");
+            let input = format!(
+                "{}.{}",
+                Base64UrlUnpadded::encode_string(&header),
+                Base64UrlUnpadded::encode_string(payload)
+            );
+            let keyring = keyring.clone();
+            async move {
+                let signature = keyring.try_sign(input.as_bytes()).await.expect("should sign");
+                format!("{input}.{}", Base64UrlUnpadded::encode_string(&signature))
+            }
+        };
+
+        let jws = sign(serde_json::json!({"alg": "EdDSA", "kid": kid}), b"hello").await;
+        assert_eq!(document.verify_jws(&jws).expect("should verify"), b"hello");
+
+        // tampered payload
+        let parts = jws.split('.').collect::<Vec<_>>();
+        let tampered =
+            format!("{}.{}.{}", parts[0], Base64UrlUnpadded::encode_string(b"goodbye"), parts[2]);
+        let Err(Error::InvalidProof(e)) = document.verify_jws(&tampered) else {
+            panic!("should reject tampered payload");
+        };
+        assert_eq!(e, "JWS signature is invalid");
+
+        // malformed JWS
+        let Err(Error::InvalidInput(_)) = document.verify_jws("not.a-jws") else {
+            panic!("should reject malformed JWS");
+        };
+
+        // unknown key
+        let header = serde_json::json!({"alg": "EdDSA", "kid": format!("{did}#key-9")});
+        let Err(Error::NotFound(_)) = document.verify_jws(&sign(header, b"hello").await) else {
+            panic!("should not find verification method");
+        };
+
+        // algorithm mismatch
+        let header = serde_json::json!({"alg": "ES256K", "kid": kid});
+        let Err(Error::InvalidPublicKey(_)) = document.verify_jws(&sign(header, b"hello").await)
+        else {
+            panic!("should reject algorithm");
+        };
+
+        // key agreement keys cannot sign
+        let header = serde_json::json!({"alg": "", "kid": key_agreement_id(&document)});
+        let Err(Error::InvalidPublicKey(e)) = document.verify_jws(&sign(header, b"hello").await)
+        else {
+            panic!("should reject X25519 key");
+        };
+        assert!(e.contains("X25519"));
+    }
+
+    #[test]
+    fn builder_service_order() {
+        const DID: &str = "did:web:demo.credibil.io";
+        let service = |id: &str| Service {
+            id: id.into(),
+            type_: "LinkedDomains".into(),
+            service_endpoint: Quota::One(Kind::String(format!("https://demo.credibil.io/{id}"))),
+        };
+        let build = |ids: [&str; 3], sort| {
+            let builder = ids
+                .into_iter()
+                .fold(Document::builder(DID), |builder, id| builder.add_service(service(id)));
+            let document = builder.sort_services(sort).build().expect("should build");
+            serde_json::to_string(&document).expect("should serialize")
+        };
+
+        let forward = ["#a", "did:web:demo.credibil.io#b", "#c"];
+        let reverse = ["#c", "did:web:demo.credibil.io#b", "#a"];
+        assert_eq!(build(forward, true), build(reverse, true));
+        assert_ne!(build(forward, false), build(reverse, false));
+        assert_eq!(build(forward, false), build(forward, true));
+    }
+
+    #[test]
+    fn builder_id_collision() {
+        const DID: &str = "did:web:demo.credibil.io";
+        let vm = VerificationMethod {
+            id: format!("{DID}#key-0"),
+            controller: DID.into(),
+            method_type: MethodType::Multikey {
+                public_key_multibase: "z6Mkr1NtupNezZtcUAMxJ79HPex6ZTR9RnGh8xfV257ZQdss".into(),
+            },
+            ..VerificationMethod::default()
+        };
+        let service = |id: &str| Service {
+            id: id.into(),
+            type_: "LinkedDomains".into(),
+            service_endpoint: Quota::One(Kind::String("https://demo.credibil.io".into())),
+        };
+
+        // a relative service id colliding with a verification method id
+        let Err(Error::InvalidInput(msg)) = Document::builder(DID)
+            .add_verification_method(vm.clone(), &[KeyPurpose::Authentication])
+            .add_service(service("#key-0"))
+            .build()
+        else {
+            panic!("should reject colliding ids");
+        };
+        assert_eq!(msg, format!("service id {DID}#key-0 is also a verification method id"));
+
+        Document::builder(DID)
+            .add_verification_method(vm, &[KeyPurpose::VerificationMethod])
+            .add_service(service("#linked-domain"))
+            .build()
+            .expect("should build");
+    }
+
+    #[test]
+    fn builder_order() {
+        const DID: &str = "did:web:demo.credibil.io";
+        let vm = |id: &str, multibase: &str| VerificationMethod {
+            id: format!("{DID}#{id}"),
+            controller: DID.into(),
+            method_type: MethodType::Multikey {
+                public_key_multibase: multibase.into(),
+            },
+            ..VerificationMethod::default()
+        };
+        let key_0 = vm("key-0", "z6Mkr1NtupNezZtcUAMxJ79HPex6ZTR9RnGh8xfV257ZQdss");
+        let key_1 = vm("key-1", "z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK");
+        let purposes = [KeyPurpose::VerificationMethod, KeyPurpose::Authentication];
+
+        let forward = Document::builder(DID)
+            .add_verification_method(key_0.clone(), &purposes)
+            .add_verification_method(key_1.clone(), &purposes)
+            .build()
+            .expect("should build");
+        let reverse = Document::builder(DID)
+            .add_verification_method(key_1, &purposes)
+            .add_verification_method(key_0, &purposes)
+            .build()
+            .expect("should build");
+
+        assert_eq!(
+            serde_json::to_string(&forward).expect("should serialize"),
+            serde_json::to_string(&reverse).expect("should serialize")
+        );
+        assert_eq!(
+            forward.authentication,
+            Some(vec![Kind::String(format!("{DID}#key-0")), Kind::String(format!("{DID}#key-1"))])
+        );
+    }
+
+    #[test]
+    fn content_hash_id() {
+        let document: Document =
+            serde_json::from_slice(include_bytes!("web/did-eddsa.json")).expect("should parse");
+        let id = super::content_hash_id(&document).expect("should hash");
+        assert_eq!(id, "zQmcJMCTc58wC83NJjEz9R2a9yXepSQYmCmgUnqHWnogA7r");
+
+        // member order does not affect the canonical form
+        let value = serde_json::json!({"b": [1, "\u{e9}"], "a": {"d": null, "c": true}});
+        assert_eq!(canonical_json(&value), r#"{"a":{"c":true,"d":null},"b":[1,"é"]}"#);
+    }
+
+    #[test]
+    fn create_options_defaults() {
+        // options serialized before later options were added
+        let options: CreateOptions = serde_json::from_value(serde_json::json!({
+            "publicKeyFormat": "Multikey",
+            "defaultContext": "https://www.w3.org/ns/did/v1",
+            "enableExperimentalPublicKeyTypes": false,
+            "enableEncryptionKeyDerivation": true
+        }))
+        .expect("should deserialize");
+        assert!(options.enable_encryption_key_derivation);
+        assert_eq!(options.key_agreement_id_style, KeyAgreementIdStyle::default());
+        assert_eq!(options.verification_method_id, VerificationMethodId::default());
+        assert_eq!(options.profile, Profile::default());
+    }
+
+    #[test]
+    fn canonical_numbers() {
+        // RFC 8785, appendix B
+        let vectors = [
+            (0x0000_0000_0000_0000, "0"),
+            (0x8000_0000_0000_0000, "0"),
+            (0x0000_0000_0000_0001, "5e-324"),
+            (0x8000_0000_0000_0001, "-5e-324"),
+            (0x7fef_ffff_ffff_ffff, "1.7976931348623157e+308"),
+            (0xffef_ffff_ffff_ffff, "-1.7976931348623157e+308"),
+            (0x4340_0000_0000_0000, "9007199254740992"),
+            (0xc340_0000_0000_0000, "-9007199254740992"),
+            (0x4430_0000_0000_0000, "295147905179352830000"),
+            (0x44b5_2d02_c7e1_4af5, "9.999999999999997e+22"),
+            (0x44b5_2d02_c7e1_4af6, "1e+23"),
+            (0x44b5_2d02_c7e1_4af7, "1.0000000000000001e+23"),
+            (0x444b_1ae4_d6e2_ef4e, "999999999999999700000"),
+            (0x444b_1ae4_d6e2_ef4f, "999999999999999900000"),
+            (0x444b_1ae4_d6e2_ef50, "1e+21"),
+            (0x3eb0_c6f7_a0b5_ed8c, "9.999999999999997e-7"),
+            (0x3eb0_c6f7_a0b5_ed8d, "0.000001"),
+            (0x41b3_de43_5555_5553, "333333333.3333332"),
+            (0x41b3_de43_5555_5554, "333333333.33333325"),
+            (0x41b3_de43_5555_5555, "333333333.3333333"),
+            (0x41b3_de43_5555_5556, "333333333.3333334"),
+            (0x41b3_de43_5555_5557, "333333333.33333343"),
+            (0xbecb_f647_612f_3696, "-0.0000033333333333333333"),
+            (0x4314_3ff3_c1cb_0959, "1424953923781206.2"),
+        ];
+        for (bits, expected) in vectors {
+            let value = Value::from(f64::from_bits(bits));
+            assert_eq!(canonical_json(&value), expected, "{bits:#018x}");
+        }
+
+        // integers and decimals are both serialized as doubles
+        let value = serde_json::json!([1.0, 10, -0.5, 1e21, 9_007_199_254_740_993_u64]);
+        assert_eq!(canonical_json(&value), "[1,10,-0.5,1e+21,9007199254740992]");
+    }
+
+    #[test]
+    fn same_key() {
+        let jwk = |value: Value| -> PublicKeyJwk {
+            serde_json::from_value(value).expect("should deserialize")
+        };
+        let signing = jwk(serde_json::json!({
+            "kid": "key-0",
+            "kty": "OKP",
+            "crv": "Ed25519",
+            "x": "11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo",
+            "alg": "ECDH-ES",
+            "use": "sig"
+        }));
+        let other = jwk(serde_json::json!({
+            "kid": "did:example:123#key-1",
+            "kty": "OKP",
+            "crv": "Ed25519",
+            "x": "11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo"
+        }));
+        assert_ne!(signing, other);
+        assert!(super::same_key(&signing, &other));
+        assert_eq!(normalize_jwk(&signing), normalize_jwk(&other));
+        assert_eq!(normalize_jwk(&signing).kid, None);
+
+        let different = PublicKeyJwk {
+            x: "Lm_M42cB3HkUiODQsXRcweM6TByfzEHGO9ND274JcOY".into(),
+            ..other.clone()
+        };
+        assert!(!super::same_key(&signing, &different));
+        let x25519 = PublicKeyJwk {
+            crv: Curve::X25519,
+            ..other
+        };
+        assert!(!super::same_key(&signing, &x25519));
+    }
+
+    #[test]
+    fn jwk_thumbprint() {
+        // RFC 8037, appendix A.3
+        let jwk = PublicKeyJwk {
+            kty: KeyType::Okp,
+            crv: Curve::Ed25519,
+            x: "11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo".into(),
+            ..PublicKeyJwk::default()
+        };
+        let thumbprint = super::jwk_thumbprint(&jwk).expect("should compute thumbprint");
+        assert_eq!(thumbprint, "kPrK_qmxVWaYVA9wwBF6Iuo3vVzz7TxHCTwXBygrS4k");
+    }
+
+    #[test]
+    fn service_endpoint_validation() {
+        const DID: &str = "did:example:123";
+        let build = |endpoint: Kind<Value>| {
+            Document::builder(DID)
+                .add_service(Service {
+                    id: format!("{DID}#service-0"),
+                    type_: "LinkedDomains".into(),
+                    service_endpoint: Quota::One(endpoint),
+                })
+                .build()
+        };
+
+        build(Kind::String("https://example.com/endpoint".into())).expect("should build");
+
+        let Err(Error::InvalidInput(e)) = build(Kind::String("/endpoint".into())) else {
+            panic!("should reject relative URL");
+        };
+        assert!(e.contains("invalid endpoint '/endpoint'"));
+
+        // DIDComm endpoints are checked by their `uri` member
+        let didcomm = serde_json::json!({
+            "uri": "https://example.com/didcomm",
+            "accept": ["didcomm/v2"],
+            "routingKeys": ["did:example:456#key-1"]
+        });
+        build(Kind::Object(didcomm)).expect("should build");
+        let didcomm = serde_json::json!({"uri": "example.com/didcomm", "accept": ["didcomm/v2"]});
+        let Err(Error::InvalidInput(_)) = build(Kind::Object(didcomm)) else {
+            panic!("should reject malformed uri");
+        };
+    }
+
+    #[test]
+    fn service_id_and_type_validation() {
+        const DID: &str = "did:example:123";
+        let build = |id: String, type_: &str| {
+            Document::builder(DID)
+                .add_service(Service {
+                    id,
+                    type_: type_.into(),
+                    service_endpoint: Quota::One(Kind::String("https://example.com".into())),
+                })
+                .build()
+        };
+
+        let Err(Error::InvalidInput(e)) = build(format!("{DID}#service-0"), " ") else {
+            panic!("should reject empty type");
+        };
+        assert!(e.contains("has no type"));
+
+        let id = format!("{DID}#{}", "a".repeat(MAX_SERVICE_ID_LENGTH));
+        let Err(Error::InvalidInput(e)) = build(id, "LinkedDomains") else {
+            panic!("should reject over-length id");
+        };
+        assert!(e.starts_with("service id must be"));
+
+        let id = format!("{DID}#{}", "a".repeat(MAX_SERVICE_ID_LENGTH - DID.len() - 1));
+        build(id, "LinkedDomains").expect("should build");
+    }
+
+    #[test]
+    fn add_service() {
+        let service = |endpoint: &str| Service {
+            id: "did:example:123#linked-domain".into(),
+            type_: "LinkedDomains".into(),
+            service_endpoint: Quota::One(Kind::String(endpoint.into())),
+        };
+        let mut document = Document {
+            id: "did:example:123".into(),
+            ..Document::default()
+        };
+
+        document.add_service(service("https://example.com"));
+        document.add_service(service("https://example.com"));
+        assert_eq!(document.service, Some(vec![service("https://example.com")]));
+
+        // a service with the same id is replaced
+        document.add_service(service("https://example.org"));
+        assert_eq!(document.service, Some(vec![service("https://example.org")]));
+    }
+
+    #[test]
+    fn compact() {
+        let verbose: Document = serde_json::from_value(serde_json::json!({
+            "@context": ["https://www.w3.org/ns/did/v1", "https://www.w3.org/ns/did/v1"],
+            "id": "did:example:123",
+            "alsoKnownAs": [],
+            "controller": ["did:example:456", "did:example:456"],
+            "service": [{
+                "id": "did:example:123#linked-domain",
+                "type": "LinkedDomains",
+                "serviceEndpoint": ["https://example.com"]
+            }],
+            "verificationMethod": [{
+                "id": "did:example:123#key-0",
+                "type": "Multikey",
+                "controller": "did:example:123",
+                "publicKeyMultibase": "z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK"
+            }],
+            "authentication": ["did:example:123#key-0"],
+            "assertionMethod": [],
+            "keyAgreement": []
+        }))
+        .expect("should deserialize");
+
+        let compact = verbose.compact();
+        assert!(compact.semantically_eq(&verbose));
+        assert_eq!(compact.context.len(), 1);
+        assert_eq!(compact.controller, Some(Quota::One("did:example:456".into())));
+        assert!(compact.also_known_as.is_none());
+        assert!(compact.assertion_method.is_none());
+        assert!(compact.key_agreement.is_none());
+
+        let service = &compact.service.as_ref().expect("should have service")[0];
+        assert_eq!(
+            service.service_endpoint,
+            Quota::One(Kind::String("https://example.com".into()))
+        );
+
+        let size = |d: &Document| serde_json::to_vec(d).expect("should serialize").len();
+        assert!(size(&compact) < size(&verbose));
+    }
+
+    #[test]
+    fn remove_services_by_type() {
+        let service = |id: &str, type_: &str| Service {
+            id: format!("did:example:123#{id}"),
+            type_: type_.into(),
+            service_endpoint: Quota::One(Kind::String("https://example.com".into())),
+        };
+        let mut document = Document {
+            id: "did:example:123".into(),
+            service: Some(vec![
+                service("domain-1", "LinkedDomains"),
+                service("didcomm", "DIDCommMessaging"),
+                service("domain-2", "LinkedDomains"),
+            ]),
+            ..Document::default()
+        };
+
+        document.remove_services_by_type(&["LinkedDomains".into()]);
+        let services = document.service.as_ref().expect("should have services");
+        assert_eq!(services.len(), 1);
+        assert_eq!(services[0].id, "did:example:123#didcomm");
+
+        document.remove_services_by_type(&["DIDCommMessaging".into()]);
+        assert!(document.service.is_none());
+    }
+
+    #[test]
+    fn remove_verification_methods_by_type() {
+        const DID: &str = "did:example:123";
+        let multikey = VerificationMethod {
+            id: format!("{DID}#key-0"),
+            controller: DID.into(),
+            method_type: MethodType::Multikey {
+                public_key_multibase: "z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK".into(),
+            },
+            ..VerificationMethod::default()
+        };
+        let jwk = VerificationMethod {
+            id: format!("{DID}#key-1"),
+            controller: DID.into(),
+            method_type: MethodType::JsonWebKey {
+                public_key_jwk: PublicKeyJwk::default(),
+            },
+            ..VerificationMethod::default()
+        };
+        let mut document = Document {
+            id: DID.into(),
+            verification_method: Some(vec![multikey.clone(), jwk.clone()]),
+            authentication: Some(vec![
+                Kind::String(multikey.id.clone()),
+                Kind::String(jwk.id.clone()),
+            ]),
+            key_agreement: Some(vec![Kind::Object(jwk)]),
+            ..Document::default()
+        };
+
+        document.remove_verification_methods_by_type(&["JsonWebKey".into()]);
+        assert_eq!(document.verification_method, Some(vec![multikey.clone()]));
+        assert_eq!(document.authentication, Some(vec![Kind::String(multikey.id)]));
+        assert!(document.key_agreement.is_none());
+    }
+}
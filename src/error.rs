@@ -24,6 +24,32 @@ pub enum Error {
     #[error("invalidDidUrl")]
     InvalidDidUrl(String),
 
+    /// The DID document could not be retrieved because of a network or
+    /// transport failure, such as a refused connection or timeout. The
+    /// underlying error is preserved as the error's source.
+    #[error("networkError")]
+    NetworkError(#[source] anyhow::Error),
+
+    /// The resolved content does not match the hashlink (`hl`) DID parameter
+    /// used to dereference it.
+    #[error("integrityError")]
+    IntegrityError(String),
+
+    /// An input to the operation, such as a service or verification method
+    /// supplied for a DID document, is invalid.
+    #[error("invalidInput")]
+    InvalidInput(String),
+
+    /// A proof is invalid or does not authorize the operation: its signature
+    /// does not verify, or it was made with a key the DID's controller has not
+    /// authorized for the proof's purpose.
+    #[error("invalidProof")]
+    InvalidProof(String),
+
+    /// The DID has been deactivated, so its document cannot be changed.
+    #[error("deactivated")]
+    Deactivated(String),
+
     // ---- Creation Errors ----  //
     /// The byte length of raw public key does not match that expected for the
     /// associated multicodecValue.
@@ -44,10 +70,14 @@ pub enum Error {
 }
 
 impl Error {
-    /// Returns the error code.
+    /// Returns the error code. Unspecified errors are reported as
+    /// `internalError`.
     #[must_use]
     pub fn code(&self) -> String {
-        self.to_string()
+        match self {
+            Self::Other(_) => "internalError".into(),
+            _ => self.to_string(),
+        }
     }
 
     /// Returns the associated error message.
@@ -58,11 +88,15 @@ impl Error {
             | Self::InvalidDid(msg)
             | Self::NotFound(msg)
             | Self::InvalidDidUrl(msg)
+            | Self::IntegrityError(msg)
+            | Self::InvalidInput(msg)
+            | Self::InvalidProof(msg)
+            | Self::Deactivated(msg)
             | Self::RepresentationNotSupported(msg)
             | Self::InvalidPublicKeyLength(msg)
             | Self::InvalidPublicKey(msg)
             | Self::UnsupportedPublicKeyType(msg) => msg.clone(),
-            Self::Other(err) => err.to_string(),
+            Self::NetworkError(err) | Self::Other(err) => err.to_string(),
         }
     }
 }
@@ -82,4 +116,11 @@ mod test {
         let err = Error::MethodNotSupported("Method not supported".into());
         assert_eq!(err.message(), "Method not supported");
     }
+
+    #[test]
+    fn internal_error() {
+        let err = Error::Other(anyhow::anyhow!("something went wrong"));
+        assert_eq!(err.code(), "internalError");
+        assert_eq!(err.message(), "something went wrong");
+    }
 }
@@ -6,16 +6,33 @@
 
 use anyhow::anyhow;
 use base64ct::{Base64UrlUnpadded, Encoding};
+use credibil_infosec::Curve;
+use credibil_infosec::jose::jwe;
+use credibil_infosec::jose::jwk::KeyUse;
 use curve25519_dalek::edwards::CompressedEdwardsY;
-use serde_json::json;
+use serde_json::{Map, json};
 
 use super::DidJwk;
 use crate::core::Kind;
-use crate::document::{CreateOptions, Document, MethodType, PublicKeyFormat, VerificationMethod};
+use crate::document::{
+    Context, CreateOptions, Document, MethodType, PublicKeyFormat, VerificationMethod,
+    append_contexts, jwk_to_multikey,
+};
 use crate::error::Error;
-use crate::{DidOperator, KeyPurpose};
+use crate::{DidOperator, KeyPurpose, PublicKeyJwk};
 
 impl DidJwk {
+    /// Create a DID Document from the verifying key provided by [`DidOperator`].
+    ///
+    /// When the operator provides a [`KeyPurpose::KeyAgreement`] key, it is
+    /// used as the document's key agreement method in preference to deriving
+    /// one from the verifying key.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the supplied verifying key is not found or not a
+    /// valid format, or encryption key derivation is requested for a key that
+    /// is not Ed25519.
     pub fn create(op: &impl DidOperator, options: CreateOptions) -> crate::Result<Document> {
         let Some(verifying_key) = op.verification(KeyPurpose::VerificationMethod) else {
             return Err(Error::Other(anyhow!("no verification key")));
@@ -28,7 +45,24 @@ impl DidJwk {
 
         // key agreement
         // <https://w3c-ccg.github.io/did-method-key/#encryption-method-creation-algorithm>
-        let key_agreement = if options.enable_encryption_key_derivation {
+        let key_agreement = if let Some(agreement_key) = op.verification(KeyPurpose::KeyAgreement) {
+            // use the explicitly provided X25519 key rather than deriving one
+            if agreement_key.crv != Curve::X25519 {
+                return Err(Error::InvalidPublicKey("key agreement key is not X25519".into()));
+            }
+            Some(vec![Kind::Object(VerificationMethod {
+                id: format!("{did}#key-1"),
+                controller: did.clone(),
+                method_type: jwk_method(&options, agreement_key, "key-1", KeyPurpose::KeyAgreement),
+                ..VerificationMethod::default()
+            })])
+        } else if options.enable_encryption_key_derivation {
+            // X25519 derivation only applies to Ed25519 keys
+            if verifying_key.crv != Curve::Ed25519 {
+                return Err(Error::InvalidPublicKey(
+                    "encryption key derivation requires Ed25519".into(),
+                ));
+            }
             let key_bytes = Base64UrlUnpadded::decode_vec(&verifying_key.x)
                 .map_err(|e| Error::InvalidPublicKey(format!("issue decoding key: {e}")))?;
 
@@ -44,8 +78,9 @@ impl DidJwk {
             let x25519_bytes = edwards_pt.to_montgomery().to_bytes();
 
             let mut jwk = verifying_key.clone();
+            jwk.crv = Curve::X25519;
             jwk.x = Base64UrlUnpadded::encode_string(&x25519_bytes);
-            let method_type = MethodType::JsonWebKey { public_key_jwk: jwk };
+            let method_type = jwk_method(&options, jwk, "key-1", KeyPurpose::KeyAgreement);
 
             Some(vec![Kind::Object(VerificationMethod {
                 id: format!("{did}#key-1"),
@@ -57,39 +92,55 @@ impl DidJwk {
             None
         };
 
+        // the JWS 2020 suite defines both `JsonWebKey2020` and `publicKeyJwk`
         let verif_type = &options.public_key_format;
-        let context = Kind::Object(json!({
-            "publicKeyJwk": {
-                "@id": "https://w3id.org/security#publicKeyJwk",
-                "@type": "@json"
-            },
-            verif_type.to_string(): format!("https://w3id.org/security#{verif_type}"),
-        }));
+        let context = if *verif_type == PublicKeyFormat::JsonWebKey2020 {
+            Context::from("https://w3id.org/security/suites/jws-2020/v1")
+        } else {
+            Context::Inline(Map::from_iter([
+                (
+                    "publicKeyJwk".to_string(),
+                    json!({"@id": "https://w3id.org/security#publicKeyJwk", "@type": "@json"}),
+                ),
+                (verif_type.to_string(), json!(format!("https://w3id.org/security#{verif_type}"))),
+            ]))
+        };
 
         let kid = format!("{did}#key-0");
 
         let method_type = match options.public_key_format {
             PublicKeyFormat::Multikey => MethodType::Multikey {
-                public_key_multibase: verifying_key.to_multibase()?,
-            },
-            _ => MethodType::JsonWebKey {
-                public_key_jwk: verifying_key,
+                public_key_multibase: jwk_to_multikey(&verifying_key)?,
             },
+            _ => jwk_method(&options, verifying_key, "key-0", KeyPurpose::VerificationMethod),
+        };
+
+        // include only the selected verification relationships
+        let relationship = |purpose: KeyPurpose| {
+            options.includes(purpose).then(|| vec![Kind::String(kid.clone())])
         };
+        let authentication = relationship(KeyPurpose::Authentication);
+        let assertion_method = relationship(KeyPurpose::AssertionMethod);
+        let capability_invocation = relationship(KeyPurpose::CapabilityInvocation);
+        let capability_delegation = relationship(KeyPurpose::CapabilityDelegation);
+        let key_agreement = key_agreement.filter(|_| options.includes(KeyPurpose::KeyAgreement));
 
         Ok(Document {
-            context: vec![Kind::String(options.default_context), context],
+            context: append_contexts(
+                vec![Context::from(options.default_context), context],
+                options.additional_contexts,
+            ),
             id: did.clone(),
             verification_method: Some(vec![VerificationMethod {
-                id: kid.clone(),
+                id: kid,
                 controller: did,
                 method_type,
                 ..VerificationMethod::default()
             }]),
-            authentication: Some(vec![Kind::String(kid.clone())]),
-            assertion_method: Some(vec![Kind::String(kid.clone())]),
-            capability_invocation: Some(vec![Kind::String(kid.clone())]),
-            capability_delegation: Some(vec![Kind::String(kid)]),
+            authentication,
+            assertion_method,
+            capability_invocation,
+            capability_delegation,
             key_agreement,
             ..Document::default()
         })
@@ -102,13 +153,35 @@ impl DidJwk {
     }
 }
 
+// The JWK verification method type for the public key format.
+fn jwk_method(
+    options: &CreateOptions, mut public_key_jwk: PublicKeyJwk, fragment: &str, purpose: KeyPurpose,
+) -> MethodType {
+    if options.populate_jwk_parameters {
+        public_key_jwk.kid = Some(fragment.to_string());
+        if purpose == KeyPurpose::KeyAgreement {
+            public_key_jwk.use_ = Some(KeyUse::Encryption);
+            public_key_jwk.alg = Some(jwe::KeyAlgorithm::EcdhEs);
+        } else {
+            public_key_jwk.use_ = Some(KeyUse::Signature);
+        }
+    }
+
+    if options.public_key_format == PublicKeyFormat::JsonWebKey2020 {
+        MethodType::JsonWebKey2020 { public_key_jwk }
+    } else {
+        MethodType::JsonWebKey { public_key_jwk }
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use credibil_infosec::{Curve, KeyType, PublicKeyJwk};
+    use credibil_infosec::KeyType;
     use ed25519_dalek::SigningKey;
     use rand::rngs::OsRng;
 
     use super::*;
+    use crate::testing::StaticOperator;
 
     #[test]
     fn create() {
@@ -122,6 +195,122 @@ mod test {
         println!("{json}");
     }
 
+    #[test]
+    fn explicit_key_agreement() {
+        let agreement_key = PublicKeyJwk {
+            kty: KeyType::Okp,
+            crv: Curve::X25519,
+            x: Base64UrlUnpadded::encode_string(&[7u8; 32]),
+            ..PublicKeyJwk::default()
+        };
+        let op = AgreementOperator(agreement_key.clone());
+        let res = DidJwk::create(&op, CreateOptions::default()).expect("should create");
+
+        let key_agreement = res.key_agreement.expect("should have key agreement");
+        assert_eq!(key_agreement.len(), 1);
+        let Kind::Object(vm) = &key_agreement[0] else {
+            panic!("key agreement should be embedded");
+        };
+        assert_eq!(vm.id, format!("{}#key-1", res.id));
+        assert_eq!(
+            vm.method_type,
+            MethodType::JsonWebKey {
+                public_key_jwk: agreement_key
+            }
+        );
+    }
+
+    // P-256 is not a supported curve, so secp256k1 stands in for EC keys.
+    #[test]
+    fn json_web_key_2020() {
+        let jwk = PublicKeyJwk {
+            kty: KeyType::Ec,
+            crv: Curve::Es256K,
+            x: "H846FVf9umFYyRrEvC5uQa-__2sKLW9H3O89THClFm4".into(),
+            y: Some("VoMVEnKQsKNtu_sS20M0LPUR5yYEQz4YxUq4hQaQIzQ".into()),
+            ..PublicKeyJwk::default()
+        };
+        let options = CreateOptions {
+            public_key_format: PublicKeyFormat::JsonWebKey2020,
+            ..CreateOptions::default()
+        };
+        let document =
+            DidJwk::create(&StaticOperator::new(jwk.clone()), options).expect("should create");
+
+        let vm = &document.verification_method.as_ref().expect("should have methods")[0];
+        assert_eq!(vm.method_type, MethodType::JsonWebKey2020 { public_key_jwk: jwk });
+        assert_eq!(vm.method_type.kind(), "JsonWebKey2020");
+        assert_eq!(
+            document.context[1],
+            Context::from("https://w3id.org/security/suites/jws-2020/v1")
+        );
+    }
+
+    #[test]
+    fn jwk_parameters() {
+        let options = CreateOptions {
+            public_key_format: PublicKeyFormat::JsonWebKey,
+            enable_encryption_key_derivation: true,
+            populate_jwk_parameters: true,
+            ..CreateOptions::default()
+        };
+        let document = DidJwk::create(&Operator, options).expect("should create");
+
+        let vm = &document.verification_method.as_ref().expect("should have methods")[0];
+        let jwk = vm.method_type.to_jwk().expect("should have jwk");
+        assert_eq!(jwk.kid.as_deref(), Some("key-0"));
+        assert_eq!(jwk.use_, Some(KeyUse::Signature));
+        assert_eq!(jwk.alg, None);
+
+        let Some(Kind::Object(vm)) = document.key_agreement.as_ref().and_then(|ka| ka.first())
+        else {
+            panic!("should have embedded key agreement method");
+        };
+        let jwk = vm.method_type.to_jwk().expect("should have jwk");
+        assert_eq!(jwk.kid.as_deref(), Some("key-1"));
+        assert_eq!(jwk.use_, Some(KeyUse::Encryption));
+        assert_eq!(jwk.alg, Some(jwe::KeyAlgorithm::EcdhEs));
+
+        // the DID encodes the key without the added parameters
+        let encoded = document.id.strip_prefix("did:jwk:").expect("should be did:jwk");
+        let decoded = Base64UrlUnpadded::decode_vec(encoded).expect("should decode");
+        let did_jwk: PublicKeyJwk = serde_json::from_slice(&decoded).expect("should deserialize");
+        assert_eq!(did_jwk.kid, None);
+    }
+
+    #[test]
+    fn authentication_only() {
+        let options = CreateOptions {
+            relationships: Some(vec![KeyPurpose::Authentication]),
+            enable_encryption_key_derivation: true,
+            ..CreateOptions::default()
+        };
+        let document = DidJwk::create(&Operator, options).expect("should create");
+
+        assert_eq!(
+            document.authentication,
+            Some(vec![Kind::String(format!("{}#key-0", document.id))])
+        );
+        assert!(document.assertion_method.is_none());
+        assert!(document.key_agreement.is_none());
+        assert!(document.capability_invocation.is_none());
+        assert!(document.capability_delegation.is_none());
+    }
+
+    #[test]
+    fn additional_contexts() {
+        let credentials = Context::from("https://www.w3.org/2018/credentials/v1");
+        let options = CreateOptions {
+            additional_contexts: vec![credentials.clone(), credentials.clone()],
+            ..CreateOptions::default()
+        };
+        let document = DidJwk::create(&Operator, options).expect("should create");
+
+        assert_eq!(document.context.len(), 3);
+        assert_eq!(document.context[0], Context::from("https://www.w3.org/ns/did/v1"));
+        assert_eq!(document.context[2], credentials);
+    }
+
     struct Operator;
     impl DidOperator for Operator {
         fn verification(&self, purpose: KeyPurpose) -> Option<PublicKeyJwk> {
@@ -136,7 +325,23 @@ mod test {
                         ..PublicKeyJwk::default()
                     })
                 }
-                _ => panic!("unsupported purpose"),
+                _ => None,
+            }
+        }
+    }
+
+    struct AgreementOperator(PublicKeyJwk);
+    impl DidOperator for AgreementOperator {
+        fn verification(&self, purpose: KeyPurpose) -> Option<PublicKeyJwk> {
+            match purpose {
+                KeyPurpose::VerificationMethod => Some(PublicKeyJwk {
+                    kty: KeyType::Okp,
+                    crv: Curve::Ed25519,
+                    x: Base64UrlUnpadded::encode_string(&generate()),
+                    ..PublicKeyJwk::default()
+                }),
+                KeyPurpose::KeyAgreement => Some(self.0.clone()),
+                _ => None,
             }
         }
     }
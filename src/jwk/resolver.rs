@@ -9,52 +9,54 @@
 //! - <https://w3c-ccg.github.io/did-method-key>
 //! - <https://w3c.github.io/did-resolution>
 
-use std::sync::LazyLock;
-
 use base64ct::{Base64UrlUnpadded, Encoding};
-use regex::Regex;
 use serde_json::json;
 
 use super::DidJwk;
+use crate::did_url::method_specific_id;
 use crate::document::{CreateOptions, MethodType};
 use crate::error::Error;
 use crate::resolution::{ContentType, Metadata, Options, Resolved};
-use crate::{DidOperator, DidResolver, KeyPurpose, PublicKeyJwk};
-
-static DID_REGEX: LazyLock<Regex> =
-    LazyLock::new(|| Regex::new("^did:jwk:(?<jwk>[A-Za-z0-9-=—]+)$").expect("should compile"));
+use crate::{Curve, DidOperator, DidResolver, KeyPurpose, PublicKeyJwk};
 
 struct Operator(MethodType);
 impl DidOperator for Operator {
     fn verification(&self, purpose: KeyPurpose) -> Option<PublicKeyJwk> {
         match purpose {
-            KeyPurpose::VerificationMethod => self.0.jwk().ok(),
-            _ => panic!("unsupported purpose"),
+            KeyPurpose::VerificationMethod => self.0.to_jwk().ok(),
+            _ => None,
         }
     }
 }
 
 impl DidJwk {
+    /// Resolve a `did:jwk` DID to a DID document.
+    ///
+    /// The document is generated from the DID's key using the create
+    /// operation with encryption key derivation enabled for Ed25519 keys, so
+    /// the X25519 key agreement method is reconstructed exactly as it was
+    /// created.
+    ///
+    /// # Errors
+    ///
+    /// Will fail if the DID is not a valid `did:jwk` or its key is invalid.
     pub fn resolve(did: &str, _: Option<Options>, _: impl DidResolver) -> crate::Result<Resolved> {
         // check DID is valid AND extract key
-        let Some(caps) = DID_REGEX.captures(did) else {
-            return Err(Error::InvalidDid("DID is not a valid did:jwk".into()));
-        };
-        // let enc = &caps["jwk"];
-
-        let decoded = Base64UrlUnpadded::decode_vec(&caps["jwk"])
+        let encoded = method_specific_id(did, "jwk")?;
+        let decoded = Base64UrlUnpadded::decode_vec(encoded)
             .map_err(|e| Error::InvalidDid(format!("issue decoding key: {e}")))?;
-        let jwk = serde_json::from_slice(&decoded)
+        let jwk: PublicKeyJwk = serde_json::from_slice(&decoded)
             .map_err(|e| Error::InvalidDid(format!("issue deserializing key: {e}")))?;
+        let enable_encryption_key_derivation = jwk.crv == Curve::Ed25519;
         let op = Operator(MethodType::JsonWebKey { public_key_jwk: jwk });
 
         // per the spec, use the create operation to generate a DID document
         let options = CreateOptions {
-            enable_encryption_key_derivation: true,
+            enable_encryption_key_derivation,
             ..CreateOptions::default()
         };
 
-        let document = Self::create(&op, options).map_err(|e| Error::InvalidDid(e.to_string()))?;
+        let document = Self::create(&op, options).map_err(|e| Error::InvalidDid(e.message()))?;
 
         Ok(Resolved {
             context: "https://w3id.org/did-resolution/v1".into(),
@@ -81,7 +83,8 @@ mod test {
     use insta::assert_json_snapshot as assert_snapshot;
 
     use super::*;
-    use crate::document::Document;
+    use crate::document::{Document, PublicKeyFormat};
+    use crate::testing::MemoryKeyRing;
 
     const DID: &str = "did:jwk:eyJrdHkiOiJFQyIsImNydiI6InNlY3AyNTZrMSIsIngiOiJKSnpQaTRxeTJydktTVk85RjItMDVWV2VYMm9oc3dYN1NUbzg3TUdxcVB3IiwieSI6IkMxUnRGbnFXOWxOTEI1ejcycG9uMTIzZHh2MWtEcVUzUWw1QjhzMFdjXzQifQ";
 
@@ -98,4 +101,70 @@ mod test {
         let resolved = DidJwk::resolve(DID, None, MockResolver).expect("should resolve");
         assert_snapshot!("resolved", resolved);
     }
+
+    #[tokio::test]
+    async fn secp256k1() {
+        let keyring = MemoryKeyRing::secp256k1();
+        let options = CreateOptions {
+            public_key_format: PublicKeyFormat::JsonWebKey,
+            enable_encryption_key_derivation: true,
+            ..CreateOptions::default()
+        };
+        // no X25519 key can be derived from a secp256k1 key
+        let Err(Error::InvalidPublicKey(msg)) = DidJwk::create(&keyring, options) else {
+            panic!("should reject derivation from a secp256k1 key");
+        };
+        assert_eq!(msg, "encryption key derivation requires Ed25519");
+
+        let options = CreateOptions {
+            public_key_format: PublicKeyFormat::JsonWebKey,
+            ..CreateOptions::default()
+        };
+        let document = DidJwk::create(&keyring, options).expect("should create");
+        assert!(document.key_agreement.is_none());
+
+        // the encoded JWK retains all key members
+        let encoded = document.id.strip_prefix("did:jwk:").expect("should be did:jwk");
+        let decoded = Base64UrlUnpadded::decode_vec(encoded).expect("should decode");
+        let jwk: PublicKeyJwk = serde_json::from_slice(&decoded).expect("should deserialize");
+        assert_eq!(jwk, keyring.public_key());
+        assert!(jwk.y.is_some());
+
+        let resolved = DidJwk::resolve(&document.id, None, MockResolver).expect("should resolve");
+        let resolved = resolved.document.expect("should have document");
+        assert_eq!(resolved.id, document.id);
+        assert!(resolved.key_agreement.is_none());
+    }
+
+    #[tokio::test]
+    async fn key_agreement_round_trip() {
+        // resolution re-derives the key agreement key created with derivation on
+        let keyring = MemoryKeyRing::ed25519();
+        let options = CreateOptions {
+            enable_encryption_key_derivation: true,
+            ..CreateOptions::default()
+        };
+        let created = DidJwk::create(&keyring, options).expect("should create");
+        assert!(created.key_agreement.is_some());
+
+        let resolved = DidJwk::resolve(&created.id, None, MockResolver).expect("should resolve");
+        let resolved = resolved.document.expect("should have document");
+        assert_eq!(resolved.key_agreement, created.key_agreement);
+        assert_eq!(resolved, created);
+
+        // the derived key is the X25519 form of the Ed25519 key
+        let agreement = &resolved.verification_methods_for(KeyPurpose::KeyAgreement)[0];
+        let x25519 = agreement.method_type.to_jwk().expect("should convert");
+        let ed25519 = ed25519_dalek::VerifyingKey::from_bytes(
+            &Base64UrlUnpadded::decode_vec(&keyring.public_key().x)
+                .expect("should decode")
+                .try_into()
+                .expect("should be 32 bytes"),
+        )
+        .expect("should be Ed25519 key");
+        assert_eq!(
+            Base64UrlUnpadded::decode_vec(&x25519.x).expect("should decode"),
+            ed25519.to_montgomery().to_bytes()
+        );
+    }
 }
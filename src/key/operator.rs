@@ -7,161 +7,549 @@
 use anyhow::anyhow;
 use base64ct::{Base64UrlUnpadded, Encoding};
 use ed25519_dalek::{PUBLIC_KEY_LENGTH, VerifyingKey};
-use multibase::Base;
-use serde_json::json;
+use serde_json::{Map, json};
 
 use super::DidKey;
 use crate::core::Kind;
-use crate::document::{CreateOptions, Document, MethodType, PublicKeyFormat, VerificationMethod};
+use crate::document::{
+    Context, CreateOptions, Document, KeyAgreementIdStyle, MethodType, Profile, PublicKeyFormat,
+    VerificationMethod, append_contexts,
+};
 use crate::error::Error;
-use crate::{DidOperator, ED25519_CODEC, KeyPurpose, X25519_CODEC};
+use crate::{Curve, DidOperator, KeyPurpose, KeyType, PublicKeyJwk, multicodec};
 
 impl DidKey {
     /// Create a DID Document from the verifying key provided by [`DidOperator`].
     ///
+    /// An Ed25519 key is used for all signing relationships, with an optional
+    /// derived X25519 key agreement key. An X25519 key creates an
+    /// encryption-only DID, used for `keyAgreement` alone.
+    ///
+    /// See [`CreateOptions::derived_methods`] for adding the derived key
+    /// agreement key as a distinct verification method.
+    ///
     /// # Errors
     ///
     /// Returns an error if the supplied verifying key is not found or not a
-    /// valid format.
+    /// valid format, signing relationships are requested for an X25519 key or
+    /// its `keyAgreement` relationship is excluded, or a derived method is
+    /// requested that cannot be derived.
     pub fn create(op: &impl DidOperator, options: CreateOptions) -> crate::Result<Document> {
-        let Some(verifying_key) = op.verification(KeyPurpose::VerificationMethod) else {
-            return Err(Error::Other(anyhow!("no verification key")));
-        };
-        let key_bytes = Base64UrlUnpadded::decode_vec(&verifying_key.x)
-            .map_err(|e| Error::InvalidPublicKey(format!("issue decoding key: {e}")))?;
+        let profile = options.profile.clone();
+        create_with_profile(op, &profile, &profile.apply(options))
+    }
 
-        let mut multi_bytes = ED25519_CODEC.to_vec();
-        multi_bytes.extend_from_slice(&key_bytes);
-        let multikey = multibase::encode(Base::Base58Btc, &multi_bytes);
+    /// Create a DID Document for each of the provided verifying keys, using
+    /// the same options.
+    ///
+    /// Results are returned in key order, so a key that cannot be used does
+    /// not prevent documents being created for the others.
+    #[must_use]
+    pub fn create_many(
+        keys: &[PublicKeyJwk], options: CreateOptions,
+    ) -> Vec<crate::Result<Document>> {
+        let profile = options.profile.clone();
+        let options = profile.apply(options);
+        keys.iter()
+            .map(|key| {
+                let op = VerifyingKeyOperator(key.clone());
+                create_with_profile(&op, &profile, &options)
+            })
+            .collect()
+    }
+}
 
-        let did = format!("did:key:{multikey}");
+// Create a DID Document using options the profile has already been applied to.
+fn create_with_profile(
+    op: &impl DidOperator, profile: &Profile, options: &CreateOptions,
+) -> crate::Result<Document> {
+    let Some(verifying_key) = op.verification(KeyPurpose::VerificationMethod) else {
+        return Err(Error::Other(anyhow!("no verification key")));
+    };
+    let key_bytes = Base64UrlUnpadded::decode_vec(&verifying_key.x)
+        .map_err(|e| Error::InvalidPublicKey(format!("issue decoding key: {e}")))?;
 
-        let context = if options.public_key_format == PublicKeyFormat::Multikey
-            || options.public_key_format == PublicKeyFormat::Ed25519VerificationKey2020
-        {
-            Kind::String("https://w3id.org/security/data-integrity/v1".into())
-        } else {
-            let verif_type = &options.public_key_format;
-            Kind::Object(json!({
-                "publicKeyJwk": {
-                    "@id": "https://w3id.org/security#publicKeyJwk",
-                    "@type": "@json"
-                },
-                verif_type.to_string(): format!("https://w3id.org/security#{verif_type}"),
-            }))
-        };
-
-        // key agreement
-        // <https://w3c-ccg.github.io/did-method-key/#encryption-method-creation-algorithm>
-        let key_agreement = if options.enable_encryption_key_derivation {
-            let verifier_bytes: [u8; PUBLIC_KEY_LENGTH] = key_bytes.try_into().map_err(|_| {
-                Error::InvalidPublicKey(format!("public key is not {PUBLIC_KEY_LENGTH} bytes"))
-            })?;
-            let verifier = VerifyingKey::from_bytes(&verifier_bytes).map_err(|e| {
-                Error::InvalidPublicKey(format!("public key is not correct size: {e}"))
-            })?;
-            let x25519_bytes = verifier.to_montgomery().to_bytes();
-
-            // base58B encode the raw key
-            let mut multi_bytes = vec![];
-            multi_bytes.extend_from_slice(&X25519_CODEC);
-            multi_bytes.extend_from_slice(&x25519_bytes);
-            let multikey = multibase::encode(Base::Base58Btc, &multi_bytes);
-
-            let method_type = match options.public_key_format {
-                PublicKeyFormat::Multikey => MethodType::Multikey {
-                    public_key_multibase: multikey.clone(),
-                },
-                _ => return Err(Error::InvalidPublicKey("Unsupported public key format".into())),
-            };
-
-            Some(vec![Kind::Object(VerificationMethod {
-                id: format!("{did}#{multikey}"),
-                controller: did.clone(),
-                method_type,
-                ..VerificationMethod::default()
-            })])
+    // an X25519 key can only be used for key agreement
+    let x25519 = verifying_key.crv == Curve::X25519;
+    if x25519 && let Some(purpose) = signing_relationship(options) {
+        return Err(Error::InvalidPublicKey(format!("X25519 key cannot be used for {purpose}")));
+    }
+    if x25519 && !options.includes(KeyPurpose::KeyAgreement) {
+        return Err(Error::InvalidPublicKey(
+            "X25519 key can only be used for keyAgreement, which is not selected".into(),
+        ));
+    }
+
+    let multikey = multicodec::encode_multikey(&KeyType::Okp, &verifying_key.crv, &key_bytes)?;
+
+    let did = format!("did:key:{multikey}");
+    let kid = format!("{did}#{multikey}");
+
+    let context = context(&options.public_key_format);
+
+    // only key agreement methods can be derived from a public key
+    if let Some(purpose) = options.derived_methods.iter().find(|p| **p != KeyPurpose::KeyAgreement)
+    {
+        return Err(Error::InvalidPublicKey(format!(
+            "a {purpose} method cannot be derived from a public key"
+        )));
+    }
+    let derive_agreement = options.derived_methods.contains(&KeyPurpose::KeyAgreement);
+    if x25519 && derive_agreement {
+        return Err(Error::InvalidPublicKey(
+            "a keyAgreement method cannot be derived from an X25519 key".into(),
+        ));
+    }
+
+    // key agreement
+    // <https://w3c-ccg.github.io/did-method-key/#encryption-method-creation-algorithm>
+    let agreement_method =
+        if !x25519 && (options.enable_encryption_key_derivation || derive_agreement) {
+            Some(agreement_method(&did, &multikey, key_bytes, options)?)
         } else {
             None
         };
 
-        let kid = format!("{did}#{multikey}");
+    // derived methods are referenced, others are embedded
+    let mut derived = vec![];
+    let key_agreement = if x25519 {
+        Some(vec![Kind::String(kid.clone())])
+    } else if let Some(vm) = agreement_method {
+        if derive_agreement {
+            let reference = Kind::String(vm.id.clone());
+            derived.push(vm);
+            Some(vec![reference])
+        } else {
+            Some(vec![Kind::Object(vm)])
+        }
+    } else {
+        None
+    };
 
-        let method_type = match options.public_key_format {
-            PublicKeyFormat::Multikey => MethodType::Multikey {
+    let method_type = match options.public_key_format {
+        PublicKeyFormat::Multikey => MethodType::Multikey {
+            public_key_multibase: multikey,
+        },
+        PublicKeyFormat::Ed25519VerificationKey2020 if x25519 => {
+            MethodType::X25519KeyAgreementKey2020 {
                 public_key_multibase: multikey,
-            },
-            _ => MethodType::JsonWebKey {
-                public_key_jwk: verifying_key,
-            },
-        };
-
-        Ok(Document {
-            context: vec![Kind::String(options.default_context), context],
-            id: did.clone(),
-            verification_method: Some(vec![VerificationMethod {
-                id: kid.clone(),
-                controller: did,
-                method_type,
-                ..VerificationMethod::default()
-            }]),
-            authentication: Some(vec![Kind::String(kid.clone())]),
-            assertion_method: Some(vec![Kind::String(kid.clone())]),
-            capability_invocation: Some(vec![Kind::String(kid.clone())]),
-            capability_delegation: Some(vec![Kind::String(kid)]),
-            key_agreement,
-            ..Document::default()
-        })
+            }
+        }
+        PublicKeyFormat::Ed25519VerificationKey2020 => MethodType::Ed25519VerificationKey2020 {
+            public_key_multibase: multikey,
+        },
+        PublicKeyFormat::JsonWebKey2020 => MethodType::JsonWebKey2020 {
+            public_key_jwk: verifying_key,
+        },
+        _ => MethodType::JsonWebKey {
+            public_key_jwk: verifying_key,
+        },
+    };
+
+    // include only the selected verification relationships
+    let relationship = |purpose: KeyPurpose| {
+        (!x25519 && options.includes(purpose)).then(|| vec![Kind::String(kid.clone())])
+    };
+
+    Ok(Document {
+        context: append_contexts(
+            profile
+                .context()
+                .unwrap_or_else(|| vec![Context::from(options.default_context.clone()), context]),
+            options.additional_contexts.clone(),
+        ),
+        id: did.clone(),
+        verification_method: Some(
+            [
+                vec![VerificationMethod {
+                    id: kid.clone(),
+                    controller: did,
+                    method_type,
+                    ..VerificationMethod::default()
+                }],
+                derived,
+            ]
+            .concat(),
+        ),
+        authentication: relationship(KeyPurpose::Authentication),
+        assertion_method: relationship(KeyPurpose::AssertionMethod),
+        capability_invocation: relationship(KeyPurpose::CapabilityInvocation),
+        capability_delegation: relationship(KeyPurpose::CapabilityDelegation),
+        key_agreement: key_agreement.filter(|_| options.includes(KeyPurpose::KeyAgreement)),
+        ..Document::default()
+    })
+}
+
+// Derive an X25519 key agreement method from the Ed25519 key.
+fn agreement_method(
+    did: &str, multikey: &str, key_bytes: Vec<u8>, options: &CreateOptions,
+) -> crate::Result<VerificationMethod> {
+    let verifier_bytes: [u8; PUBLIC_KEY_LENGTH] = key_bytes.try_into().map_err(|_| {
+        Error::InvalidPublicKey(format!("public key is not {PUBLIC_KEY_LENGTH} bytes"))
+    })?;
+    let verifier = VerifyingKey::from_bytes(&verifier_bytes)
+        .map_err(|e| Error::InvalidPublicKey(format!("public key is not correct size: {e}")))?;
+    let x25519_bytes = verifier.to_montgomery().to_bytes();
+
+    // base58B encode the raw key
+    let x25519_multikey =
+        multicodec::encode_multikey(&KeyType::Okp, &Curve::X25519, &x25519_bytes)?;
+
+    let id = match options.key_agreement_id_style {
+        KeyAgreementIdStyle::X25519Multibase => format!("{did}#{x25519_multikey}"),
+        KeyAgreementIdStyle::Ed25519Suffix => format!("{did}#{multikey}-x25519"),
+    };
+    let method_type = match options.public_key_format {
+        PublicKeyFormat::Multikey => MethodType::Multikey {
+            public_key_multibase: x25519_multikey,
+        },
+        PublicKeyFormat::Ed25519VerificationKey2020 => MethodType::X25519KeyAgreementKey2020 {
+            public_key_multibase: x25519_multikey,
+        },
+        _ => return Err(Error::InvalidPublicKey("Unsupported public key format".into())),
+    };
+
+    Ok(VerificationMethod {
+        id,
+        controller: did.to_string(),
+        method_type,
+        ..VerificationMethod::default()
+    })
+}
+
+/// Provides a single, already known verifying key, for creating documents
+/// from the key alone.
+pub struct VerifyingKeyOperator(pub PublicKeyJwk);
+impl DidOperator for VerifyingKeyOperator {
+    fn verification(&self, purpose: KeyPurpose) -> Option<PublicKeyJwk> {
+        match purpose {
+            KeyPurpose::VerificationMethod => Some(self.0.clone()),
+            _ => None,
+        }
+    }
+}
+
+// The first signing relationship selected by the options, if any.
+fn signing_relationship(options: &CreateOptions) -> Option<KeyPurpose> {
+    options
+        .relationships
+        .iter()
+        .flatten()
+        .copied()
+        .find(|p| !matches!(p, KeyPurpose::KeyAgreement | KeyPurpose::VerificationMethod))
+}
+
+// The security context for the verification method format.
+fn context(public_key_format: &PublicKeyFormat) -> Context {
+    match public_key_format {
+        PublicKeyFormat::Multikey => Context::from("https://w3id.org/security/multikey/v1"),
+        PublicKeyFormat::Ed25519VerificationKey2020 => {
+            Context::from("https://w3id.org/security/data-integrity/v1")
+        }
+        verif_type => Context::Inline(Map::from_iter([
+            (
+                "publicKeyJwk".to_string(),
+                json!({"@id": "https://w3id.org/security#publicKeyJwk", "@type": "@json"}),
+            ),
+            (verif_type.to_string(), json!(format!("https://w3id.org/security#{verif_type}"))),
+        ])),
     }
 }
 
 #[cfg(test)]
 mod test {
-    use credibil_infosec::{Curve, KeyType, PublicKeyJwk};
-    use ed25519_dalek::SigningKey;
-    use rand::rngs::OsRng;
+    use credibil_infosec::PublicKeyJwk;
 
     use super::*;
+    use crate::document::Profile;
+    use crate::testing::StaticOperator;
+    use crate::testing::test::key_agreement_id;
 
     #[test]
     fn create() {
+        let jwk = PublicKeyJwk::from_multibase("z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK")
+            .expect("should convert");
+        let op = StaticOperator::new(jwk);
+
         let mut options = CreateOptions::default();
         options.enable_encryption_key_derivation = true;
+        let document = DidKey::create(&op, options).expect("should create");
+
+        assert_eq!(document.id, "did:key:z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK");
+        assert_eq!(
+            key_agreement_id(&document),
+            "did:key:z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK#z6LSj72tK8brWgZja8NLRwPigth2T9QRiG1uH9oKZuKjdh9p"
+        );
+    }
 
-        let op = Operator;
-        let res = DidKey::create(&op, options).expect("should create");
+    #[test]
+    fn multikey_context() {
+        let op = KeyOperator("z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK");
+        let document = DidKey::create(&op, CreateOptions::default()).expect("should create");
+        assert_eq!(
+            document.context,
+            vec![
+                Context::from("https://www.w3.org/ns/did/v1"),
+                Context::from("https://w3id.org/security/multikey/v1")
+            ]
+        );
+    }
 
-        let json = serde_json::to_string_pretty(&res).expect("should serialize");
-        println!("{json}");
+    #[test]
+    fn additional_contexts() {
+        let op = KeyOperator("z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK");
+        let options = CreateOptions {
+            additional_contexts: vec![
+                Context::from("https://www.w3.org/2018/credentials/v1"),
+                Context::from("https://w3id.org/security/multikey/v1"),
+                Context::Inline(Map::from_iter([(
+                    "@vocab".into(),
+                    json!("https://example.com/vocab#"),
+                )])),
+            ],
+            ..CreateOptions::default()
+        };
+        let document = DidKey::create(&op, options).expect("should create");
+        assert_eq!(
+            document.context,
+            vec![
+                Context::from("https://www.w3.org/ns/did/v1"),
+                Context::from("https://w3id.org/security/multikey/v1"),
+                Context::from("https://www.w3.org/2018/credentials/v1"),
+                Context::Inline(Map::from_iter([(
+                    "@vocab".into(),
+                    json!("https://example.com/vocab#")
+                )])),
+            ]
+        );
     }
 
-    struct Operator;
-    impl DidOperator for Operator {
-        fn verification(&self, purpose: KeyPurpose) -> Option<PublicKeyJwk> {
-            match purpose {
-                KeyPurpose::VerificationMethod => {
-                    let key = generate();
-
-                    Some(PublicKeyJwk {
-                        kty: KeyType::Okp,
-                        crv: Curve::Ed25519,
-                        x: Base64UrlUnpadded::encode_string(&key),
-                        ..PublicKeyJwk::default()
-                    })
-                }
-                _ => panic!("unsupported purpose"),
+    #[test]
+    fn key_agreement_id_style() {
+        const DID: &str = "did:key:z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK";
+        let op = KeyOperator("z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK");
+
+        let options = CreateOptions {
+            enable_encryption_key_derivation: true,
+            ..CreateOptions::default()
+        };
+        let document = DidKey::create(&op, options).expect("should create");
+        assert_eq!(
+            key_agreement_id(&document),
+            format!("{DID}#z6LSj72tK8brWgZja8NLRwPigth2T9QRiG1uH9oKZuKjdh9p")
+        );
+
+        let options = CreateOptions {
+            enable_encryption_key_derivation: true,
+            key_agreement_id_style: KeyAgreementIdStyle::Ed25519Suffix,
+            ..CreateOptions::default()
+        };
+        let document = DidKey::create(&op, options).expect("should create");
+        assert_eq!(
+            key_agreement_id(&document),
+            format!("{DID}#z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK-x25519")
+        );
+    }
+
+    #[test]
+    fn derived_methods() {
+        const DID: &str = "did:key:z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK";
+        let op = KeyOperator("z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK");
+
+        let options = CreateOptions {
+            derived_methods: vec![KeyPurpose::KeyAgreement],
+            ..CreateOptions::default()
+        };
+        let document = DidKey::create(&op, options).expect("should create");
+
+        let agreement_id = format!("{DID}#z6LSj72tK8brWgZja8NLRwPigth2T9QRiG1uH9oKZuKjdh9p");
+        let methods = document.verification_method.as_ref().expect("should have methods");
+        assert_eq!(
+            methods.iter().map(|vm| vm.id.as_str()).collect::<Vec<_>>(),
+            vec![&format!("{DID}#z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK"), &agreement_id]
+        );
+        assert_eq!(document.key_agreement, Some(vec![Kind::String(agreement_id.clone())]));
+        let agreement = document.verification_methods_for(KeyPurpose::KeyAgreement);
+        assert_eq!(agreement[0].id, agreement_id);
+
+        // signing keys cannot be derived
+        let options = CreateOptions {
+            derived_methods: vec![KeyPurpose::CapabilityInvocation],
+            ..CreateOptions::default()
+        };
+        let Err(Error::InvalidPublicKey(msg)) = DidKey::create(&op, options) else {
+            panic!("should not derive a capability invocation method");
+        };
+        assert_eq!(msg, "a capabilityInvocation method cannot be derived from a public key");
+    }
+
+    #[test]
+    fn jws2020_profile() {
+        let op = KeyOperator("z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK");
+        let options = CreateOptions {
+            profile: Profile::Jws2020,
+            enable_encryption_key_derivation: true,
+            ..CreateOptions::default()
+        };
+        let document = DidKey::create(&op, options).expect("should create");
+
+        assert_eq!(
+            document.context,
+            vec![
+                Context::from("https://www.w3.org/ns/did/v1"),
+                Context::from("https://w3id.org/security/suites/jws-2020/v1")
+            ]
+        );
+        let vm = &document.verification_method.as_ref().expect("should have methods")[0];
+        let MethodType::JsonWebKey2020 { public_key_jwk } = &vm.method_type else {
+            panic!("should be JsonWebKey2020");
+        };
+        assert_eq!(public_key_jwk.crv, Curve::Ed25519);
+        let serialized = serde_json::to_value(vm).expect("should serialize");
+        assert_eq!(serialized["type"], "JsonWebKey2020");
+
+        assert!(document.authentication.is_some());
+        assert!(document.assertion_method.is_some());
+        assert!(document.capability_invocation.is_some());
+        assert!(document.capability_delegation.is_some());
+        assert!(document.key_agreement.is_none());
+    }
+
+    #[test]
+    fn diddoc2020_profile() {
+        let op = KeyOperator("z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK");
+        let options = CreateOptions {
+            profile: Profile::Diddoc2020,
+            ..CreateOptions::default()
+        };
+        let document = DidKey::create(&op, options).expect("should create");
+
+        assert_eq!(document.context.len(), 3);
+        let vm = &document.verification_method.as_ref().expect("should have methods")[0];
+        assert_eq!(
+            vm.method_type,
+            MethodType::Ed25519VerificationKey2020 {
+                public_key_multibase: "z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK".into()
             }
+        );
+
+        let key_agreement = document.key_agreement.as_ref().expect("should have key agreement");
+        let Kind::Object(vm) = &key_agreement[0] else {
+            panic!("key agreement should be embedded");
+        };
+        assert_eq!(
+            vm.method_type,
+            MethodType::X25519KeyAgreementKey2020 {
+                public_key_multibase: "z6LSj72tK8brWgZja8NLRwPigth2T9QRiG1uH9oKZuKjdh9p".into()
+            }
+        );
+    }
+
+    #[test]
+    fn authentication_only() {
+        let op = KeyOperator("z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK");
+        let options = CreateOptions {
+            relationships: Some(vec![KeyPurpose::Authentication]),
+            enable_encryption_key_derivation: true,
+            ..CreateOptions::default()
+        };
+        let document = DidKey::create(&op, options).expect("should create");
+
+        let kid = &document.verification_method.as_ref().expect("should have methods")[0].id;
+        assert_eq!(document.authentication, Some(vec![Kind::String(kid.clone())]));
+        assert!(document.assertion_method.is_none());
+        assert!(document.key_agreement.is_none());
+        assert!(document.capability_invocation.is_none());
+        assert!(document.capability_delegation.is_none());
+
+        let serialized = serde_json::to_value(&document).expect("should serialize");
+        assert!(serialized.get("assertionMethod").is_none());
+    }
+
+    #[test]
+    fn x25519_only() {
+        const MULTIKEY: &str = "z6LSj72tK8brWgZja8NLRwPigth2T9QRiG1uH9oKZuKjdh9p";
+        let jwk = MethodType::Multikey {
+            public_key_multibase: MULTIKEY.into(),
         }
+        .to_jwk()
+        .expect("should convert");
+        let op = StaticOperator::new(jwk);
+
+        let document = DidKey::create(&op, CreateOptions::default()).expect("should create");
+        assert_eq!(document.id, format!("did:key:{MULTIKEY}"));
+
+        let kid = format!("did:key:{MULTIKEY}#{MULTIKEY}");
+        let vm = &document.verification_method.as_ref().expect("should have methods")[0];
+        assert_eq!(vm.id, kid);
+        assert_eq!(document.key_agreement, Some(vec![Kind::String(kid)]));
+        assert!(document.authentication.is_none());
+        assert!(document.assertion_method.is_none());
+        assert!(document.capability_invocation.is_none());
+        assert!(document.capability_delegation.is_none());
+
+        // resolves to the same document
+        let resolved = DidKey::resolve(&document.id).expect("should resolve");
+        assert_eq!(resolved.document, Some(document));
+
+        // signing relationships cannot be requested
+        let options = CreateOptions {
+            relationships: Some(vec![KeyPurpose::KeyAgreement, KeyPurpose::Authentication]),
+            ..CreateOptions::default()
+        };
+        let Err(Error::InvalidPublicKey(_)) = DidKey::create(&op, options) else {
+            panic!("should not create signing relationships");
+        };
+
+        // nor can key agreement, its only use, be excluded
+        let options = CreateOptions {
+            relationships: Some(vec![KeyPurpose::VerificationMethod]),
+            ..CreateOptions::default()
+        };
+        let Err(Error::InvalidPublicKey(_)) = DidKey::create(&op, options) else {
+            panic!("should not create a DID with no relationships");
+        };
+        let options = CreateOptions {
+            profile: Profile::Jws2020,
+            ..CreateOptions::default()
+        };
+        let Err(Error::InvalidPublicKey(_)) = DidKey::create(&op, options) else {
+            panic!("should not create a DID with no relationships");
+        };
     }
 
-    // HACK: generate a key pair
-    #[allow(dead_code)]
-    pub fn generate() -> Vec<u8> {
-        // TODO: pass in public key
-        let signing_key = SigningKey::generate(&mut OsRng);
-        let secret = Base64UrlUnpadded::encode_string(signing_key.as_bytes());
-        println!("secret: {secret}");
+    #[test]
+    fn create_many() {
+        let multikeys = [
+            "z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK",
+            "z6Mkj8Jr1rg3YjVWWhg7ahEYJibqhjBgZt1pDCbT4Lv7D4HX",
+            "z6Mkr1NtupNezZtcUAMxJ79HPex6ZTR9RnGh8xfV257ZQdss",
+        ];
+        let mut keys = multikeys
+            .iter()
+            .map(|mk| PublicKeyJwk::from_multibase(mk).expect("should convert"))
+            .collect::<Vec<_>>();
+        keys.push(PublicKeyJwk {
+            x: "not base64!".into(),
+            ..keys[0].clone()
+        });
+
+        let results = DidKey::create_many(&keys, CreateOptions::default());
+        assert_eq!(results.len(), 4);
+        for (result, multikey) in results.iter().zip(multikeys) {
+            let document = result.as_ref().expect("should create");
+            assert_eq!(document.id, format!("did:key:{multikey}"));
+        }
+        assert!(results[3].is_err(), "invalid key should fail alone");
+    }
 
-        signing_key.verifying_key().to_bytes().to_vec()
+    struct KeyOperator(&'static str);
+    impl DidOperator for KeyOperator {
+        fn verification(&self, purpose: KeyPurpose) -> Option<PublicKeyJwk> {
+            match purpose {
+                KeyPurpose::VerificationMethod => PublicKeyJwk::from_multibase(self.0).ok(),
+                _ => None,
+            }
+        }
     }
 }
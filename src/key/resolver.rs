@@ -9,56 +9,88 @@
 //! - <https://w3c-ccg.github.io/did-method-key>
 //! - <https://w3c.github.io/did-resolution>
 
-use std::sync::LazyLock;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{LazyLock, Mutex};
 
-use regex::Regex;
 use serde_json::json;
 
 use super::DidKey;
-use crate::document::{CreateOptions, MethodType};
+use super::operator::VerifyingKeyOperator;
+use crate::did_url::method_specific_id;
+use crate::document::{CreateOptions, Document, MethodType};
 use crate::error::Error;
 use crate::resolution::{ContentType, Metadata, Resolved};
-use crate::{DidOperator, KeyPurpose, PublicKeyJwk};
-
-static DID_REGEX: LazyLock<Regex> = LazyLock::new(|| {
-    Regex::new("^did:key:(?<identifier>z[a-km-zA-HJ-NP-Z1-9]+)$").expect("should compile")
-});
-
-struct Operator(MethodType);
-impl DidOperator for Operator {
-    fn verification(&self, purpose: KeyPurpose) -> Option<PublicKeyJwk> {
-        match purpose {
-            KeyPurpose::VerificationMethod => self.0.jwk().ok(),
-            _ => panic!("unsupported purpose"),
+
+// The maximum number of documents held by the `did:key` document cache.
+const CACHE_CAPACITY: usize = 256;
+
+// Documents created from `did:key` identifiers, keyed by multibase key.
+// Resolution is deterministic, so entries never expire.
+static CACHE: LazyLock<Mutex<DocumentCache>> = LazyLock::new(Mutex::default);
+
+// A bounded cache that evicts its oldest entry when full.
+#[derive(Default)]
+struct DocumentCache {
+    order: VecDeque<String>,
+    documents: HashMap<String, Document>,
+}
+
+impl DocumentCache {
+    fn get(&self, multikey: &str) -> Option<Document> {
+        self.documents.get(multikey).cloned()
+    }
+
+    fn insert(&mut self, multikey: &str, document: Document) {
+        if self.documents.contains_key(multikey) {
+            return;
+        }
+        if self.documents.len() >= CACHE_CAPACITY
+            && let Some(oldest) = self.order.pop_front()
+        {
+            self.documents.remove(&oldest);
         }
+        self.order.push_back(multikey.to_string());
+        self.documents.insert(multikey.to_string(), document);
     }
 }
 
 impl DidKey {
+    /// Returns the base58btc multibase key of a `did:key` DID.
+    ///
+    /// # Errors
+    ///
+    /// Will fail if the DID is not a valid `did:key` DID.
+    pub(crate) fn multikey(did: &str) -> crate::Result<&str> {
+        let multikey = method_specific_id(did, "key")?;
+        if !multikey.starts_with('z') {
+            return Err(Error::InvalidDid("did:key is not a base58btc multibase key".into()));
+        }
+        Ok(multikey)
+    }
+
     /// Resolve the provided `did:key` URL to a DID Document.
-    /// 
+    ///
+    /// Created documents are cached, so resolving the same DID again does not
+    /// re-decode the key.
+    ///
     /// # Errors
-    /// 
+    ///
     /// Will fail if the DID is not a valid `did:key` URL.
     pub fn resolve(did: &str) -> crate::Result<Resolved> {
         // check DID is valid AND extract key
-        let Some(caps) = DID_REGEX.captures(did) else {
-            return Err(Error::InvalidDid("DID is not a valid did:key".into()));
-        };
-        let multikey = &caps["identifier"];
-
-        let op = Operator(MethodType::Multikey {
-            public_key_multibase: multikey.to_string(),
-        });
+        let multikey = Self::multikey(did)?;
 
-        // per the spec, use the create operation to generate a DID document
-        let options = CreateOptions {
-            enable_encryption_key_derivation: true,
-            ..CreateOptions::default()
+        let cached = CACHE.lock().ok().and_then(|cache| cache.get(multikey));
+        let document = if let Some(document) = cached {
+            document
+        } else {
+            let document = create(multikey)?;
+            if let Ok(mut cache) = CACHE.lock() {
+                cache.insert(multikey, document.clone());
+            }
+            document
         };
 
-        let document = Self::create(&op, options).map_err(|e| Error::InvalidDid(e.to_string()))?;
-
         Ok(Resolved {
             context: "https://w3id.org/did-resolution/v1".into(),
             metadata: Metadata {
@@ -79,6 +111,22 @@ impl DidKey {
     }
 }
 
+// Create the DID document for a multibase key.
+fn create(multikey: &str) -> crate::Result<Document> {
+    let method_type = MethodType::Multikey {
+        public_key_multibase: multikey.to_string(),
+    };
+    let jwk = method_type.to_jwk().map_err(|e| Error::InvalidDid(e.message()))?;
+
+    // per the spec, use the create operation to generate a DID document
+    let options = CreateOptions {
+        enable_encryption_key_derivation: true,
+        ..CreateOptions::default()
+    };
+
+    DidKey::create(&VerifyingKeyOperator(jwk), options).map_err(|e| Error::InvalidDid(e.message()))
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -92,4 +140,46 @@ mod test {
         println!("{}", serde_json::to_string_pretty(&resolved).unwrap());
         // assert_snapshot!("resolved", resolved);
     }
+
+    #[test]
+    fn cached() {
+        let first = DidKey::resolve(DID).expect("should resolve");
+        for _ in 0..100 {
+            let resolved = DidKey::resolve(DID).expect("should resolve");
+            assert_eq!(resolved.document, first.document);
+        }
+
+        let cache = CACHE.lock().expect("should lock");
+        let (cached, len) = (cache.get(&DID[8..]), cache.documents.len());
+        drop(cache);
+        assert_eq!(cached, first.document);
+        assert!(len <= CACHE_CAPACITY);
+    }
+
+    #[test]
+    fn invalid_did() {
+        // DID syntax errors are located
+        let Err(Error::InvalidDid(msg)) = DidKey::resolve("did:key:z6Mk!") else {
+            panic!("should be invalid");
+        };
+        assert!(msg.contains("offset 12"));
+
+        let Err(Error::InvalidDid(_)) = DidKey::resolve("did:web:example.com") else {
+            panic!("should not be a did:key");
+        };
+        let Err(Error::InvalidDid(_)) = DidKey::resolve("did:key:u6Mkj8Jr1rg3") else {
+            panic!("should not be base58btc");
+        };
+    }
+
+    #[test]
+    fn cache_bounded() {
+        let mut cache = DocumentCache::default();
+        for i in 0..=CACHE_CAPACITY {
+            cache.insert(&i.to_string(), Document::default());
+        }
+        assert_eq!(cache.documents.len(), CACHE_CAPACITY);
+        assert!(cache.get("0").is_none(), "oldest entry should be evicted");
+        assert!(cache.get(&CACHE_CAPACITY.to_string()).is_some());
+    }
 }
@@ -15,27 +15,55 @@
 // Ed25519VerificationKey2020 |             Ed25519VerificationKey2018 |
 // X25519KeyAgreementKey2019   crv: Ed25519 | secp256k1 | P-256 | P-384 | p-521
 
+mod cache;
+mod context;
+mod convert;
 mod core;
+mod did_url;
 mod document;
 mod error;
 mod jwk;
 mod key;
+mod method;
+mod multicodec;
+mod proof;
 mod resolution;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 mod web;
 
+use std::fmt::{self, Display, Formatter};
 use std::future::Future;
+use std::str::FromStr;
 
+use serde::{Deserialize, Serialize};
+
+pub use cache::{resolve_cached, MemoryCache, ResolverCache};
+pub use context::{ContextLoader, StaticContextLoader};
+pub use convert::{
+    convert_did_jwk_to_key, convert_did_key_to_jwk, did_key_from_jwk, parse_did_key,
+};
+pub use core::{Kind, Quota};
 pub use credibil_infosec::{Curve, KeyType, PublicKeyJwk};
-pub use document::{CreateOptions, Document};
+pub use did_url::{method_specific_id, validate_did, DidSyntaxError, DidUrl};
+pub use document::{
+    absolute_id, append_contexts, canonical_json, content_hash_id, jwk_thumbprint, jwk_to_multikey,
+    normalize_jwk, redact_private_keys, same_key, Context, CreateOptions, Document, DocumentBuilder,
+    DocumentDiff, KeyAgreementIdStyle, KeyAlgorithm, MethodType, Profile, PublicKeyFormat, Service,
+    VerificationMethod, VerificationMethodId, MAX_SERVICE_ID_LENGTH,
+};
 pub use error::Error;
 pub use key::DidKey;
+pub use method::{Method, MethodRegistry, ResolveFuture};
+pub use multicodec::{decode_multikey, encode_multikey, multihash, HashAlgorithm};
+pub use proof::{verify_signature, Cryptosuite, EddsaJcs2022, Proof, ProofOptions};
 pub use resolution::{
-    dereference, resolve, ContentType, Dereferenced, Metadata, Options, Resolved, Resource,
+    dereference, dereference_with, resolve, resolve_with, ContentType, Dereferenced, Metadata,
+    Options, Parameters, Resolved, Resource, RetryPolicy, DEFAULT_WEB_TIMEOUT,
+    MAX_ALSO_KNOWN_AS_DEPTH, MAX_LINKED_RESOLUTIONS,
 };
 pub use web::DidWeb;
-
-const ED25519_CODEC: [u8; 2] = [0xed, 0x01];
-const X25519_CODEC: [u8; 2] = [0xec, 0x01];
+pub use web::path::{decode_web_path, decode_web_path_with_template, encode_web_path};
 
 /// Returns DID-specific errors.
 pub type Result<T> = std::result::Result<T, Error>;
@@ -56,19 +84,47 @@ pub trait DidResolver: Send + Clone {
     ///
     /// # Errors
     ///
-    /// Returns an error if the DID URL cannot be resolved.
+    /// Returns an error if the DID URL cannot be resolved. Returning an
+    /// [`Error::NotFound`] or [`Error::NetworkError`] lets callers distinguish
+    /// a missing document from a transport failure. Other errors are reported
+    /// as `notFound`.
     fn resolve(&self, url: &str) -> impl Future<Output = anyhow::Result<Document>> + Send;
 }
 
+/// [`DocumentStore`] is used by implementers to persist DID documents for DID
+/// methods where the document is hosted by the DID controller, as in the case
+/// of `did:web`.
+pub trait DocumentStore: Send + Sync {
+    /// Retrieve the DID document for the specified DID, if it exists.
+    fn get(&self, did: &str) -> impl Future<Output = anyhow::Result<Option<Document>>> + Send;
+
+    /// Store the DID document, replacing any existing document for the DID.
+    fn put(
+        &self, did: &str, document: &Document,
+    ) -> impl Future<Output = anyhow::Result<()>> + Send;
+}
+
 /// [`DidOperator`] is used by implementers to provide material required for DID
 /// document operations — creation, update, etc.
 pub trait DidOperator: Send + Sync {
     /// Provides verification material to be used for the specified
     /// verification method.
     fn verification(&self, purpose: KeyPurpose) -> Option<PublicKeyJwk>;
+
+    /// Provides all current verification material for the specified
+    /// verification method, for documents that publish several keys for the
+    /// same purpose (for example, to rotate signing keys without downtime).
+    ///
+    /// The default implementation returns the key provided by
+    /// [`DidOperator::verification`], if any.
+    fn active_keys(&self, purpose: KeyPurpose) -> Vec<PublicKeyJwk> {
+        self.verification(purpose).into_iter().collect()
+    }
 }
 
 /// The purpose the requested key material will be used for.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
 pub enum KeyPurpose {
     /// The document's `verification_method` field.
     VerificationMethod,
@@ -88,3 +144,160 @@ pub enum KeyPurpose {
     /// The document's `capability_delegation` field.
     CapabilityDelegation,
 }
+
+impl Display for KeyPurpose {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_term())
+    }
+}
+
+impl KeyPurpose {
+    /// All key purposes, in document order.
+    pub const ALL: [Self; 6] = [
+        Self::VerificationMethod,
+        Self::Authentication,
+        Self::AssertionMethod,
+        Self::KeyAgreement,
+        Self::CapabilityInvocation,
+        Self::CapabilityDelegation,
+    ];
+
+    /// The JSON-LD term for the document property holding keys for the
+    /// purpose. [`KeyPurpose::VerificationMethod`] is the document's
+    /// `verificationMethod` set rather than a verification relationship.
+    #[must_use]
+    pub const fn as_term(&self) -> &'static str {
+        match self {
+            Self::VerificationMethod => "verificationMethod",
+            Self::Authentication => "authentication",
+            Self::AssertionMethod => "assertionMethod",
+            Self::KeyAgreement => "keyAgreement",
+            Self::CapabilityInvocation => "capabilityInvocation",
+            Self::CapabilityDelegation => "capabilityDelegation",
+        }
+    }
+
+    /// The key purpose for a JSON-LD term, such as `assertionMethod`. Unlike
+    /// [`KeyPurpose::from_str`], kebab-case names are not accepted.
+    #[must_use]
+    pub fn from_term(term: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|p| p.as_term() == term)
+    }
+
+    // The kebab-case form of the term, such as `assertion-method`.
+    fn kebab_case(self) -> String {
+        let mut name = String::new();
+        for c in self.as_term().chars() {
+            if c.is_ascii_uppercase() {
+                name.push('-');
+            }
+            name.push(c.to_ascii_lowercase());
+        }
+        name
+    }
+}
+
+/// Parses a key purpose from its camelCase (`assertionMethod`) or kebab-case
+/// (`assertion-method`) name.
+impl FromStr for KeyPurpose {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let Some(purpose) = Self::ALL.into_iter().find(|p| p.as_term() == s || p.kebab_case() == s)
+        else {
+            let valid = Self::ALL.map(|p| p.to_string()).join(", ");
+            return Err(Error::InvalidInput(format!(
+                "invalid key purpose '{s}', expected one of: {valid}"
+            )));
+        };
+        Ok(purpose)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Provides several signing keys and no other keys.
+    struct MultiKeyOperator(Vec<PublicKeyJwk>);
+    impl DidOperator for MultiKeyOperator {
+        fn verification(&self, purpose: KeyPurpose) -> Option<PublicKeyJwk> {
+            self.active_keys(purpose).into_iter().next()
+        }
+
+        fn active_keys(&self, purpose: KeyPurpose) -> Vec<PublicKeyJwk> {
+            if purpose == KeyPurpose::VerificationMethod { self.0.clone() } else { vec![] }
+        }
+    }
+
+    #[test]
+    fn active_keys() {
+        let jwk = |x: &str| PublicKeyJwk {
+            kty: KeyType::Okp,
+            crv: Curve::Ed25519,
+            x: x.into(),
+            ..PublicKeyJwk::default()
+        };
+        let keys = vec![
+            jwk("11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo"),
+            jwk("Lm_M42cB3HkUiODQsXRcweM6TByfzEHGO9ND274JcOY"),
+        ];
+
+        let op = MultiKeyOperator(keys.clone());
+        assert_eq!(op.active_keys(KeyPurpose::VerificationMethod), keys);
+        assert_eq!(op.verification(KeyPurpose::VerificationMethod), Some(keys[0].clone()));
+        assert_eq!(op.active_keys(KeyPurpose::KeyAgreement), vec![]);
+
+        // the default implementation provides the single verification key
+        let keyring = testing::MemoryKeyRing::ed25519();
+        let active = keyring.active_keys(KeyPurpose::VerificationMethod);
+        assert_eq!(active, vec![keyring.public_key()]);
+    }
+
+    #[test]
+    fn key_purpose_from_str() {
+        for purpose in KeyPurpose::ALL {
+            assert_eq!(purpose.to_string().parse::<KeyPurpose>().expect("should parse"), purpose);
+        }
+        assert_eq!(
+            "capability-invocation".parse::<KeyPurpose>().expect("should parse"),
+            KeyPurpose::CapabilityInvocation
+        );
+
+        // other spellings are rejected
+        let invalid =
+            ["signing", "AssertionMethod", "assertionmethod", "assertion-Method", "key_agreement"];
+        for name in invalid {
+            let Err(Error::InvalidInput(msg)) = name.parse::<KeyPurpose>() else {
+                panic!("{name} should not parse");
+            };
+            assert!(msg.contains("expected one of: verificationMethod, authentication"));
+        }
+    }
+
+    #[test]
+    fn key_purpose_terms() {
+        let terms = [
+            (KeyPurpose::VerificationMethod, "verificationMethod"),
+            (KeyPurpose::Authentication, "authentication"),
+            (KeyPurpose::AssertionMethod, "assertionMethod"),
+            (KeyPurpose::KeyAgreement, "keyAgreement"),
+            (KeyPurpose::CapabilityInvocation, "capabilityInvocation"),
+            (KeyPurpose::CapabilityDelegation, "capabilityDelegation"),
+        ];
+
+        // terms match the document's serialized field names
+        let document: Document =
+            serde_json::from_slice(include_bytes!("web/did-eddsa.json")).expect("should parse");
+        let document = serde_json::to_value(&document).expect("should serialize");
+
+        for (purpose, term) in terms {
+            assert_eq!(purpose.as_term(), term);
+            assert_eq!(KeyPurpose::from_term(term), Some(purpose));
+            assert!(document.get(term).is_some(), "document should have {term}");
+        }
+
+        assert_eq!(KeyPurpose::from_term("assertion-method"), None);
+        assert_eq!(KeyPurpose::from_term("AssertionMethod"), None);
+    }
+}
@@ -0,0 +1,260 @@
+//! # DID Methods
+//!
+//! Dispatches DID resolution to the implementation of the DID's method, in the
+//! manner of Universal Resolver drivers.
+//!
+//! [`MethodRegistry::new`] provides the built-in `did:key`, `did:jwk`, and
+//! `did:web` methods. Additional methods are added by implementing [`Method`]
+//! and registering the implementation against the method name. The registry
+//! is then passed to [`crate::resolve_with`], [`crate::dereference_with`], or
+//! [`crate::Document::verify_proof_with_registry`].
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::LazyLock;
+
+use crate::DidResolver;
+use crate::did_url::DidUrl;
+use crate::error::Error;
+use crate::jwk::DidJwk;
+use crate::key::DidKey;
+use crate::resolution::{Options, Resolved};
+use crate::web::DidWeb;
+
+/// The future returned by [`Method::resolve`].
+pub type ResolveFuture<'a> = Pin<Box<dyn Future<Output = crate::Result<Resolved>> + Send + 'a>>;
+
+/// [`Method`] is implemented by DID methods to resolve DIDs of that method.
+///
+/// The trait is object safe so methods can be held in a [`MethodRegistry`].
+pub trait Method<R: DidResolver>: Send + Sync {
+    /// Resolve the DID to a DID document.
+    fn resolve<'a>(&'a self, did: &'a str, opts: Option<Options>, resolver: R) -> ResolveFuture<'a>
+    where
+        R: 'a;
+}
+
+impl<R: DidResolver> Method<R> for DidKey {
+    fn resolve<'a>(&'a self, did: &'a str, _: Option<Options>, _: R) -> ResolveFuture<'a>
+    where
+        R: 'a,
+    {
+        Box::pin(async move { Self::resolve(did) })
+    }
+}
+
+impl<R: DidResolver> Method<R> for DidJwk {
+    fn resolve<'a>(&'a self, did: &'a str, opts: Option<Options>, resolver: R) -> ResolveFuture<'a>
+    where
+        R: 'a,
+    {
+        Box::pin(async move { Self::resolve(did, opts, resolver) })
+    }
+}
+
+impl<R: DidResolver> Method<R> for DidWeb {
+    fn resolve<'a>(&'a self, did: &'a str, opts: Option<Options>, resolver: R) -> ResolveFuture<'a>
+    where
+        R: 'a,
+    {
+        Box::pin(Self::resolve(did, opts, resolver))
+    }
+}
+
+// The built-in DID methods.
+#[derive(Clone, Copy)]
+enum Builtin {
+    Key,
+    Jwk,
+    Web,
+}
+
+impl<R: DidResolver> Method<R> for Builtin {
+    fn resolve<'a>(&'a self, did: &'a str, opts: Option<Options>, resolver: R) -> ResolveFuture<'a>
+    where
+        R: 'a,
+    {
+        match self {
+            Self::Key => Method::resolve(&DidKey, did, opts, resolver),
+            Self::Jwk => Method::resolve(&DidJwk, did, opts, resolver),
+            Self::Web => Method::resolve(&DidWeb, did, opts, resolver),
+        }
+    }
+}
+
+// Built-in methods are shared by every registry rather than registered anew
+// each time one is created.
+static BUILTIN_METHODS: LazyLock<HashMap<&'static str, Builtin>> = LazyLock::new(|| {
+    HashMap::from([("key", Builtin::Key), ("jwk", Builtin::Jwk), ("web", Builtin::Web)])
+});
+
+/// A registry of DID method implementations, keyed by method name (e.g.
+/// `web` for `did:web`).
+pub struct MethodRegistry<R: DidResolver> {
+    methods: HashMap<String, Box<dyn Method<R>>>,
+    builtin: bool,
+}
+
+impl<R: DidResolver> MethodRegistry<R> {
+    /// Create a registry with the built-in `did:key`, `did:jwk`, and `did:web`
+    /// methods.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            methods: HashMap::new(),
+            builtin: true,
+        }
+    }
+
+    /// Create a registry with no methods, not even the built-in methods.
+    #[must_use]
+    pub fn empty() -> Self {
+        Self {
+            methods: HashMap::new(),
+            builtin: false,
+        }
+    }
+
+    /// Register the implementation for a DID method, replacing any existing
+    /// implementation for the method, including a built-in method.
+    pub fn register(
+        &mut self, method: impl Into<String>, implementation: impl Method<R> + 'static,
+    ) -> &mut Self {
+        self.methods.insert(method.into(), Box::new(implementation));
+        self
+    }
+
+    /// Resolve a DID using the implementation registered for its method.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the DID is invalid or contains a path, query, or
+    /// fragment, no implementation is registered for its method, or the
+    /// method's implementation returns an error.
+    pub async fn resolve(
+        &self, did: &str, opts: Option<Options>, resolver: R,
+    ) -> crate::Result<Resolved> {
+        let url = did.parse::<DidUrl>().map_err(|e| Error::InvalidDid(e.message()))?;
+        if url.to_string() != url.did() {
+            return Err(Error::InvalidDid(
+                "DID must not contain a path, query, or fragment".into(),
+            ));
+        }
+
+        let builtin = BUILTIN_METHODS.get(url.method.as_str()).filter(|_| self.builtin);
+        let method: &dyn Method<R> = match (self.methods.get(&url.method), builtin) {
+            (Some(method), _) => method.as_ref(),
+            (None, Some(builtin)) => builtin,
+            (None, None) => {
+                return Err(Error::MethodNotSupported(format!("{} is not supported", url.method)));
+            }
+        };
+        method.resolve(did, opts, resolver).await
+    }
+}
+
+impl<R: DidResolver> Default for MethodRegistry<R> {
+    /// A registry with the built-in `did:key`, `did:jwk`, and `did:web`
+    /// methods. The same as [`MethodRegistry::new`].
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::anyhow;
+
+    use super::*;
+    use crate::document::Document;
+    use crate::resolution::{resolve, resolve_with};
+
+    #[derive(Clone)]
+    struct MockResolver;
+    impl DidResolver for MockResolver {
+        async fn resolve(&self, _url: &str) -> anyhow::Result<Document> {
+            serde_json::from_slice(include_bytes!("web/did-ecdsa.json"))
+                .map_err(|e| anyhow!("issue deserializing document: {e}"))
+        }
+    }
+
+    // Resolves any DID to an empty document with the DID as its id.
+    struct ExampleMethod;
+    impl<R: DidResolver> Method<R> for ExampleMethod {
+        fn resolve<'a>(&'a self, did: &'a str, _: Option<Options>, _: R) -> ResolveFuture<'a>
+        where
+            R: 'a,
+        {
+            Box::pin(async move {
+                Ok(Resolved {
+                    document: Some(Document {
+                        id: did.to_string(),
+                        ..Document::default()
+                    }),
+                    ..Resolved::default()
+                })
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn dispatch() {
+        let mut registry = MethodRegistry::default();
+
+        for did in [
+            "did:key:z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK",
+            "did:jwk:eyJrdHkiOiJFQyIsImNydiI6InNlY3AyNTZrMSIsIngiOiJKSnpQaTRxeTJydktTVk85RjItMDVWV2VYMm9oc3dYN1NUbzg3TUdxcVB3IiwieSI6IkMxUnRGbnFXOWxOTEI1ejcycG9uMTIzZHh2MWtEcVUzUWw1QjhzMFdjXzQifQ",
+            "did:web:demo.credibil.io",
+        ] {
+            let resolved = registry.resolve(did, None, MockResolver).await.expect("should resolve");
+            assert!(resolved.document.is_some(), "{did} should resolve to a document");
+        }
+
+        let Err(Error::MethodNotSupported(_)) =
+            registry.resolve("did:example:123", None, MockResolver).await
+        else {
+            panic!("should not support did:example");
+        };
+
+        // add a method without touching call sites
+        registry.register("example", ExampleMethod);
+        let resolved =
+            registry.resolve("did:example:123", None, MockResolver).await.expect("should resolve");
+        assert_eq!(resolved.document.expect("should have document").id, "did:example:123");
+
+        // registered methods replace built-in methods
+        registry.register("key", ExampleMethod);
+        let did = "did:key:z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK";
+        let resolved = registry.resolve(did, None, MockResolver).await.expect("should resolve");
+        assert!(resolved.document.expect("should have document").verification_method.is_none());
+    }
+
+    #[tokio::test]
+    async fn entry_points() {
+        let mut registry = MethodRegistry::new();
+        registry.register("example", ExampleMethod);
+
+        // the built-in methods only
+        let resolved = resolve("did:example:123", None, MockResolver).await.expect("should return");
+        assert!(resolved.document.is_none());
+
+        let resolved = resolve_with("did:example:123", None, MockResolver, &registry)
+            .await
+            .expect("should resolve");
+        assert_eq!(resolved.document.expect("should have document").id, "did:example:123");
+    }
+
+    #[tokio::test]
+    async fn empty() {
+        let mut registry = MethodRegistry::empty();
+        let did = "did:key:z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK";
+        let Err(Error::MethodNotSupported(_)) = registry.resolve(did, None, MockResolver).await
+        else {
+            panic!("should have no built-in methods");
+        };
+
+        registry.register("example", ExampleMethod);
+        registry.resolve("did:example:123", None, MockResolver).await.expect("should resolve");
+    }
+}
@@ -0,0 +1,208 @@
+//! # Multicodec
+//!
+//! Registry of the multicodec prefixes used to encode public keys as Multikey
+//! (Multibase) values, and digests as multihashes.
+//!
+//! See <https://github.com/multiformats/multicodec/blob/master/table.csv>
+
+use multibase::Base;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+use crate::error::Error;
+use crate::{Curve, KeyType};
+
+/// Registered key types and curves with their (varint-encoded) multicodec
+/// prefix and the length of their public keys in bytes.
+const REGISTRY: [(KeyType, Curve, [u8; 2], usize); 3] = [
+    // ed25519-pub (0xed)
+    (KeyType::Okp, Curve::Ed25519, [0xed, 0x01], 32),
+    // x25519-pub (0xec)
+    (KeyType::Okp, Curve::X25519, [0xec, 0x01], 32),
+    // secp256k1-pub (0xe7), a compressed point
+    (KeyType::Ec, Curve::Es256K, [0xe7, 0x01], 33),
+];
+
+/// Encodes the raw public key bytes as a Multikey: the key's multicodec
+/// prefix followed by the key bytes, `Base58Btc` Multibase encoded.
+///
+/// # Errors
+///
+/// Returns an error if the key type and curve combination is not registered,
+/// or the key is not the length of the curve's public keys.
+pub fn encode_multikey(key_type: &KeyType, curve: &Curve, key: &[u8]) -> crate::Result<String> {
+    let Some((.., prefix, len)) =
+        REGISTRY.iter().find(|(kty, crv, ..)| kty == key_type && crv == curve)
+    else {
+        return Err(Error::UnsupportedPublicKeyType(format!(
+            "no multicodec registered for {key_type:?} {curve:?}"
+        )));
+    };
+    if key.len() != *len {
+        return Err(Error::InvalidPublicKeyLength(format!(
+            "{curve:?} key is {} bytes, expected {len}",
+            key.len()
+        )));
+    }
+    let multi_bytes = [prefix.as_slice(), key].concat();
+    Ok(multibase::encode(Base::Base58Btc, multi_bytes))
+}
+
+/// Decodes a Multikey into its key type, curve, and raw public key bytes.
+///
+/// # Errors
+///
+/// Returns an error if the value is not valid Multibase, the multicodec
+/// prefix is not registered, or the key is not the length of the curve's
+/// public keys.
+pub fn decode_multikey(multikey: &str) -> crate::Result<(KeyType, Curve, Vec<u8>)> {
+    let (_, multi_bytes) = multibase::decode(multikey)
+        .map_err(|e| Error::InvalidPublicKey(format!("issue decoding multibase: {e}")))?;
+    if multi_bytes.len() < 2 {
+        return Err(Error::InvalidPublicKeyLength("missing multicodec prefix".into()));
+    }
+    let (prefix, key) = multi_bytes.split_at(2);
+
+    let Some((key_type, curve, _, len)) = REGISTRY.iter().find(|(_, _, c, _)| c == prefix) else {
+        return Err(Error::UnsupportedPublicKeyType(format!(
+            "unsupported multicodec prefix: {prefix:02x?}"
+        )));
+    };
+    if key.len() != *len {
+        return Err(Error::InvalidPublicKeyLength(format!(
+            "{curve:?} key is {} bytes, expected {len}",
+            key.len()
+        )));
+    }
+    Ok((key_type.clone(), curve.clone(), key.to_vec()))
+}
+
+/// Hash algorithms supported for multihash digests.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    /// SHA-256 (`sha2-256`, 0x12).
+    #[default]
+    Sha256,
+
+    /// SHA-384 (`sha2-384`, 0x20).
+    Sha384,
+
+    /// SHA-512 (`sha2-512`, 0x13).
+    Sha512,
+}
+
+impl HashAlgorithm {
+    /// The algorithm's multihash code.
+    #[must_use]
+    pub const fn code(&self) -> u8 {
+        match self {
+            Self::Sha256 => 0x12,
+            Self::Sha384 => 0x20,
+            Self::Sha512 => 0x13,
+        }
+    }
+
+    /// The algorithm for a multihash code, if supported.
+    #[must_use]
+    pub const fn from_code(code: u8) -> Option<Self> {
+        match code {
+            0x12 => Some(Self::Sha256),
+            0x20 => Some(Self::Sha384),
+            0x13 => Some(Self::Sha512),
+            _ => None,
+        }
+    }
+}
+
+/// Hashes the data and encodes the digest as a multihash: the algorithm's
+/// code and the digest length followed by the digest, `Base58Btc` Multibase
+/// encoded.
+#[must_use]
+pub fn multihash(data: &[u8], algorithm: HashAlgorithm) -> String {
+    let digest = match algorithm {
+        HashAlgorithm::Sha256 => Sha256::digest(data).to_vec(),
+        HashAlgorithm::Sha384 => Sha384::digest(data).to_vec(),
+        HashAlgorithm::Sha512 => Sha512::digest(data).to_vec(),
+    };
+    // digests are shorter than 128 bytes, so the varint length is one byte
+    #[allow(clippy::cast_possible_truncation)]
+    let prefix = [algorithm.code(), digest.len() as u8];
+    multibase::encode(Base::Base58Btc, [prefix.as_slice(), &digest].concat())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn registered_codecs() {
+        for (key_type, curve, prefix, len) in REGISTRY {
+            let key = vec![7u8; len];
+            let multikey = encode_multikey(&key_type, &curve, &key).expect("should encode");
+            let (_, bytes) = multibase::decode(&multikey).expect("should decode");
+            assert_eq!(bytes[..2], prefix);
+
+            let decoded = decode_multikey(&multikey).expect("should decode");
+            assert_eq!(decoded, (key_type, curve, key));
+        }
+    }
+
+    #[test]
+    fn key_length() {
+        for (_, _, prefix, len) in REGISTRY {
+            for key in [vec![7u8; len - 1], vec![7u8; len + 1]] {
+                let multikey =
+                    multibase::encode(Base::Base58Btc, [prefix.as_slice(), &key].concat());
+                let Err(Error::InvalidPublicKeyLength(_)) = decode_multikey(&multikey) else {
+                    panic!("should reject a {}-byte key", key.len());
+                };
+            }
+        }
+    }
+
+    #[test]
+    fn ed25519() {
+        let (key_type, curve, key) =
+            decode_multikey("z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK")
+                .expect("should decode");
+        assert_eq!(key_type, KeyType::Okp);
+        assert_eq!(curve, Curve::Ed25519);
+        assert_eq!(key.len(), 32);
+    }
+
+    #[test]
+    fn multihashes() {
+        assert_eq!(
+            multihash(b"abc", HashAlgorithm::Sha256),
+            "zQmatYkNGZnELf8cAGdyJpUca2PyY4szai3RHyyWofNY1pY"
+        );
+        assert_eq!(
+            multihash(b"abc", HashAlgorithm::Sha384),
+            "zQ1FxNwJ7N4ZqZFGaP3cMMuVQ8iGEeHXZwgECWhyrHqYcfiCddPoFDTXRbjJzbWs3GbT4"
+        );
+        assert_eq!(
+            multihash(b"abc", HashAlgorithm::Sha512),
+            "z8VxDbq4MtJpdapHPC2SxLkUEJVMxZBxmgg176BpaBtsVFnqsQNoYyrgdJ2W7WgTfX2W8iWjjcvPd49wGeXCybtrX8z"
+        );
+
+        // the prefix records the algorithm and digest length
+        let (_, bytes) =
+            multibase::decode(multihash(b"", HashAlgorithm::Sha512)).expect("should decode");
+        assert_eq!(bytes[..2], [0x13, 64]);
+        assert_eq!(bytes.len(), 66);
+    }
+
+    #[test]
+    fn unknown_codec() {
+        let multikey =
+            multibase::encode(Base::Base58Btc, [[0x12, 0x00].as_slice(), &[0; 32]].concat());
+        let Err(Error::UnsupportedPublicKeyType(_)) = decode_multikey(&multikey) else {
+            panic!("should be unsupported");
+        };
+
+        let Err(Error::UnsupportedPublicKeyType(_)) =
+            encode_multikey(&KeyType::Ec, &Curve::Ed25519, &[0; 32])
+        else {
+            panic!("should be unsupported");
+        };
+    }
+}
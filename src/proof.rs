@@ -0,0 +1,618 @@
+//! # Data Integrity Proofs
+//!
+//! Secures DID documents with a
+//! [Data Integrity](https://www.w3.org/TR/vc-data-integrity/) proof, so a
+//! document published by its controller (for example, at a `did:web`
+//! location) is self-certifying.
+//!
+//! The document and proof options are canonicalized using JCS, as specified by
+//! the `eddsa-jcs-2022` cryptosuite. Verification is delegated to a
+//! [`Cryptosuite`], so other suites can be used with
+//! [`Document::add_proof_with`] and [`Document::verify_proof_with`].
+
+use anyhow::anyhow;
+use base64ct::{Base64UrlUnpadded, Encoding};
+use chrono::{DateTime, Utc};
+use credibil_infosec::{Algorithm, Curve, Signer};
+use ed25519_dalek::Verifier as _;
+use multibase::Base;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::core::Quota;
+use crate::document::{Document, absolute_id, canonical_json};
+use crate::error::Error;
+use crate::method::MethodRegistry;
+use crate::resolution::resolve_with;
+use crate::{DidResolver, KeyPurpose, PublicKeyJwk};
+
+/// The Data Integrity proof type.
+const PROOF_TYPE: &str = "DataIntegrityProof";
+
+/// A Data Integrity proof.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Proof {
+    /// The proof type. Always `DataIntegrityProof`.
+    #[serde(rename = "type")]
+    pub type_: String,
+
+    /// The cryptosuite used to create the proof, such as `eddsa-jcs-2022`.
+    pub cryptosuite: String,
+
+    /// When the proof was created.
+    pub created: DateTime<Utc>,
+
+    /// The DID URL of the verification method used to verify the proof.
+    pub verification_method: String,
+
+    /// The purpose of the proof.
+    pub proof_purpose: KeyPurpose,
+
+    /// A value chosen by the verifier that binds the proof to a single use,
+    /// preventing it from being replayed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub challenge: Option<String>,
+
+    /// The base58btc multibase-encoded signature.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub proof_value: String,
+}
+
+/// Options for creating a proof.
+#[derive(Clone, Debug)]
+pub struct ProofOptions {
+    /// The purpose of the proof. Defaults to
+    /// [`KeyPurpose::AssertionMethod`].
+    pub purpose: KeyPurpose,
+
+    /// The DID URL of the verification method used to verify the proof. When
+    /// `None`, the signer's verification method is used.
+    pub verification_method: Option<String>,
+
+    /// When the proof was created. When `None`, the current time is used.
+    pub created: Option<DateTime<Utc>>,
+
+    /// The challenge the proof is bound to, if the verifier requires one.
+    pub challenge: Option<String>,
+}
+
+impl Default for ProofOptions {
+    fn default() -> Self {
+        Self {
+            purpose: KeyPurpose::AssertionMethod,
+            verification_method: None,
+            created: None,
+            challenge: None,
+        }
+    }
+}
+
+/// [`Cryptosuite`] is implemented by Data Integrity cryptosuites to verify
+/// the data secured by a proof.
+///
+/// The trait is object safe, so suites can be held as `dyn Cryptosuite` and
+/// chosen by a proof's `cryptosuite` member. Data is signed by a [`Signer`]
+/// using the suite's [`Cryptosuite::algorithm`].
+pub trait Cryptosuite: Send + Sync {
+    /// The cryptosuite identifier set in the proof's `cryptosuite` member,
+    /// such as `eddsa-jcs-2022`.
+    fn cryptosuite(&self) -> &'static str;
+
+    /// The proof type set in the proof's `type` member.
+    fn proof_type(&self) -> &'static str {
+        PROOF_TYPE
+    }
+
+    /// The signing algorithm used by the cryptosuite.
+    fn algorithm(&self) -> Algorithm;
+
+    /// Verify the signature over the data using the public key.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the key cannot be used with the cryptosuite.
+    fn verify(&self, data: &[u8], signature: &[u8], jwk: &PublicKeyJwk) -> crate::Result<bool>;
+}
+
+/// The `eddsa-jcs-2022` cryptosuite: Ed25519 signatures over JCS-canonicalized
+/// data.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EddsaJcs2022;
+
+impl Cryptosuite for EddsaJcs2022 {
+    fn cryptosuite(&self) -> &'static str {
+        "eddsa-jcs-2022"
+    }
+
+    fn algorithm(&self) -> Algorithm {
+        Algorithm::EdDSA
+    }
+
+    fn verify(&self, data: &[u8], signature: &[u8], jwk: &PublicKeyJwk) -> crate::Result<bool> {
+        if jwk.crv != Curve::Ed25519 {
+            return Err(Error::InvalidPublicKey(format!(
+                "{} requires an Ed25519 key",
+                self.cryptosuite()
+            )));
+        }
+        verify_signature(jwk, data, signature)
+    }
+}
+
+// The cryptosuites used to verify proofs by their `cryptosuite` member.
+static CRYPTOSUITES: [&dyn Cryptosuite; 1] = [&EddsaJcs2022];
+
+impl Document {
+    /// Secures the document with a Data Integrity proof signed by the
+    /// [`Signer`], replacing any existing proof. The cryptosuite is chosen
+    /// for the signer's algorithm.
+    ///
+    /// Only Ed25519 signers are supported, using `eddsa-jcs-2022`. The Data
+    /// Integrity ECDSA cryptosuites are defined for P-256 and P-384 keys, so
+    /// there is no standard suite for secp256k1 signers.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no cryptosuite is defined for the signer's
+    /// algorithm, the signer's verification method cannot be determined, the
+    /// document cannot be serialized, or signing fails.
+    pub async fn add_proof(
+        &mut self, signer: &impl Signer, options: ProofOptions,
+    ) -> crate::Result<()> {
+        match signer.algorithm() {
+            Algorithm::EdDSA => self.add_proof_with(&EddsaJcs2022, signer, options).await,
+            Algorithm::ES256K => Err(Error::UnsupportedPublicKeyType(
+                "no Data Integrity cryptosuite is defined for secp256k1 keys".into(),
+            )),
+        }
+    }
+
+    /// Secures the document with a Data Integrity proof created using the
+    /// cryptosuite, replacing any existing proof.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the signer's verification method cannot be
+    /// determined, the document cannot be serialized, or signing fails.
+    pub async fn add_proof_with(
+        &mut self, suite: &dyn Cryptosuite, signer: &impl Signer, options: ProofOptions,
+    ) -> crate::Result<()> {
+        let algorithm = suite.algorithm();
+        if signer.algorithm() != algorithm {
+            return Err(Error::InvalidPublicKey(format!(
+                "{} requires a {algorithm:?} signer",
+                suite.cryptosuite()
+            )));
+        }
+        let verification_method = match options.verification_method {
+            Some(verification_method) => verification_method,
+            None => signer.verification_method().await?,
+        };
+
+        let mut proof = Proof {
+            type_: suite.proof_type().into(),
+            cryptosuite: suite.cryptosuite().into(),
+            created: options.created.unwrap_or_else(Utc::now),
+            verification_method,
+            proof_purpose: options.purpose,
+            challenge: options.challenge,
+            proof_value: String::new(),
+        };
+        self.proof = None;
+        let signature = signer.try_sign(&hash_data(self, &proof)?).await?;
+        proof.proof_value = multibase::encode(Base::Base58Btc, signature);
+        self.proof = Some(proof);
+
+        Ok(())
+    }
+
+    /// Verifies the document's proof, returning the DID URL of the
+    /// verification method that validated it.
+    ///
+    /// The proof's verification method must belong to the document's DID or
+    /// one of its controllers, and be listed in that DID's document for the
+    /// proof's purpose. The controlling DID is resolved using the
+    /// [`DidResolver`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the document has no proof, the verification method
+    /// cannot be found, the method is not authorized for the proof's purpose
+    /// by the document's DID or a controller, or the signature is invalid.
+    /// Returns [`Error::Deactivated`] if the controlling DID has been
+    /// deactivated.
+    pub async fn verify_proof(&self, resolver: impl DidResolver) -> crate::Result<String> {
+        self.verify_proof_with_registry(resolver, &MethodRegistry::new()).await
+    }
+
+    /// Verifies the document's proof, resolving the controlling DID using the
+    /// DID methods in the registry rather than only the built-in methods.
+    ///
+    /// See [`Document::verify_proof`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the document has no proof, the verification method
+    /// cannot be found, the method is not authorized for the proof's purpose
+    /// by the document's DID or a controller, or the signature is invalid.
+    /// Returns [`Error::Deactivated`] if the controlling DID has been
+    /// deactivated.
+    pub async fn verify_proof_with_registry<R: DidResolver>(
+        &self, resolver: R, registry: &MethodRegistry<R>,
+    ) -> crate::Result<String> {
+        let Some(proof) = &self.proof else {
+            return Err(Error::NotFound("document has no proof".into()));
+        };
+        let vm_id = absolute_id(&self.id, &proof.verification_method);
+
+        // the key must belong to the document's DID or one of its controllers
+        let did = vm_id.split(['/', '?', '#']).next().unwrap_or_default();
+        let controllers = match &self.controller {
+            None => &[][..],
+            Some(Quota::One(controller)) => std::slice::from_ref(controller),
+            Some(Quota::Many(controllers)) => controllers.as_slice(),
+        };
+        if did != self.id && !controllers.iter().any(|c| c == did) {
+            return Err(Error::InvalidProof(format!(
+                "{vm_id} is not controlled by {} or its controllers",
+                self.id
+            )));
+        }
+
+        let Some(did_document) = resolve_with(did, None, resolver, registry).await?.document else {
+            return Err(Error::Deactivated(format!("{did} has been deactivated")));
+        };
+        let Some(vm) = did_document
+            .verification_methods_for(proof.proof_purpose)
+            .into_iter()
+            .find(|vm| absolute_id(&did_document.id, &vm.id) == vm_id)
+        else {
+            if did_document.public_key_for_kid(&vm_id).is_none() {
+                return Err(Error::NotFound(format!("verification method {vm_id} not found")));
+            }
+            return Err(Error::InvalidProof(format!(
+                "{vm_id} is not authorized for {}",
+                proof.proof_purpose
+            )));
+        };
+        self.verify_proof_with_key(&vm.method_type.to_jwk()?)?;
+
+        Ok(vm_id)
+    }
+
+    /// Verifies the document's proof using the provided public key, ignoring
+    /// the proof's verification method.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the document has no proof, the proof's cryptosuite
+    /// is not supported, or the signature is invalid.
+    pub fn verify_proof_with_key(&self, jwk: &PublicKeyJwk) -> crate::Result<()> {
+        let Some(proof) = &self.proof else {
+            return Err(Error::NotFound("document has no proof".into()));
+        };
+        let Some(suite) = CRYPTOSUITES.iter().find(|s| s.cryptosuite() == proof.cryptosuite) else {
+            return Err(Error::InvalidProof(format!(
+                "unsupported cryptosuite: {}",
+                proof.cryptosuite
+            )));
+        };
+        self.verify_proof_with(*suite, jwk)
+    }
+
+    /// Verifies the document's proof using the cryptosuite and the provided
+    /// public key, ignoring the proof's verification method.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the document has no proof, the proof was not
+    /// created with the cryptosuite, or the signature is invalid.
+    pub fn verify_proof_with(
+        &self, suite: &dyn Cryptosuite, jwk: &PublicKeyJwk,
+    ) -> crate::Result<()> {
+        let Some(proof) = &self.proof else {
+            return Err(Error::NotFound("document has no proof".into()));
+        };
+        if proof.type_ != suite.proof_type() || proof.cryptosuite != suite.cryptosuite() {
+            return Err(Error::InvalidProof(format!(
+                "proof was not created with {}",
+                suite.cryptosuite()
+            )));
+        }
+        let (_, signature) = multibase::decode(&proof.proof_value)
+            .map_err(|e| Error::InvalidProof(format!("issue decoding proof value: {e}")))?;
+        if !suite.verify(&hash_data(self, proof)?, &signature, jwk)? {
+            return Err(Error::InvalidProof("proof signature is invalid".into()));
+        }
+        Ok(())
+    }
+}
+
+/// Verifies a signature over `msg` using the public key. Ed25519 signatures
+/// and secp256k1 (ES256K) signatures in fixed-size `r || s` form are
+/// supported.
+///
+/// Returns `false` if the signature does not verify.
+///
+/// # Errors
+///
+/// Returns an error if the public key is invalid or is an X25519 key, which
+/// cannot sign.
+pub fn verify_signature(jwk: &PublicKeyJwk, msg: &[u8], signature: &[u8]) -> crate::Result<bool> {
+    let decode = |value: &str| {
+        Base64UrlUnpadded::decode_vec(value)
+            .map_err(|e| Error::InvalidPublicKey(format!("issue decoding key: {e}")))
+    };
+
+    match jwk.crv {
+        Curve::Ed25519 => {
+            let key_bytes: [u8; 32] = decode(&jwk.x)?
+                .try_into()
+                .map_err(|_| Error::InvalidPublicKey("key is not 32 bytes".into()))?;
+            let key = ed25519_dalek::VerifyingKey::from_bytes(&key_bytes)
+                .map_err(|e| Error::InvalidPublicKey(format!("invalid key: {e}")))?;
+            Ok(ed25519_dalek::Signature::from_slice(signature)
+                .is_ok_and(|signature| key.verify(msg, &signature).is_ok()))
+        }
+        Curve::Es256K => {
+            let Some(y) = &jwk.y else {
+                return Err(Error::InvalidPublicKey("secp256k1 key has no y".into()));
+            };
+            let sec1 = [vec![0x04], decode(&jwk.x)?, decode(y)?].concat();
+            let key = k256::ecdsa::VerifyingKey::from_sec1_bytes(&sec1)
+                .map_err(|e| Error::InvalidPublicKey(format!("invalid key: {e}")))?;
+            Ok(k256::ecdsa::Signature::from_slice(signature).is_ok_and(|signature| {
+                k256::ecdsa::signature::Verifier::verify(&key, msg, &signature).is_ok()
+            }))
+        }
+        Curve::X25519 => Err(Error::InvalidPublicKey("X25519 keys cannot sign".into())),
+    }
+}
+
+// The data signed by a proof: the hash of the canonical proof options
+// followed by the hash of the canonical unsecured document.
+fn hash_data(document: &Document, proof: &Proof) -> crate::Result<Vec<u8>> {
+    let serialize = |value: Result<Value, serde_json::Error>| {
+        value.map_err(|e| Error::Other(anyhow!("issue serializing document: {e}")))
+    };
+
+    let unsecured = Document {
+        proof: None,
+        ..document.clone()
+    };
+    let unsecured = serialize(serde_json::to_value(&unsecured))?;
+
+    let config = Proof {
+        proof_value: String::new(),
+        ..proof.clone()
+    };
+    let mut config = serialize(serde_json::to_value(&config))?;
+    if let Value::Object(members) = &mut config
+        && !document.context.is_empty()
+    {
+        members.insert("@context".into(), serialize(serde_json::to_value(&document.context))?);
+    }
+
+    let mut data = Sha256::digest(canonical_json(&config).as_bytes()).to_vec();
+    data.extend(Sha256::digest(canonical_json(&unsecured).as_bytes()));
+    Ok(data)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::document::{CreateOptions, DocumentMetadata, PublicKeyFormat};
+    use crate::testing::MemoryKeyRing;
+    use crate::web::DidWeb;
+
+    // Resolves any URL to the wrapped document.
+    #[derive(Clone)]
+    struct DocumentResolver(Document);
+    impl DidResolver for DocumentResolver {
+        async fn resolve(&self, _url: &str) -> anyhow::Result<Document> {
+            Ok(self.0.clone())
+        }
+    }
+
+    #[tokio::test]
+    async fn round_trip() {
+        let keyring = MemoryKeyRing::ed25519();
+        let options = CreateOptions {
+            public_key_format: PublicKeyFormat::JsonWebKey,
+            ..CreateOptions::default()
+        };
+        let mut document =
+            DidWeb::create("https://demo.credibil.io", &keyring, options).expect("should create");
+        let resolver = DocumentResolver(document.clone());
+
+        let options = ProofOptions {
+            verification_method: Some("#key-0".into()),
+            ..ProofOptions::default()
+        };
+        document.add_proof(&keyring, options).await.expect("should add proof");
+
+        let proof = document.proof.as_ref().expect("should have proof");
+        assert_eq!(proof.type_, "DataIntegrityProof");
+        assert_eq!(proof.cryptosuite, "eddsa-jcs-2022");
+        assert_eq!(proof.proof_purpose, KeyPurpose::AssertionMethod);
+        assert!(proof.proof_value.starts_with('z'));
+
+        // the proof survives serialization
+        let json = serde_json::to_string(&document).expect("should serialize");
+        let document: Document = serde_json::from_str(&json).expect("should deserialize");
+        let vm_id = document.verify_proof(resolver.clone()).await.expect("should verify");
+        assert_eq!(vm_id, "did:web:demo.credibil.io#key-0");
+
+        // a modified document fails verification
+        let mut tampered = document.clone();
+        tampered.also_known_as = Some(vec!["https://example.com".into()]);
+        let Err(Error::InvalidProof(_)) = tampered.verify_proof(resolver).await else {
+            panic!("should not verify a modified document");
+        };
+    }
+
+    #[tokio::test]
+    async fn secp256k1_unsupported() {
+        let keyring = MemoryKeyRing::secp256k1();
+        let mut document =
+            DidWeb::create("https://demo.credibil.io", &keyring, CreateOptions::default())
+                .expect("should create");
+
+        let Err(Error::UnsupportedPublicKeyType(_)) =
+            document.add_proof(&keyring, ProofOptions::default()).await
+        else {
+            panic!("should not sign with a secp256k1 key");
+        };
+    }
+
+    #[tokio::test]
+    async fn authorization() {
+        let keyring = MemoryKeyRing::ed25519();
+        let document =
+            DidWeb::create("https://demo.credibil.io", &keyring, CreateOptions::default())
+                .expect("should create");
+        let resolver = DocumentResolver(document.clone());
+
+        // a key from another DID cannot sign the document
+        let foreign = MemoryKeyRing::ed25519();
+        let mut signed = document.clone();
+        signed.add_proof(&foreign, ProofOptions::default()).await.expect("should add proof");
+        let Err(Error::InvalidProof(msg)) = signed.verify_proof(resolver.clone()).await else {
+            panic!("should not verify a proof by a foreign did:key");
+        };
+        assert!(msg.contains("is not controlled by did:web:demo.credibil.io"));
+
+        // unless that DID is a controller of the document
+        let did_key = foreign.verification_method().await.expect("should have method");
+        let did = did_key.split('#').next().expect("should have DID");
+        let mut controlled = document.clone();
+        controlled.controller = Some(Quota::Many(vec![document.id.clone(), did.into()]));
+        controlled.add_proof(&foreign, ProofOptions::default()).await.expect("should add proof");
+        let vm_id = controlled.verify_proof(resolver.clone()).await.expect("should verify");
+        assert_eq!(vm_id, did_key);
+
+        // the key must be authorized for the proof's purpose
+        let mut signed = document.clone();
+        let options = ProofOptions {
+            purpose: KeyPurpose::KeyAgreement,
+            verification_method: Some("#key-0".into()),
+            ..ProofOptions::default()
+        };
+        signed.add_proof(&keyring, options).await.expect("should add proof");
+        let Err(Error::InvalidProof(msg)) = signed.verify_proof(resolver).await else {
+            panic!("should not verify a key used for another purpose");
+        };
+        assert!(msg.ends_with("is not authorized for keyAgreement"));
+    }
+
+    // A cryptosuite defined outside the crate.
+    struct TestSuite;
+    impl Cryptosuite for TestSuite {
+        fn cryptosuite(&self) -> &'static str {
+            "test-eddsa-2025"
+        }
+
+        fn algorithm(&self) -> Algorithm {
+            Algorithm::EdDSA
+        }
+
+        fn verify(&self, data: &[u8], signature: &[u8], jwk: &PublicKeyJwk) -> crate::Result<bool> {
+            verify_signature(jwk, data, signature)
+        }
+    }
+
+    #[tokio::test]
+    async fn cryptosuite() {
+        let keyring = MemoryKeyRing::ed25519();
+        let mut document =
+            DidWeb::create("https://demo.credibil.io", &keyring, CreateOptions::default())
+                .expect("should create");
+        let options = ProofOptions {
+            verification_method: Some("#key-0".into()),
+            ..ProofOptions::default()
+        };
+
+        // the suite's algorithm must match the signer
+        let Err(Error::InvalidPublicKey(_)) = document
+            .add_proof_with(&EddsaJcs2022, &MemoryKeyRing::secp256k1(), options.clone())
+            .await
+        else {
+            panic!("should not sign with a secp256k1 key");
+        };
+
+        // suites can be registered and chosen by a proof's cryptosuite
+        let suites: Vec<Box<dyn Cryptosuite>> = vec![Box::new(EddsaJcs2022), Box::new(TestSuite)];
+        document.add_proof_with(&TestSuite, &keyring, options).await.expect("should add proof");
+        let proof = document.proof.as_ref().expect("should have proof");
+        assert_eq!(proof.cryptosuite, "test-eddsa-2025");
+
+        let suite = suites
+            .iter()
+            .find(|s| s.cryptosuite() == proof.cryptosuite)
+            .expect("should find suite");
+        document.verify_proof_with(suite.as_ref(), &keyring.public_key()).expect("should verify");
+        let Err(Error::InvalidProof(_)) =
+            document.verify_proof_with(&EddsaJcs2022, &keyring.public_key())
+        else {
+            panic!("should not verify with another suite");
+        };
+
+        // unregistered suites are not verified by default
+        let Err(Error::InvalidProof(_)) = document.verify_proof_with_key(&keyring.public_key())
+        else {
+            panic!("should not verify an unsupported suite");
+        };
+
+        // nor are proof values that cannot be decoded
+        document.proof.as_mut().expect("should have proof").proof_value = "not-base58".into();
+        let Err(Error::InvalidProof(_)) =
+            document.verify_proof_with(&TestSuite, &keyring.public_key())
+        else {
+            panic!("should not verify an undecodable proof value");
+        };
+    }
+
+    #[tokio::test]
+    async fn unknown_method() {
+        let keyring = MemoryKeyRing::ed25519();
+        let mut document =
+            DidWeb::create("https://demo.credibil.io", &keyring, CreateOptions::default())
+                .expect("should create");
+        let resolver = DocumentResolver(document.clone());
+
+        let options = ProofOptions {
+            verification_method: Some("#key-9".into()),
+            ..ProofOptions::default()
+        };
+        document.add_proof(&keyring, options).await.expect("should add proof");
+
+        let Err(Error::NotFound(_)) = document.verify_proof(resolver).await else {
+            panic!("should not find verification method");
+        };
+    }
+
+    #[tokio::test]
+    async fn deactivated() {
+        let keyring = MemoryKeyRing::ed25519();
+        let mut document =
+            DidWeb::create("https://demo.credibil.io", &keyring, CreateOptions::default())
+                .expect("should create");
+        let options = ProofOptions {
+            verification_method: Some("#key-0".into()),
+            ..ProofOptions::default()
+        };
+        document.add_proof(&keyring, options).await.expect("should add proof");
+
+        let mut hosted = document.clone();
+        hosted.did_document_metadata = Some(DocumentMetadata {
+            deactivated: Some(true),
+            ..DocumentMetadata::default()
+        });
+        let Err(Error::Deactivated(_)) = document.verify_proof(DocumentResolver(hosted)).await
+        else {
+            panic!("should not verify against a deactivated DID");
+        };
+    }
+}
@@ -6,13 +6,21 @@
 //! See [DID resolution](https://www.w3.org/TR/did-core/#did-resolution) fpr more.
 
 use std::collections::HashMap;
+use std::time::Duration;
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::Value;
-
-use crate::document::{Document, DocumentMetadata, Service, VerificationMethod};
+use url::Url;
+
+use crate::DidResolver;
+use crate::core::{Kind, Quota};
+use crate::did_url::DidUrl;
+use crate::document::{
+    Document, DocumentMetadata, Service, VerificationMethod, absolute_id, canonical_json,
+};
 use crate::error::Error;
-use crate::{jwk, key, web, DidResolver};
+use crate::method::MethodRegistry;
+use crate::multicodec::{HashAlgorithm, multihash};
 
 /// Resolve a DID to a DID document.
 ///
@@ -22,8 +30,8 @@ use crate::{jwk, key, web, DidResolver};
 ///
 /// Caveats:
 /// - No JSON-LD Processing, however, valid JSON-LD is returned.
-/// - Ignores accept header.
-/// - Only returns application/did+ld+json.
+/// - Returns application/did+ld+json unless application/did+json is
+///   requested using the `accept` option.
 /// - did:key support for ed25519
 /// - did:web support for .well-known and path based DIDs.
 ///
@@ -33,54 +41,208 @@ use crate::{jwk, key, web, DidResolver};
 /// error as specified.
 pub async fn resolve(
     did: &str, opts: Option<Options>, resolver: impl DidResolver,
+) -> crate::Result<Resolved> {
+    resolve_with(did, opts, resolver, &MethodRegistry::new()).await
+}
+
+/// Resolve a DID to a DID document using the DID methods in the registry.
+///
+/// As for [`resolve`], but DIDs are resolved, and `alsoKnownAs` links
+/// followed, using the registry's DID methods rather than only the built-in
+/// methods.
+///
+/// # Errors
+///
+/// Returns a [DID resolution](https://www.w3.org/TR/did-core/#did-resolution-metadata)
+/// error as specified.
+pub async fn resolve_with<R: DidResolver>(
+    did: &str, opts: Option<Options>, resolver: R, registry: &MethodRegistry<R>,
 ) -> crate::Result<Resolved> {
     // use DID-specific resolver
-    let method = did.split(':').nth(1).unwrap_or_default();
+    let result = registry.resolve(did, opts.clone(), resolver.clone()).await;
+    Ok(complete_resolution(result, opts.as_ref(), resolver, registry).await)
+}
 
-    let result = match method {
-        "key" => key::DidKey::resolve(did),
-        "jwk" => jwk::DidJwk::resolve(did, opts, resolver),
-        "web" => web::DidWeb::resolve(did, opts, resolver).await,
-        _ => Err(Error::MethodNotSupported(format!("{method} is not supported"))),
+/// Complete a DID method's resolution of a DID: errors are reported in the
+/// resolution metadata, `alsoKnownAs` links are followed, and the requested
+/// representation of the document is returned.
+pub async fn complete_resolution<R: DidResolver>(
+    result: crate::Result<Resolved>, opts: Option<&Options>, resolver: R,
+    registry: &MethodRegistry<R>,
+) -> Resolved {
+    let accept = opts.and_then(|o| o.accept.clone());
+    let follow = opts.and_then(|o| o.follow_also_known_as).unwrap_or_default();
+    let link_opts = Options {
+        web_path_template: opts.and_then(|o| o.web_path_template.clone()),
+        web_timeout: opts.and_then(|o| o.web_timeout),
+        web_retry: opts.and_then(|o| o.web_retry),
+        ..Options::default()
     };
 
-    if let Err(e) = result {
-        return Ok(Resolved {
-            metadata: Metadata {
-                error: Some(e.to_string()),
-                error_message: Some(e.message()),
-                content_type: ContentType::DidLdJson,
-                ..Metadata::default()
-            },
-            ..Resolved::default()
-        });
+    let mut resolution = match result {
+        Ok(resolution) => resolution,
+        Err(e) => {
+            return Resolved {
+                context: "https://w3id.org/did-resolution/v1".into(),
+                metadata: Metadata {
+                    error: Some(e.code()),
+                    error_message: Some(e.message()),
+                    content_type: accept.unwrap_or_default(),
+                    ..Metadata::default()
+                },
+                ..Resolved::default()
+            };
+        }
+    };
+
+    // resolve linked DIDs
+    if follow > 0
+        && let Some(document) = &resolution.document
+    {
+        resolution.linked_documents =
+            resolve_links(document, follow, link_opts, resolver, registry).await;
+    }
+
+    // return the requested representation
+    if let Some(accept) = accept {
+        resolution.document = resolution.document.map(|d| d.to_representation(&accept));
+        resolution.linked_documents =
+            resolution.linked_documents.iter().map(|d| d.to_representation(&accept)).collect();
+        resolution.metadata.content_type = accept;
     }
 
-    result
+    resolution
+}
+
+/// The maximum number of `alsoKnownAs` links followed from a resolved
+/// document, whatever [`Options::follow_also_known_as`] requests.
+pub const MAX_ALSO_KNOWN_AS_DEPTH: usize = 3;
+
+/// The maximum number of linked DIDs resolved when following `alsoKnownAs`
+/// links.
+pub const MAX_LINKED_RESOLUTIONS: usize = 10;
+
+// Resolve the DIDs in a document's `alsoKnownAs` set, following up to `depth`
+// links. Each DID is resolved at most once, and DIDs that cannot be resolved
+// are skipped. The depth is clamped to `MAX_ALSO_KNOWN_AS_DEPTH` and at most
+// `MAX_LINKED_RESOLUTIONS` DIDs are resolved.
+//
+// Links are not verified: a linked document need not list the resolved DID in
+// its own `alsoKnownAs` set.
+async fn resolve_links<R: DidResolver>(
+    document: &Document, depth: usize, opts: Options, resolver: R, registry: &MethodRegistry<R>,
+) -> Vec<Document> {
+    let mut visited = vec![document.id.clone()];
+    let mut linked = vec![];
+
+    let mut dids = also_known_as_dids(document);
+    for _ in 0..depth.min(MAX_ALSO_KNOWN_AS_DEPTH) {
+        let mut next = vec![];
+        for did in dids {
+            if visited.contains(&did) {
+                continue;
+            }
+            if visited.len() > MAX_LINKED_RESOLUTIONS {
+                return linked;
+            }
+            visited.push(did.clone());
+            if let Ok(Resolved {
+                document: Some(document),
+                ..
+            }) = registry.resolve(&did, Some(opts.clone()), resolver.clone()).await
+            {
+                next.extend(also_known_as_dids(&document));
+                linked.push(document);
+            }
+        }
+        dids = next;
+    }
+
+    linked
+}
+
+// The DIDs in a document's `alsoKnownAs` set. Other URIs are ignored.
+fn also_known_as_dids(document: &Document) -> Vec<String> {
+    document
+        .also_known_as
+        .iter()
+        .flatten()
+        .filter_map(|aka| aka.parse::<DidUrl>().ok().map(|url| url.did()))
+        .collect()
 }
 
 /// Dereference a DID URL into a resource.
 ///
+/// A DID URL with a `service` parameter dereferences to the service's endpoint
+/// URL, with any `relativeRef` parameter appended. Otherwise, the DID URL is
+/// treated as the ID of a verification method.
+///
+/// The `versionId` and `versionTime` parameters select the version of the DID
+/// document to dereference. When the `hl` (hashlink) parameter is set, the
+/// resolved document must match it: the parameter is a multibase-encoded
+/// multihash of the document's canonical (JCS) form, as produced by
+/// [`crate::content_hash_id`].
+///
 /// # Errors
+///
+/// Returns an error if the DID cannot be resolved, the resolved document does
+/// not match the hashlink, or the requested service or verification method is
+/// not in the DID document.
 pub async fn dereference(
     did_url: &str, opts: Option<Options>, resolver: impl DidResolver,
+) -> crate::Result<Dereferenced> {
+    dereference_with(did_url, opts, resolver, &MethodRegistry::new()).await
+}
+
+/// Dereference a DID URL into a resource using the DID methods in the
+/// registry.
+///
+/// As for [`dereference`], but the DID is resolved using the registry's DID
+/// methods rather than only the built-in methods.
+///
+/// # Errors
+///
+/// Returns an error if the DID cannot be resolved, the resolved document does
+/// not match the hashlink, or the requested service or verification method is
+/// not in the DID document.
+pub async fn dereference_with<R: DidResolver>(
+    did_url: &str, opts: Option<Options>, resolver: R, registry: &MethodRegistry<R>,
 ) -> crate::Result<Dereferenced> {
     // extract DID from DID URL
-    let url = url::Url::parse(did_url)
-        .map_err(|e| Error::InvalidDidUrl(format!("issue parsing URL: {e}")))?;
-    let did = format!("did:{}", url.path());
+    let url = did_url.parse::<DidUrl>()?;
+    let did = url.did();
+    let params = url.parameters()?.unwrap_or_default();
+
+    // request the version of the DID document identified by the DID URL
+    let mut opts = opts.unwrap_or_default();
+    if params.version_id.is_some() {
+        opts.version_id.clone_from(&params.version_id);
+    }
+    if params.version_time.is_some() {
+        opts.version_time.clone_from(&params.version_time);
+    }
 
     // resolve DID document
-    let method = did_url.split(':').nth(1).unwrap_or_default();
-    let resolution = match method {
-        "key" => key::DidKey::resolve(&did)?,
-        "web" => web::DidWeb::resolve(&did, opts, resolver).await?,
-        _ => return Err(Error::MethodNotSupported(format!("{method} is not supported"))),
-    };
+    let resolution = registry.resolve(&did, Some(opts), resolver).await?;
 
     let Some(document) = resolution.document else {
         return Err(Error::InvalidDid("Unable to resolve DID document".into()));
     };
+    if let Some(hashlink) = &params.hashlink {
+        check_hashlink(&document, hashlink)?;
+    }
+
+    // select a service endpoint using the `service` and `relativeRef` parameters
+    if let Some(service) = &params.service {
+        let endpoint = service_endpoint(&document, service, params.relative_ref.as_deref())?;
+        return Ok(Dereferenced {
+            metadata: Metadata::default(),
+            content_stream: Some(Resource::Url(endpoint)),
+            content_metadata: Some(ContentMetadata {
+                document_metadata: resolution.document_metadata,
+            }),
+        });
+    }
 
     // process document to dereference DID URL for requested resource
     let Some(verifcation_methods) = document.verification_method else {
@@ -89,7 +251,8 @@ pub async fn dereference(
 
     // for now we assume the DID URL is the ID of the verification method
     // e.g. did:web:demo.credibil.io#key-0
-    let Some(vm) = verifcation_methods.iter().find(|vm| vm.id == did_url) else {
+    let vm_id = url.fragment.as_ref().map_or_else(|| did_url.to_string(), |f| format!("{did}#{f}"));
+    let Some(vm) = verifcation_methods.iter().find(|vm| absolute_id(&did, &vm.id) == vm_id) else {
         return Err(Error::NotFound("verification method not found".into()));
     };
 
@@ -105,6 +268,73 @@ pub async fn dereference(
     })
 }
 
+// Check the document matches a hashlink: a multibase-encoded multihash of the
+// document's canonical form.
+fn check_hashlink(document: &Document, hashlink: &str) -> crate::Result<()> {
+    let (_, expected) = multibase::decode(hashlink)
+        .map_err(|e| Error::InvalidDidUrl(format!("issue decoding hashlink: {e}")))?;
+    let Some(algorithm) = expected.first().and_then(|code| HashAlgorithm::from_code(*code)) else {
+        return Err(Error::InvalidDidUrl("unsupported hashlink algorithm".into()));
+    };
+
+    let value = serde_json::to_value(document)
+        .map_err(|e| Error::Other(anyhow::anyhow!("issue serializing document: {e}")))?;
+    let (_, actual) = multibase::decode(multihash(canonical_json(&value).as_bytes(), algorithm))
+        .map_err(|e| Error::Other(anyhow::anyhow!("issue decoding multihash: {e}")))?;
+
+    if actual != expected {
+        return Err(Error::IntegrityError(format!(
+            "{} does not match hashlink {hashlink}",
+            document.id
+        )));
+    }
+    Ok(())
+}
+
+// The URL of the service's endpoint, with the relative reference (already URL
+// decoded) appended to the endpoint's path. Query strings from the endpoint
+// and the relative reference are combined.
+//
+// See <https://www.w3.org/TR/did-resolution/#dereferencing-algorithm-primary>
+fn service_endpoint(
+    document: &Document, service: &str, relative_ref: Option<&str>,
+) -> crate::Result<String> {
+    let Some(service) =
+        document.service.iter().flatten().find(|s| {
+            s.id.rsplit_once('#').map_or(s.id.as_str(), |(_, fragment)| fragment) == service
+        })
+    else {
+        return Err(Error::NotFound(format!("service {service} not found")));
+    };
+    let endpoints = match &service.service_endpoint {
+        Quota::One(endpoint) => std::slice::from_ref(endpoint),
+        Quota::Many(endpoints) => endpoints.as_slice(),
+    };
+    let Some(endpoint) = endpoints.iter().find_map(|kind| match kind {
+        Kind::String(uri) => Some(uri),
+        Kind::Object(_) => None,
+    }) else {
+        return Err(Error::NotFound(format!("service {} has no URI endpoint", service.id)));
+    };
+
+    let Some(relative_ref) = relative_ref else {
+        return Ok(endpoint.clone());
+    };
+    let mut url = Url::parse(endpoint)
+        .map_err(|e| Error::InvalidDidUrl(format!("invalid service endpoint: {e}")))?;
+    let (path, query) =
+        relative_ref.split_once('?').map_or((relative_ref, None), |(p, q)| (p, Some(q)));
+
+    let joined = format!("{}/{}", url.path().trim_end_matches('/'), path.trim_start_matches('/'));
+    url.set_path(&joined);
+    if let Some(query) = query {
+        let query = url.query().map_or_else(|| query.to_string(), |q| format!("{q}&{query}"));
+        url.set_query(Some(&query));
+    }
+
+    Ok(url.to_string())
+}
+
 /// Used to pass addtional values to a `resolve` and `dereference` methods. Any
 /// properties used should be registered in the DID Specification Registries.
 ///
@@ -124,12 +354,126 @@ pub struct Options {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub accept: Option<ContentType>,
 
+    /// Resolve a specific version of the DID document. The version ID could
+    /// be sequential, a UUID, or method-specific.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version_id: Option<String>,
+
+    /// Resolve the version of the DID document that was valid at the specified
+    /// time. An XML datetime value normalized to UTC without sub-second
+    /// precision. For example: 2020-12-20T19:17:47Z.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version_time: Option<String>,
+
+    /// Bypass any cached copy of the DID document when resolving with
+    /// [`crate::resolve_cached`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub no_cache: Option<bool>,
+
+    /// A URL template for locating `did:web` documents hosted under a custom
+    /// prefix, such as `https://{host}/dids/{path}/did.json`. See
+    /// [`crate::decode_web_path_with_template`]. Defaults to the mapping
+    /// defined by the `did:web` specification.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub web_path_template: Option<String>,
+
+    /// Resolve DIDs in the document's `alsoKnownAs` set, returning them as
+    /// [`Resolved::linked_documents`]. The value is the number of links to
+    /// follow from the resolved document: `1` resolves its `alsoKnownAs` DIDs,
+    /// `2` also resolves theirs, and so on, up to [`MAX_ALSO_KNOWN_AS_DEPTH`]
+    /// links. Each DID is resolved at most once, so cyclic links terminate,
+    /// and at most [`MAX_LINKED_RESOLUTIONS`] DIDs are resolved. Linked
+    /// `did:web` documents are fetched using the same URL template, timeout,
+    /// and retry policy as the resolved document.
+    ///
+    /// Links are not verified: a linked document is returned whether or not
+    /// it lists the resolved DID in its own `alsoKnownAs` set, so it must not
+    /// be treated as the same subject without checking.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub follow_also_known_as: Option<usize>,
+
+    /// The maximum time to wait for each attempt to fetch a `did:web`
+    /// document. Defaults to [`DEFAULT_WEB_TIMEOUT`]. Serialized as a number
+    /// of milliseconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde(serialize_with = "serialize_optional_millis")]
+    #[serde(deserialize_with = "deserialize_optional_millis")]
+    pub web_timeout: Option<Duration>,
+
+    /// How failed attempts to fetch a `did:web` document are retried. Defaults
+    /// to [`RetryPolicy::default`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub web_retry: Option<RetryPolicy>,
+
     // pub public_key_format: Option<String>,
     /// Additional options.
     #[serde(flatten)]
     pub additional: Option<HashMap<String, Metadata>>,
 }
 
+/// The default time to wait for each attempt to fetch a `did:web` document.
+pub const DEFAULT_WEB_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Retry policy for fetching remotely hosted DID documents.
+///
+/// Only transient failures are retried: timeouts and resolver errors reported
+/// as [`Error::NetworkError`]. The delay before each retry doubles, starting
+/// from `backoff`.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RetryPolicy {
+    /// The number of retries after the first attempt fails.
+    pub retries: u32,
+
+    /// The delay before the first retry. Serialized as a number of
+    /// milliseconds.
+    #[serde(serialize_with = "serialize_millis", deserialize_with = "deserialize_millis")]
+    pub backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            retries: 2,
+            backoff: Duration::from_millis(500),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// The delay before the retry following the numbered (zero-based) attempt.
+    #[must_use]
+    pub const fn backoff(&self, attempt: u32) -> Duration {
+        self.backoff.saturating_mul(2u32.saturating_pow(attempt))
+    }
+}
+
+// Durations are serialized as a whole number of milliseconds.
+fn serialize_millis<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(u64::try_from(duration.as_millis()).unwrap_or(u64::MAX))
+}
+
+fn deserialize_millis<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    u64::deserialize(deserializer).map(Duration::from_millis)
+}
+
+// serde passes optional fields by reference
+#[allow(clippy::ref_option)]
+fn serialize_optional_millis<S: Serializer>(
+    duration: &Option<Duration>, serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match duration {
+        Some(duration) => serialize_millis(duration, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+fn deserialize_optional_millis<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Duration>, D::Error> {
+    Ok(Option::<u64>::deserialize(deserializer)?.map(Duration::from_millis))
+}
+
 /// The DID URL syntax supports parameters in the URL query component. Adding a
 /// DID parameter to a DID URL means the parameter becomes part of the
 /// identifier for a resource.
@@ -188,6 +532,12 @@ pub struct Resolved {
     /// DID document metadata.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub document_metadata: Option<DocumentMetadata>,
+
+    /// Documents for DIDs in the document's `alsoKnownAs` set, resolved when
+    /// the [`Options::follow_also_known_as`] option is set. The links are not
+    /// verified to be bidirectional.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub linked_documents: Vec<Document>,
 }
 
 /// `Dereferenced` contains the result of dereferencing a DID URL.
@@ -223,6 +573,10 @@ pub enum Resource {
 
     /// `Service` resource.
     Service(Service),
+
+    /// A service endpoint URL, selected using the `service` and
+    /// `relativeRef` DID parameters.
+    Url(String),
 }
 
 impl Default for Resource {
@@ -261,6 +615,10 @@ pub enum ContentType {
     #[default]
     #[serde(rename = "application/did+ld+json")]
     DidLdJson,
+
+    /// JSON representation of a DID document, without `@context`.
+    #[serde(rename = "application/did+json")]
+    DidJson,
     //
     // /// The JSON-LD Media Type.
     // #[serde(rename = "application/ld+json")]
@@ -281,10 +639,14 @@ pub struct ContentMetadata {
 
 #[cfg(test)]
 mod test {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
     use anyhow::anyhow;
     use insta::assert_json_snapshot as assert_snapshot;
 
     use super::*;
+    use crate::testing::MockWebHost;
 
     #[derive(Clone)]
     struct MockResolver;
@@ -295,12 +657,216 @@ mod test {
         }
     }
 
+    #[test]
+    fn web_options_millis() {
+        let options = Options {
+            web_timeout: Some(Duration::from_secs(5)),
+            web_retry: Some(RetryPolicy {
+                retries: 1,
+                backoff: Duration::from_millis(250),
+            }),
+            ..Options::default()
+        };
+        let json = serde_json::to_value(&options).expect("should serialize");
+        assert_eq!(
+            json,
+            serde_json::json!({"webTimeout": 5000, "webRetry": {"retries": 1, "backoff": 250}})
+        );
+
+        let options: Options = serde_json::from_value(json).expect("should deserialize");
+        assert_eq!(options.web_timeout, Some(Duration::from_secs(5)));
+        assert_eq!(options.web_retry.map(|retry| retry.backoff), Some(Duration::from_millis(250)));
+
+        let options: Options = serde_json::from_str("{}").expect("should deserialize");
+        assert!(options.web_timeout.is_none());
+    }
+
     #[test]
     fn error_code() {
         let err = Error::MethodNotSupported("Method not supported".into());
         assert_eq!(err.message(), "Method not supported");
     }
 
+    #[derive(Clone)]
+    struct NotFoundResolver;
+    impl DidResolver for NotFoundResolver {
+        async fn resolve(&self, url: &str) -> anyhow::Result<Document> {
+            Err(anyhow!("{url} returned 404"))
+        }
+    }
+
+    #[tokio::test]
+    async fn resolve_errors() {
+        let cases = [
+            ("not-a-did", "invalidDid"),
+            ("did:key:z6Mkabc", "invalidDid"),
+            ("did:key:z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK#key-0", "invalidDid"),
+            ("did:example:123", "methodNotSupported"),
+            ("did:web:demo.credibil.io", "notFound"),
+        ];
+
+        for (did, code) in cases {
+            let resolved = resolve(did, None, NotFoundResolver).await.expect("should return");
+            assert!(resolved.document.is_none());
+            assert_eq!(resolved.metadata.content_type, ContentType::DidLdJson);
+            assert_eq!(resolved.metadata.error.as_deref(), Some(code), "{did}");
+            assert!(resolved.metadata.error_message.is_some());
+        }
+    }
+
+    #[tokio::test]
+    async fn resolve_web() {
+        let resolved =
+            resolve("did:web:demo.credibil.io", None, MockResolver).await.expect("should resolve");
+        assert!(resolved.document.is_some());
+        assert!(resolved.metadata.error.is_none());
+    }
+
+    #[tokio::test]
+    async fn resolve_did_json() {
+        let opts = Options {
+            accept: Some(ContentType::DidJson),
+            ..Options::default()
+        };
+        let resolved = resolve("did:web:demo.credibil.io", Some(opts), MockResolver)
+            .await
+            .expect("should resolve");
+        assert_eq!(resolved.metadata.content_type, ContentType::DidJson);
+        let document = resolved.document.expect("should have document");
+        assert_eq!(document.context, []);
+    }
+
+    #[tokio::test]
+    async fn follow_also_known_as() {
+        const DID_KEY: &str = "did:key:z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK";
+
+        // a -> b -> c -> a, with a also linking to a did:key and a website
+        let host = MockWebHost::new();
+        let links = [
+            (
+                "did:web:a.example.com",
+                vec!["did:web:b.example.com", DID_KEY, "https://a.example.com"],
+            ),
+            ("did:web:b.example.com", vec!["did:web:c.example.com#key-0"]),
+            ("did:web:c.example.com", vec!["did:web:a.example.com"]),
+        ];
+        for (did, also_known_as) in links {
+            let document = Document {
+                id: did.into(),
+                also_known_as: Some(also_known_as.into_iter().map(Into::into).collect()),
+                ..Document::default()
+            };
+            host.register(did, document).expect("should register");
+        }
+
+        let resolve_linked = |follow_also_known_as| {
+            let opts = Options {
+                follow_also_known_as,
+                ..Options::default()
+            };
+            let host = host.clone();
+            async move {
+                let resolved = resolve("did:web:a.example.com", Some(opts), host)
+                    .await
+                    .expect("should resolve");
+                assert!(resolved.metadata.error.is_none());
+                resolved.linked_documents.into_iter().map(|d| d.id).collect::<Vec<_>>()
+            }
+        };
+
+        assert_eq!(resolve_linked(None).await, Vec::<String>::new());
+        assert_eq!(resolve_linked(Some(1)).await, vec!["did:web:b.example.com", DID_KEY]);
+
+        // the cycle back to a is not followed
+        assert_eq!(
+            resolve_linked(Some(5)).await,
+            vec!["did:web:b.example.com", DID_KEY, "did:web:c.example.com"]
+        );
+
+        // a long chain is followed no further than the maximum depth
+        for n in 0..8 {
+            let did = format!("did:web:{n}.chain.com");
+            let document = Document {
+                id: did.clone(),
+                also_known_as: Some(vec![format!("did:web:{}.chain.com", n + 1)]),
+                ..Document::default()
+            };
+            host.register(&did, document).expect("should register");
+        }
+        let opts = Options {
+            follow_also_known_as: Some(usize::MAX),
+            ..Options::default()
+        };
+        let resolved =
+            resolve("did:web:0.chain.com", Some(opts), host.clone()).await.expect("should resolve");
+        assert_eq!(resolved.linked_documents.len(), MAX_ALSO_KNOWN_AS_DEPTH);
+
+        // and a wide fan-out is capped
+        let also_known_as = (0..20).map(|n| format!("did:web:{n}.fan.com")).collect::<Vec<_>>();
+        for did in &also_known_as {
+            let document = Document {
+                id: did.clone(),
+                ..Document::default()
+            };
+            host.register(did, document).expect("should register");
+        }
+        let document = Document {
+            id: "did:web:fan.com".into(),
+            also_known_as: Some(also_known_as),
+            ..Document::default()
+        };
+        host.register("did:web:fan.com", document).expect("should register");
+        let opts = Options {
+            follow_also_known_as: Some(1),
+            ..Options::default()
+        };
+        let resolved = resolve("did:web:fan.com", Some(opts), host).await.expect("should resolve");
+        assert_eq!(resolved.linked_documents.len(), MAX_LINKED_RESOLUTIONS);
+    }
+
+    // Fails the first fetch of the linked did:web:b.example.com document.
+    #[derive(Clone, Default)]
+    struct FlakyLinkResolver {
+        attempts: Arc<AtomicUsize>,
+    }
+    impl DidResolver for FlakyLinkResolver {
+        async fn resolve(&self, url: &str) -> anyhow::Result<Document> {
+            let (id, also_known_as) = if url.starts_with("https://b.example.com") {
+                if self.attempts.fetch_add(1, Ordering::SeqCst) == 0 {
+                    return Err(Error::NetworkError(anyhow!("connection reset")).into());
+                }
+                ("did:web:b.example.com", None)
+            } else {
+                ("did:web:a.example.com", Some(vec!["did:web:b.example.com".into()]))
+            };
+            Ok(Document {
+                id: id.into(),
+                also_known_as,
+                ..Document::default()
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn link_fetch_options() {
+        let resolver = FlakyLinkResolver::default();
+        let opts = Options {
+            follow_also_known_as: Some(1),
+            web_retry: Some(RetryPolicy {
+                retries: 0,
+                backoff: Duration::from_millis(1),
+            }),
+            ..Options::default()
+        };
+
+        // linked DIDs are fetched with the caller's retry policy
+        let resolved = resolve("did:web:a.example.com", Some(opts), resolver.clone())
+            .await
+            .expect("should resolve");
+        assert_eq!(resolved.linked_documents, []);
+        assert_eq!(resolver.attempts.load(Ordering::SeqCst), 1);
+    }
+
     #[tokio::test]
     async fn deref_web() {
         const DID_URL: &str = "did:web:demo.credibil.io#key-0";
@@ -318,4 +884,93 @@ mod test {
             dereference(DID_URL, None, MockResolver).await.expect("should dereference");
         assert_snapshot!("deref_key", dereferenced);
     }
+
+    #[tokio::test]
+    async fn deref_hashlink() {
+        let document: Document =
+            serde_json::from_slice(include_bytes!("web/did-ecdsa.json")).expect("should parse");
+        let hashlink = crate::content_hash_id(&document).expect("should hash");
+
+        let did_url = format!("did:web:demo.credibil.io?hl={hashlink}#key-0");
+        let dereferenced =
+            dereference(&did_url, None, MockResolver).await.expect("should dereference");
+        let Some(Resource::VerificationMethod(vm)) = dereferenced.content_stream else {
+            panic!("should dereference to a verification method");
+        };
+        assert_eq!(vm.id, "did:web:demo.credibil.io#key-0");
+
+        // a hashlink for different content
+        let other: Document =
+            serde_json::from_slice(include_bytes!("web/did-eddsa.json")).expect("should parse");
+        let hashlink = crate::content_hash_id(&other).expect("should hash");
+        let did_url = format!("did:web:demo.credibil.io?hl={hashlink}#key-0");
+        let Err(Error::IntegrityError(_)) = dereference(&did_url, None, MockResolver).await else {
+            panic!("should reject mismatched hashlink");
+        };
+
+        let did_url = "did:web:demo.credibil.io?hl=not-a-hashlink#key-0";
+        let Err(Error::InvalidDidUrl(_)) = dereference(did_url, None, MockResolver).await else {
+            panic!("should reject malformed hashlink");
+        };
+    }
+
+    #[derive(Clone)]
+    struct ServiceResolver;
+    impl DidResolver for ServiceResolver {
+        async fn resolve(&self, _url: &str) -> anyhow::Result<Document> {
+            let document = serde_json::json!({
+                "id": "did:web:demo.credibil.io",
+                "service": [{
+                    "id": "did:web:demo.credibil.io#files",
+                    "type": "LinkedDomains",
+                    "serviceEndpoint": "https://files.example.com/storage/"
+                }, {
+                    "id": "#signed",
+                    "type": "LinkedDomains",
+                    "serviceEndpoint": "https://files.example.com/storage?token=abc"
+                }]
+            });
+            serde_json::from_value(document).map_err(|e| anyhow!("issue deserializing: {e}"))
+        }
+    }
+
+    #[tokio::test]
+    async fn deref_service() {
+        let cases = [
+            ("?service=files", "https://files.example.com/storage/"),
+            (
+                "?service=files&relativeRef=%2Fresume.pdf",
+                "https://files.example.com/storage/resume.pdf",
+            ),
+            (
+                "?service=files&relativeRef=cv%2Fresume.pdf",
+                "https://files.example.com/storage/cv/resume.pdf",
+            ),
+            (
+                "?service=signed&relativeRef=%2Fresume.pdf",
+                "https://files.example.com/storage/resume.pdf?token=abc",
+            ),
+            (
+                "?service=signed&relativeRef=%2Fresume.pdf%3Fversion%3D2",
+                "https://files.example.com/storage/resume.pdf?token=abc&version=2",
+            ),
+        ];
+
+        for (query, expected) in cases {
+            let did_url = format!("did:web:demo.credibil.io{query}");
+            let dereferenced =
+                dereference(&did_url, None, ServiceResolver).await.expect("should dereference");
+            assert_eq!(
+                dereferenced.content_stream,
+                Some(Resource::Url(expected.into())),
+                "{query}"
+            );
+        }
+
+        let Err(Error::NotFound(_)) =
+            dereference("did:web:demo.credibil.io?service=missing", None, ServiceResolver).await
+        else {
+            panic!("should not find service");
+        };
+    }
 }
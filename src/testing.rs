@@ -0,0 +1,287 @@
+//! # Testing
+//!
+//! In-memory implementations of the key management traits used by the crate,
+//! allowing downstream tests to create and sign DID documents without wiring
+//! up a key store.
+//!
+//! [`StaticOperator`] provides a fixed public key so created documents have
+//! deterministic DIDs.
+//!
+//! [`MockWebHost`] serves `did:web` documents from memory, standing in for the
+//! HTTP client used to resolve `did:web` DIDs.
+//!
+//! Enable with the `testing` feature.
+
+use std::collections::HashMap;
+use std::future::{self, Future};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Result, anyhow};
+use base64ct::{Base64UrlUnpadded, Encoding};
+use credibil_infosec::{Algorithm, Curve, KeyType, PublicKeyJwk, Signer};
+use ed25519_dalek::Signer as _;
+use rand::rngs::OsRng;
+
+use crate::document::{Document, jwk_to_multikey};
+use crate::error::Error;
+use crate::web::DidWeb;
+use crate::{DidOperator, DidResolver, KeyPurpose};
+
+/// An in-memory key ring backed by a generated signing key.
+///
+/// The key ring provides the signing key as verification material for all
+/// verification relationships other than key agreement, and signs using the
+/// key's native algorithm.
+#[derive(Clone, Debug)]
+pub struct MemoryKeyRing {
+    key: SigningKey,
+}
+
+#[derive(Clone, Debug)]
+enum SigningKey {
+    Ed25519(ed25519_dalek::SigningKey),
+    Secp256k1(k256::ecdsa::SigningKey),
+}
+
+impl MemoryKeyRing {
+    /// Create a key ring with a newly generated Ed25519 signing key.
+    #[must_use]
+    pub fn ed25519() -> Self {
+        Self {
+            key: SigningKey::Ed25519(ed25519_dalek::SigningKey::generate(&mut OsRng)),
+        }
+    }
+
+    /// Create a key ring with a newly generated secp256k1 signing key.
+    #[must_use]
+    pub fn secp256k1() -> Self {
+        Self {
+            key: SigningKey::Secp256k1(k256::ecdsa::SigningKey::random(&mut OsRng)),
+        }
+    }
+
+    /// The generated secret key bytes.
+    #[must_use]
+    pub fn secret_key(&self) -> Vec<u8> {
+        match &self.key {
+            SigningKey::Ed25519(key) => key.to_bytes().to_vec(),
+            SigningKey::Secp256k1(key) => key.to_bytes().to_vec(),
+        }
+    }
+
+    /// The generated public key as a JWK.
+    #[must_use]
+    pub fn public_key(&self) -> PublicKeyJwk {
+        match &self.key {
+            SigningKey::Ed25519(key) => PublicKeyJwk {
+                kty: KeyType::Okp,
+                crv: Curve::Ed25519,
+                x: Base64UrlUnpadded::encode_string(key.verifying_key().as_bytes()),
+                ..PublicKeyJwk::default()
+            },
+            SigningKey::Secp256k1(key) => {
+                let point = key.verifying_key().to_encoded_point(false);
+                PublicKeyJwk {
+                    kty: KeyType::Ec,
+                    crv: Curve::Es256K,
+                    x: point.x().map(|x| Base64UrlUnpadded::encode_string(x)).unwrap_or_default(),
+                    y: point.y().map(|y| Base64UrlUnpadded::encode_string(y)),
+                    ..PublicKeyJwk::default()
+                }
+            }
+        }
+    }
+}
+
+impl DidOperator for MemoryKeyRing {
+    fn verification(&self, purpose: KeyPurpose) -> Option<PublicKeyJwk> {
+        match purpose {
+            KeyPurpose::KeyAgreement => None,
+            _ => Some(self.public_key()),
+        }
+    }
+}
+
+impl Signer for MemoryKeyRing {
+    fn try_sign(&self, msg: &[u8]) -> impl Future<Output = Result<Vec<u8>>> + Send {
+        let signature = match &self.key {
+            SigningKey::Ed25519(key) => key.sign(msg).to_bytes().to_vec(),
+            SigningKey::Secp256k1(key) => {
+                let signature: k256::ecdsa::Signature =
+                    k256::ecdsa::signature::Signer::sign(key, msg);
+                signature.to_vec()
+            }
+        };
+        future::ready(Ok(signature))
+    }
+
+    fn verifying_key(&self) -> impl Future<Output = Result<Vec<u8>>> + Send {
+        let verifying_key = match &self.key {
+            SigningKey::Ed25519(key) => key.verifying_key().to_bytes().to_vec(),
+            SigningKey::Secp256k1(key) => {
+                key.verifying_key().to_encoded_point(true).as_bytes().to_vec()
+            }
+        };
+        future::ready(Ok(verifying_key))
+    }
+
+    fn algorithm(&self) -> Algorithm {
+        match &self.key {
+            SigningKey::Ed25519(_) => Algorithm::EdDSA,
+            SigningKey::Secp256k1(_) => Algorithm::ES256K,
+        }
+    }
+
+    /// The key's `did:key` DID URL.
+    fn verification_method(&self) -> impl Future<Output = Result<String>> + Send {
+        let did_url = jwk_to_multikey(&self.public_key())
+            .map(|multikey| format!("did:key:{multikey}#{multikey}"))
+            .map_err(Into::into);
+        future::ready(did_url)
+    }
+}
+
+/// A [`DidOperator`] that provides a fixed public key.
+///
+/// Like [`MemoryKeyRing`], the key is provided for all verification
+/// relationships other than key agreement.
+#[derive(Clone, Debug)]
+pub struct StaticOperator {
+    jwk: PublicKeyJwk,
+}
+
+impl StaticOperator {
+    /// Create an operator that provides the specified public key.
+    #[must_use]
+    pub const fn new(jwk: PublicKeyJwk) -> Self {
+        Self { jwk }
+    }
+}
+
+impl DidOperator for StaticOperator {
+    fn verification(&self, purpose: KeyPurpose) -> Option<PublicKeyJwk> {
+        match purpose {
+            KeyPurpose::KeyAgreement => None,
+            _ => Some(self.jwk.clone()),
+        }
+    }
+}
+
+/// A [`DidResolver`] that serves registered `did:web` documents from memory.
+///
+/// Documents are served at the URL their DID maps to, or at an explicit URL
+/// for documents hosted using a custom path template. Unregistered URLs fail
+/// with [`Error::NotFound`], as for an HTTP 404 response. Clones share the
+/// registered documents.
+#[derive(Clone, Debug, Default)]
+pub struct MockWebHost {
+    documents: Arc<Mutex<HashMap<String, Document>>>,
+}
+
+impl MockWebHost {
+    /// Create a host with no documents.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serve the document at the URL its `did:web` DID maps to.
+    ///
+    /// # Errors
+    ///
+    /// Will fail if the DID is not a valid `did:web` DID.
+    pub fn register(&self, did: &str, document: Document) -> crate::Result<()> {
+        self.register_at(&DidWeb::url(did)?, document);
+        Ok(())
+    }
+
+    /// Serve the document at the specified URL.
+    pub fn register_at(&self, url: &str, document: Document) {
+        if let Ok(mut documents) = self.documents.lock() {
+            documents.insert(url.to_string(), document);
+        }
+    }
+}
+
+impl DidResolver for MockWebHost {
+    fn resolve(&self, url: &str) -> impl Future<Output = Result<Document>> + Send {
+        // version parameters are ignored
+        let url = url.split_once('?').map_or(url, |(url, _)| url);
+        let document = self.documents.lock().map_err(|_| anyhow!("host lock poisoned")).and_then(
+            |documents| {
+                documents
+                    .get(url)
+                    .cloned()
+                    .ok_or_else(|| Error::NotFound(format!("{url} returned 404 Not Found")).into())
+            },
+        );
+        future::ready(document)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+
+    use super::*;
+    use crate::core::Kind;
+    use crate::document::{CreateOptions, MethodType, PublicKeyFormat};
+    use crate::web::DidWeb;
+
+    /// Returns the id of the document's first key agreement method.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the document has no key agreement method, or the method is
+    /// referenced rather than embedded.
+    pub fn key_agreement_id(document: &Document) -> String {
+        let key_agreement = document.key_agreement.as_ref().expect("should have key agreement");
+        let Kind::Object(vm) = &key_agreement[0] else {
+            panic!("key agreement should be embedded");
+        };
+        vm.id.clone()
+    }
+
+    #[tokio::test]
+    async fn create_web() {
+        let keyring = MemoryKeyRing::ed25519();
+        let options = CreateOptions {
+            public_key_format: PublicKeyFormat::JsonWebKey,
+            ..CreateOptions::default()
+        };
+        let document = DidWeb::create("https://demo.credibil.io/entity/funder", &keyring, options)
+            .expect("should create");
+
+        let vm = &document.verification_method.as_ref().expect("should have methods")[0];
+        assert_eq!(document.id, "did:web:demo.credibil.io:entity:funder");
+        assert_eq!(
+            vm.method_type,
+            MethodType::JsonWebKey {
+                public_key_jwk: keyring.public_key()
+            }
+        );
+
+        // verify a signature using the document's verification method
+        let signature = keyring.try_sign(b"payload").await.expect("should sign");
+        let jwk = vm.method_type.to_jwk().expect("should convert");
+        let key_bytes: [u8; 32] = Base64UrlUnpadded::decode_vec(&jwk.x)
+            .expect("should decode")
+            .try_into()
+            .expect("should be 32 bytes");
+        let verifying_key = VerifyingKey::from_bytes(&key_bytes).expect("should be valid");
+        let signature = Signature::from_slice(&signature).expect("should be valid signature");
+        verifying_key.verify(b"payload", &signature).expect("should verify");
+    }
+
+    #[tokio::test]
+    async fn secp256k1() {
+        let keyring = MemoryKeyRing::secp256k1();
+        assert_eq!(keyring.algorithm(), Algorithm::ES256K);
+        assert_eq!(keyring.secret_key().len(), 32);
+
+        let vm = keyring.verification_method().await.expect("should have method");
+        assert!(vm.starts_with("did:key:zQ3s"));
+        let signature = keyring.try_sign(b"payload").await.expect("should sign");
+        assert_eq!(signature.len(), 64);
+    }
+}
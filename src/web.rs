@@ -7,7 +7,9 @@
 //! - <https://w3c-ccg.github.io/did-method-web>
 //! - <https://w3c.github.io/did-resolution>
 
+pub mod fetch;
 pub mod operator;
+pub mod path;
 pub mod resolver;
 
 /// `DidWeb` provides a type for implementing `did:web` operation and 
@@ -0,0 +1,186 @@
+//! # DID Web Fetch
+//!
+//! Fetches `did:web` documents using the [`DidResolver`], bounding the time
+//! taken by each attempt and retrying transient failures.
+//!
+//! Timeouts and retry delays are driven by a single shared timer thread
+//! rather than an async runtime, so fetching works with any executor.
+
+use std::collections::BTreeMap;
+use std::future::{Future, poll_fn};
+use std::pin::{Pin, pin};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, LazyLock, Mutex, PoisonError};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::anyhow;
+
+use crate::DidResolver;
+use crate::document::Document;
+use crate::error::Error;
+use crate::resolution::RetryPolicy;
+
+/// Fetch the document at the URL, retrying network failures and timeouts
+/// according to the retry policy.
+///
+/// # Errors
+///
+/// Returns the resolver's error, or an [`Error::NetworkError`] if the final
+/// attempt timed out.
+pub async fn fetch(
+    resolver: impl DidResolver, url: &str, timeout: Duration, retry: &RetryPolicy,
+) -> crate::Result<Document> {
+    let mut attempt = 0;
+    loop {
+        let timed_out =
+            || Err(Error::NetworkError(anyhow!("fetching {url} timed out after {timeout:?}")));
+        let result = with_timeout(resolver.resolve(url), timeout)
+            .await
+            .map_or_else(timed_out, |result| result.map_err(resolver_error));
+
+        // only transport failures are transient
+        match result {
+            Err(Error::NetworkError(_)) if attempt < retry.retries => {
+                Delay::new(retry.backoff(attempt)).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+// Map a resolver's error to a DID resolution error.
+fn resolver_error(err: anyhow::Error) -> Error {
+    match err.downcast::<Error>() {
+        Ok(err) => err,
+        Err(err) => Error::NotFound(err.to_string()),
+    }
+}
+
+// Await the future, returning `None` if it does not complete in time.
+async fn with_timeout<F: Future>(future: F, timeout: Duration) -> Option<F::Output> {
+    let mut future = pin!(future);
+    let mut delay = Delay::new(timeout);
+    poll_fn(|cx| {
+        if let Poll::Ready(output) = future.as_mut().poll(cx) {
+            return Poll::Ready(Some(output));
+        }
+        if Pin::new(&mut delay).poll(cx).is_ready() {
+            return Poll::Ready(None);
+        }
+        Poll::Pending
+    })
+    .await
+}
+
+// A future that completes once the duration has elapsed. Pending delays are
+// woken by the shared timer thread.
+struct Delay {
+    deadline: Instant,
+    key: Option<TimerKey>,
+}
+
+impl Delay {
+    fn new(duration: Duration) -> Self {
+        Self {
+            deadline: Instant::now() + duration,
+            key: None,
+        }
+    }
+}
+
+impl Future for Delay {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let this = self.get_mut();
+        if Instant::now() >= this.deadline {
+            if let Some(key) = this.key.take() {
+                TIMER.cancel(key);
+            }
+            return Poll::Ready(());
+        }
+
+        let key = *this.key.get_or_insert_with(|| TIMER.key(this.deadline));
+        TIMER.register(key, cx.waker());
+        Poll::Pending
+    }
+}
+
+impl Drop for Delay {
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            TIMER.cancel(key);
+        }
+    }
+}
+
+// Pending delays, ordered by deadline. The key's sequence number
+// distinguishes delays with the same deadline.
+type TimerKey = (Instant, u64);
+
+// A single thread wakes every pending delay, so waiting does not need a
+// thread per delay.
+static TIMER: LazyLock<Arc<Timer>> = LazyLock::new(|| {
+    let timer = Arc::new(Timer::default());
+    let shared = Arc::clone(&timer);
+    thread::spawn(move || shared.run());
+    timer
+});
+
+#[derive(Default)]
+struct Timer {
+    pending: Mutex<BTreeMap<TimerKey, Waker>>,
+    changed: Condvar,
+    sequence: AtomicU64,
+}
+
+impl Timer {
+    fn key(&self, deadline: Instant) -> TimerKey {
+        (deadline, self.sequence.fetch_add(1, Ordering::Relaxed))
+    }
+
+    fn register(&self, key: TimerKey, waker: &Waker) {
+        let mut pending = self.pending.lock().unwrap_or_else(PoisonError::into_inner);
+        let earliest = pending.first_key_value().is_none_or(|(first, _)| key <= *first);
+        pending.insert(key, waker.clone());
+        drop(pending);
+
+        // only an earlier deadline changes how long the timer thread waits
+        if earliest {
+            self.changed.notify_one();
+        }
+    }
+
+    fn cancel(&self, key: TimerKey) {
+        self.pending.lock().unwrap_or_else(PoisonError::into_inner).remove(&key);
+    }
+
+    // Wake delays as their deadlines pass, sleeping until the earliest
+    // deadline or a new registration.
+    fn run(&self) {
+        let mut pending = self.pending.lock().unwrap_or_else(PoisonError::into_inner);
+        loop {
+            let now = Instant::now();
+            while let Some(entry) = pending.first_entry() {
+                if entry.key().0 > now {
+                    break;
+                }
+                entry.remove().wake();
+            }
+
+            pending = match pending.first_key_value() {
+                Some(((deadline, _), _)) => {
+                    let timeout = deadline.saturating_duration_since(now);
+                    self.changed
+                        .wait_timeout(pending, timeout)
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .0
+                }
+                None => self.changed.wait(pending).unwrap_or_else(PoisonError::into_inner),
+            };
+        }
+    }
+}
@@ -6,49 +6,60 @@
 
 use anyhow::anyhow;
 use base64ct::{Base64UrlUnpadded, Encoding};
+use chrono::Utc;
+use credibil_infosec::{Curve, KeyType, PublicKeyJwk};
 use curve25519_dalek::edwards::CompressedEdwardsY;
-use multibase::Base;
-use url::Url;
 
 use super::DidWeb;
 use crate::core::Kind;
-use crate::document::{CreateOptions, Document, MethodType, PublicKeyFormat, VerificationMethod};
+use crate::document::{
+    Context, CreateOptions, Document, DocumentMetadata, MethodType, PublicKeyFormat,
+    VerificationMethod, absolute_id, append_contexts, content_hash_id, jwk_to_multikey,
+};
 use crate::error::Error;
-use crate::{DidOperator, ED25519_CODEC, KeyPurpose, X25519_CODEC};
+use crate::{DidOperator, DocumentStore, KeyPurpose, multicodec};
 
 // TODO: request public key from DidOperator for each verification relationship
 
 impl DidWeb {
     /// Create a new DID Document from the provided `did:web` DID URL.
     ///
+    /// When [`CreateOptions::key_agreement_key`] is set, it is used as the
+    /// document's key agreement method in preference to deriving one from the
+    /// verifying key. This allows encryption keys generated separately from
+    /// the signing key.
+    ///
     /// # Errors
     ///
-    /// Will fail if the DID URL is not a valid or the verifying key is invalid.
+    /// Will fail if the DID URL is not a valid or the verifying key is invalid,
+    /// a provided key agreement key is not X25519, or encryption key derivation
+    /// is requested for a verifying key that is not Ed25519.
     pub fn create(
         url: &str, op: &impl DidOperator, options: CreateOptions,
     ) -> crate::Result<Document> {
         // create identifier from url
-        let url =
-            Url::parse(url).map_err(|e| Error::InvalidDid(format!("issue parsing url: {e}")))?;
-        let host = url.host_str().ok_or(Error::InvalidDid("no host in url".into()))?;
-        let mut did = format!("did:web:{host}");
-        if let Some(path) = url.path().strip_prefix('/')
-            && !path.is_empty()
-        {
-            did = format!("{did}:{}", path.replace('/', ":"));
-        }
+        let did = Self::url_to_did(url)?;
 
         // get DID controller's verification key
         let Some(verifying_key) = op.verification(KeyPurpose::VerificationMethod) else {
             return Err(Error::Other(anyhow!("no verification key")));
         };
-        let key_bytes = Base64UrlUnpadded::decode_vec(&verifying_key.x)
-            .map_err(|e| Error::InvalidPublicKey(format!("issue decoding key: {e}")))?;
 
         // key agreement
         // <https://w3c-ccg.github.io/did-method-key/#encryption-method-creation-algorithm>
-        let key_agreement = if options.enable_encryption_key_derivation {
+        let key_agreement = if let Some(agreement_key) = options.key_agreement_key.clone() {
+            // attach the explicitly provided key rather than deriving one
+            Some(vec![Kind::Object(agreement_method(&did, agreement_key, &options)?)])
+        } else if options.enable_encryption_key_derivation {
+            if verifying_key.crv != Curve::Ed25519 {
+                return Err(Error::InvalidPublicKey(
+                    "encryption key derivation requires Ed25519".into(),
+                ));
+            }
+
             // derive an X25519 public encryption key from the Ed25519 key
+            let key_bytes = Base64UrlUnpadded::decode_vec(&verifying_key.x)
+                .map_err(|e| Error::InvalidPublicKey(format!("issue decoding key: {e}")))?;
             let edwards_y = CompressedEdwardsY::from_slice(&key_bytes).map_err(|e| {
                 Error::InvalidPublicKey(format!("public key is not Edwards Y: {e}"))
             })?;
@@ -60,15 +71,16 @@ impl DidWeb {
             let x25519_bytes = edwards_pt.to_montgomery().to_bytes();
 
             let method_type = match options.public_key_format {
-                PublicKeyFormat::Multikey => {
-                    let mut multi_bytes = X25519_CODEC.to_vec();
-                    multi_bytes.extend_from_slice(&x25519_bytes);
-                    MethodType::Multikey {
-                        public_key_multibase: multibase::encode(Base::Base58Btc, &multi_bytes),
-                    }
-                }
+                PublicKeyFormat::Multikey => MethodType::Multikey {
+                    public_key_multibase: multicodec::encode_multikey(
+                        &KeyType::Okp,
+                        &Curve::X25519,
+                        &x25519_bytes,
+                    )?,
+                },
                 PublicKeyFormat::JsonWebKey => {
                     let mut jwk = verifying_key.clone();
+                    jwk.crv = Curve::X25519;
                     jwk.x = Base64UrlUnpadded::encode_string(&x25519_bytes);
                     MethodType::JsonWebKey { public_key_jwk: jwk }
                 }
@@ -76,8 +88,16 @@ impl DidWeb {
                 _ => return Err(Error::InvalidPublicKey("Unsupported public key format".into())),
             };
 
+            let agreement_key = PublicKeyJwk {
+                kty: KeyType::Okp,
+                crv: Curve::X25519,
+                x: Base64UrlUnpadded::encode_string(&x25519_bytes),
+                ..PublicKeyJwk::default()
+            };
+            let fragment = options.verification_method_id.fragment(&agreement_key, 1)?;
+
             Some(vec![Kind::Object(VerificationMethod {
-                id: format!("{did}#key-1"),
+                id: format!("{did}#{fragment}"),
                 controller: did.clone(),
                 method_type,
                 ..VerificationMethod::default()
@@ -86,26 +106,30 @@ impl DidWeb {
             None
         };
 
-        let kid = format!("{did}#key-0");
+        let fragment = options.verification_method_id.fragment(&verifying_key, 0)?;
+        let kid = format!("{did}#{fragment}");
         let method_type = match options.public_key_format {
-            PublicKeyFormat::Multikey => {
-                // multibase encode the public key
-                let mut multi_bytes = ED25519_CODEC.to_vec();
-                multi_bytes.extend_from_slice(&key_bytes);
-                MethodType::Multikey {
-                    public_key_multibase: multibase::encode(Base::Base58Btc, &multi_bytes),
-                }
-            }
+            PublicKeyFormat::Multikey => MethodType::Multikey {
+                // multibase encode the public key using the curve's own codec
+                public_key_multibase: jwk_to_multikey(&verifying_key)?,
+            },
             PublicKeyFormat::JsonWebKey => MethodType::JsonWebKey {
                 public_key_jwk: verifying_key,
             },
             _ => return Err(Error::InvalidPublicKey("Unsupported public key format".into())),
         };
 
-        let context = Kind::String("https://w3id.org/security/data-integrity/v1".into());
+        let context = if options.public_key_format == PublicKeyFormat::Multikey {
+            Context::from("https://w3id.org/security/multikey/v1")
+        } else {
+            Context::from("https://w3id.org/security/data-integrity/v1")
+        };
 
         Ok(Document {
-            context: vec![Kind::String(options.default_context), context],
+            context: append_contexts(
+                vec![Context::from(options.default_context), context],
+                options.additional_contexts,
+            ),
             id: did.clone(),
             verification_method: Some(vec![VerificationMethod {
                 id: kid.clone(),
@@ -122,6 +146,146 @@ impl DidWeb {
         })
     }
 
+    /// Update a hosted DID document by writing the updated document back to
+    /// the [`DocumentStore`]. The document's `@context` is normalized before
+    /// it is written.
+    ///
+    /// # Errors
+    ///
+    /// Will fail if the document has not previously been stored or has been
+    /// deactivated, a verification method's key cannot be used for its
+    /// relationship, or the store returns an error.
+    pub async fn update(
+        mut document: Document, store: &impl DocumentStore,
+    ) -> crate::Result<Document> {
+        let Some(stored) = store.get(&document.id).await? else {
+            return Err(Error::NotFound(format!("{} not found", document.id)));
+        };
+        if is_deactivated(&stored) {
+            return Err(Error::Deactivated(format!("{} has been deactivated", document.id)));
+        }
+        document.check_purposes()?;
+        document.normalize_context();
+        store.put(&document.id, &document).await?;
+        Ok(document)
+    }
+
+    /// Update a hosted DID document when the update is authorized by the DID
+    /// controller.
+    ///
+    /// The updated document must carry a [`Proof`](crate::Proof) with a
+    /// `capabilityInvocation` purpose, signed by one of the stored document's
+    /// `capabilityInvocation` keys. The proof's `challenge` must be the
+    /// stored document's [`DidWeb::update_challenge`], so a proof authorizes
+    /// a single update and cannot be replayed against later versions. The
+    /// proof is stored with the document.
+    ///
+    /// # Errors
+    ///
+    /// Will fail with [`Error::InvalidProof`] if the update is not authorized
+    /// by the stored document's capability invocation keys or was not made
+    /// against the stored document, or for any reason [`DidWeb::update`]
+    /// fails.
+    pub async fn update_authorized(
+        document: Document, store: &impl DocumentStore,
+    ) -> crate::Result<Document> {
+        let Some(stored) = store.get(&document.id).await? else {
+            return Err(Error::NotFound(format!("{} not found", document.id)));
+        };
+        let Some(proof) = &document.proof else {
+            return Err(Error::InvalidProof("update has no proof".into()));
+        };
+        if proof.proof_purpose != KeyPurpose::CapabilityInvocation {
+            return Err(Error::InvalidProof(format!(
+                "update proof purpose is {}, not capabilityInvocation",
+                proof.proof_purpose
+            )));
+        }
+        if proof.challenge != Some(Self::update_challenge(&stored)?) {
+            return Err(Error::InvalidProof(
+                "update proof challenge does not match the stored document".into(),
+            ));
+        }
+
+        let vm_id = absolute_id(&stored.id, &proof.verification_method);
+        let Some(vm) = stored
+            .verification_methods_for(KeyPurpose::CapabilityInvocation)
+            .into_iter()
+            .find(|vm| absolute_id(&stored.id, &vm.id) == vm_id)
+        else {
+            return Err(Error::InvalidProof(format!("{vm_id} is not a capability invocation key")));
+        };
+        document.verify_proof_with_key(&vm.method_type.to_jwk()?)?;
+
+        Self::update(document, store).await
+    }
+
+    /// The challenge a proof authorizing an update of the stored document
+    /// must carry: the content hash of the document as stored, including its
+    /// metadata and any proof. See [`crate::content_hash_id`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the document cannot be serialized.
+    pub fn update_challenge(stored: &Document) -> crate::Result<String> {
+        content_hash_id(stored)
+    }
+
+    /// Deactivate a hosted DID document by marking it as deactivated in the
+    /// [`DocumentStore`]. The stored document keeps its verification methods,
+    /// but resolving the DID reports it as a tombstone would be reported: no
+    /// document, and `deactivated: true` in the document metadata.
+    ///
+    /// Returns the deactivated document's metadata.
+    ///
+    /// # Errors
+    ///
+    /// Will fail if the document is not found or the store returns an error.
+    pub async fn deactivate(
+        did: &str, store: &impl DocumentStore,
+    ) -> crate::Result<DocumentMetadata> {
+        let Some(mut document) = store.get(did).await? else {
+            return Err(Error::NotFound(format!("{did} not found")));
+        };
+        let metadata = document.did_document_metadata.get_or_insert_default();
+        metadata.deactivated = Some(true);
+        metadata.updated = Some(Utc::now());
+        let metadata = metadata.clone();
+
+        store.put(did, &document).await?;
+        Ok(metadata)
+    }
+
+    /// Recover a DID document by re-publishing it to the [`DocumentStore`].
+    /// A deactivated document, including a tombstone, is overwritten and the
+    /// DID reactivated.
+    ///
+    /// The document should be the last known good version of the DID
+    /// document, such as a backup taken before deactivation.
+    ///
+    /// # Errors
+    ///
+    /// Will fail if the document has no verification methods (as would
+    /// re-publishing a tombstone), a verification method's key cannot be used
+    /// for its relationship, or the store returns an error.
+    pub async fn recover(
+        mut document: Document, store: &impl DocumentStore,
+    ) -> crate::Result<Document> {
+        if document.verification_method.as_ref().is_none_or(Vec::is_empty) {
+            return Err(Error::InvalidInput(format!(
+                "cannot recover {} from a document with no verification methods",
+                document.id
+            )));
+        }
+        document.check_purposes()?;
+        document.normalize_context();
+        if let Some(metadata) = &mut document.did_document_metadata {
+            metadata.deactivated = None;
+        }
+        store.put(&document.id, &document).await?;
+        Ok(document)
+    }
+
     // #[allow(dead_code)]
     // pub fn read(_did: &str, _: CreateOptions) -> crate::Result<Document> {
     //     // self.create(did, options)
@@ -129,13 +293,53 @@ impl DidWeb {
     // }
 }
 
+// The key agreement method for an explicitly provided X25519 key.
+fn agreement_method(
+    did: &str, agreement_key: PublicKeyJwk, options: &CreateOptions,
+) -> crate::Result<VerificationMethod> {
+    if agreement_key.crv != Curve::X25519 {
+        return Err(Error::InvalidPublicKey("key agreement key is not X25519".into()));
+    }
+    let fragment = options.verification_method_id.fragment(&agreement_key, 1)?;
+    let method_type = match options.public_key_format {
+        PublicKeyFormat::Multikey => MethodType::Multikey {
+            public_key_multibase: jwk_to_multikey(&agreement_key)?,
+        },
+        PublicKeyFormat::JsonWebKey => MethodType::JsonWebKey {
+            public_key_jwk: agreement_key,
+        },
+        _ => return Err(Error::InvalidPublicKey("Unsupported public key format".into())),
+    };
+
+    Ok(VerificationMethod {
+        id: format!("{did}#{fragment}"),
+        controller: did.to_string(),
+        method_type,
+        ..VerificationMethod::default()
+    })
+}
+
+// Whether the stored document has been deactivated.
+fn is_deactivated(document: &Document) -> bool {
+    document.did_document_metadata.as_ref().and_then(|md| md.deactivated) == Some(true)
+}
+
 #[cfg(test)]
 mod test {
-    use credibil_infosec::{Curve, KeyType, PublicKeyJwk};
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
     use ed25519_dalek::SigningKey;
     use rand::rngs::OsRng;
+    use serde_json::json;
 
     use super::*;
+    use crate::DidResolver;
+    use crate::core::Quota;
+    use crate::document::{Service, VerificationMethodId};
+    use crate::proof::ProofOptions;
+    use crate::testing::test::key_agreement_id;
+    use crate::testing::{MemoryKeyRing, StaticOperator};
 
     struct MockOperator;
     impl DidOperator for MockOperator {
@@ -182,6 +386,382 @@ mod test {
         println!("{json}");
     }
 
+    #[test]
+    fn derivation_requires_ed25519() {
+        let url = "https://demo.credibil.io/entity/funder";
+        let mut options = CreateOptions::default();
+        options.enable_encryption_key_derivation = true;
+
+        let keyring = MemoryKeyRing::secp256k1();
+        let Err(Error::InvalidPublicKey(msg)) = DidWeb::create(url, &keyring, options) else {
+            panic!("should reject derivation from a secp256k1 key");
+        };
+        assert_eq!(msg, "encryption key derivation requires Ed25519");
+    }
+
+    #[test]
+    fn secp256k1_multikey() {
+        let url = "https://demo.credibil.io/entity/funder";
+        let keyring = MemoryKeyRing::secp256k1();
+        let verifying_key =
+            keyring.verification(KeyPurpose::VerificationMethod).expect("should have key");
+
+        let document =
+            DidWeb::create(url, &keyring, CreateOptions::default()).expect("should create");
+        let methods = document.verification_method.expect("should have methods");
+        let MethodType::Multikey { public_key_multibase } = &methods[0].method_type else {
+            panic!("should be a Multikey");
+        };
+
+        let (_, curve, key) =
+            multicodec::decode_multikey(public_key_multibase).expect("should decode");
+        assert_eq!(curve, Curve::Es256K);
+        assert_eq!(key.len(), 33);
+        let jwk = methods[0].method_type.to_jwk().expect("should convert");
+        assert_eq!(jwk, verifying_key);
+    }
+
+    #[test]
+    fn verification_method_id() {
+        let url = "https://demo.credibil.io/entity/funder";
+        let op = StaticOperator::new(PublicKeyJwk {
+            kty: KeyType::Okp,
+            crv: Curve::Ed25519,
+            x: "11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo".into(),
+            ..PublicKeyJwk::default()
+        });
+        let create = |verification_method_id| {
+            let options = CreateOptions {
+                verification_method_id,
+                enable_encryption_key_derivation: true,
+                ..CreateOptions::default()
+            };
+            let document = DidWeb::create(url, &op, options).expect("should create");
+            let vm = &document.verification_method.as_ref().expect("should have methods")[0];
+            let fragment =
+                vm.id.strip_prefix(&format!("{}#", document.id)).expect("should be DID URL");
+            (fragment.to_string(), key_agreement_id(&document))
+        };
+
+        let (fragment, agreement) = create(VerificationMethodId::Sequential);
+        assert_eq!(fragment, "key-0");
+        assert!(agreement.ends_with("#key-1"));
+
+        let (fragment, _) = create(VerificationMethodId::Random);
+        assert_eq!(fragment.len(), 16);
+        assert!(fragment.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(create(VerificationMethodId::Random).0, fragment);
+
+        let (fragment, agreement) = create(VerificationMethodId::JwkThumbprint);
+        assert_eq!(fragment, "kPrK_qmxVWaYVA9wwBF6Iuo3vVzz7TxHCTwXBygrS4k");
+        assert_eq!(create(VerificationMethodId::JwkThumbprint).1, agreement);
+
+        let (fragment, agreement) = create(VerificationMethodId::Fragment("signing".into()));
+        assert_eq!(fragment, "signing");
+        assert!(agreement.ends_with("#signing-x25519"));
+
+        // fragments must not be empty or contain DID URL delimiters
+        for fragment in ["", "#signing", "keys/signing", "signing?v=1"] {
+            let options = CreateOptions {
+                verification_method_id: VerificationMethodId::Fragment(fragment.into()),
+                ..CreateOptions::default()
+            };
+            let Err(Error::InvalidInput(_)) = DidWeb::create(url, &op, options) else {
+                panic!("should reject fragment '{fragment}'");
+            };
+        }
+    }
+
+    #[test]
+    fn explicit_key_agreement() {
+        let agreement_key = PublicKeyJwk {
+            kty: KeyType::Okp,
+            crv: Curve::X25519,
+            x: Base64UrlUnpadded::encode_string(&[7u8; 32]),
+            ..PublicKeyJwk::default()
+        };
+        let options = CreateOptions {
+            public_key_format: PublicKeyFormat::JsonWebKey,
+            key_agreement_key: Some(agreement_key.clone()),
+            // the provided key is used in preference to a derived key
+            enable_encryption_key_derivation: true,
+            ..CreateOptions::default()
+        };
+        let document = DidWeb::create("https://demo.credibil.io", &MockOperator, options)
+            .expect("should create");
+
+        let key_agreement = document.key_agreement.expect("should have key agreement");
+        let Kind::Object(vm) = &key_agreement[0] else {
+            panic!("key agreement should be embedded");
+        };
+        assert_eq!(vm.id, "did:web:demo.credibil.io#key-1");
+        assert_eq!(
+            vm.method_type,
+            MethodType::JsonWebKey {
+                public_key_jwk: agreement_key
+            }
+        );
+
+        // key agreement keys must be X25519
+        let options = CreateOptions {
+            key_agreement_key: Some(MemoryKeyRing::ed25519().public_key()),
+            ..CreateOptions::default()
+        };
+        let Err(Error::InvalidPublicKey(_)) =
+            DidWeb::create("https://demo.credibil.io", &MockOperator, options)
+        else {
+            panic!("should reject an Ed25519 key agreement key");
+        };
+
+        // an operator's key for other purposes is not used for key agreement
+        let op = SingleKeyOperator(MemoryKeyRing::ed25519().public_key());
+        let options = CreateOptions {
+            enable_encryption_key_derivation: true,
+            ..CreateOptions::default()
+        };
+        let document =
+            DidWeb::create("https://demo.credibil.io", &op, options).expect("should create");
+        let key_agreement = document.key_agreement.expect("should have key agreement");
+        let Kind::Object(vm) = &key_agreement[0] else {
+            panic!("key agreement should be embedded");
+        };
+        let jwk = vm.method_type.to_jwk().expect("should convert");
+        assert_eq!(jwk.crv, Curve::X25519);
+    }
+
+    // Provides the same key for every purpose.
+    struct SingleKeyOperator(PublicKeyJwk);
+    impl DidOperator for SingleKeyOperator {
+        fn verification(&self, _purpose: KeyPurpose) -> Option<PublicKeyJwk> {
+            Some(self.0.clone())
+        }
+    }
+
+    #[tokio::test]
+    async fn update_deactivate() {
+        let url = "https://demo.credibil.io/entity/funder";
+        let document =
+            DidWeb::create(url, &MockOperator, CreateOptions::default()).expect("should create");
+        let store = MockStore::default();
+        store.put(&document.id, &document).await.expect("should store");
+
+        // update
+        let mut updated = document.clone();
+        updated.also_known_as = Some(vec!["https://funder.example.com".into()]);
+        DidWeb::update(updated.clone(), &store).await.expect("should update");
+        let stored = store.get(&document.id).await.expect("should get");
+        assert_eq!(stored, Some(updated));
+
+        // deactivate
+        let metadata = DidWeb::deactivate(&document.id, &store).await.expect("should deactivate");
+        assert_eq!(metadata.deactivated, Some(true));
+        let stored = store.get(&document.id).await.expect("should get").expect("should exist");
+        assert_eq!(stored.did_document_metadata, Some(metadata));
+
+        // updating a deactivated document fails
+        let Err(Error::Deactivated(_)) = DidWeb::update(document.clone(), &store).await else {
+            panic!("should not update deactivated document");
+        };
+
+        // resolving a deactivated document reports it as deactivated, as
+        // for a tombstone
+        let resolved = DidWeb::resolve(&document.id, None, StoredResolver(stored))
+            .await
+            .expect("should resolve");
+        assert!(resolved.document.is_none());
+        let metadata = resolved.document_metadata.expect("should have metadata");
+        assert_eq!(metadata.deactivated, Some(true));
+
+        // recover
+        DidWeb::recover(document.clone(), &store).await.expect("should recover");
+        assert_eq!(store.get(&document.id).await.expect("should get"), Some(document));
+    }
+
+    #[tokio::test]
+    async fn recover_tombstone() {
+        let url = "https://demo.credibil.io/entity/funder";
+        let backup =
+            DidWeb::create(url, &MockOperator, CreateOptions::default()).expect("should create");
+        let store = MockStore::default();
+
+        // the host serves a tombstone in place of the document
+        let tombstone = Document {
+            id: backup.id.clone(),
+            did_document_metadata: Some(DocumentMetadata {
+                deactivated: Some(true),
+                ..DocumentMetadata::default()
+            }),
+            ..Document::default()
+        };
+        store.put(&backup.id, &tombstone).await.expect("should store");
+        let resolved = DidWeb::resolve(&backup.id, None, StoredResolver(tombstone.clone()))
+            .await
+            .expect("should resolve");
+        assert!(resolved.document.is_none());
+
+        // a tombstone cannot be used to recover
+        let Err(Error::InvalidInput(_)) = DidWeb::recover(tombstone, &store).await else {
+            panic!("should not recover from a tombstone");
+        };
+
+        // recovering from a backup overwrites the tombstone
+        let mut deactivated = backup.clone();
+        deactivated.did_document_metadata = Some(DocumentMetadata {
+            deactivated: Some(true),
+            ..DocumentMetadata::default()
+        });
+        DidWeb::recover(deactivated, &store).await.expect("should recover");
+        let stored = store.get(&backup.id).await.expect("should get").expect("should exist");
+
+        let resolved = DidWeb::resolve(&backup.id, None, StoredResolver(stored))
+            .await
+            .expect("should resolve");
+        assert_eq!(
+            resolved.document.map(|d| d.verification_method),
+            Some(backup.verification_method)
+        );
+        let metadata = resolved.document_metadata.expect("should have metadata");
+        assert_eq!(metadata.deactivated, None);
+    }
+
+    #[tokio::test]
+    async fn update_authorized() {
+        let controller = MemoryKeyRing::ed25519();
+        let url = "https://demo.credibil.io/entity/funder";
+        let document =
+            DidWeb::create(url, &controller, CreateOptions::default()).expect("should create");
+        let store = MockStore::default();
+        store.put(&document.id, &document).await.expect("should store");
+
+        let mut updated = document.clone();
+        updated.also_known_as = Some(vec!["https://funder.example.com".into()]);
+        let options = ProofOptions {
+            purpose: KeyPurpose::CapabilityInvocation,
+            verification_method: Some("#key-0".into()),
+            challenge: Some(DidWeb::update_challenge(&document).expect("should hash")),
+            ..ProofOptions::default()
+        };
+
+        // signed by a key not in the document
+        let mut unauthorized = updated.clone();
+        unauthorized
+            .add_proof(&MemoryKeyRing::ed25519(), options.clone())
+            .await
+            .expect("should add proof");
+        let Err(Error::InvalidProof(_)) = DidWeb::update_authorized(unauthorized, &store).await
+        else {
+            panic!("should not apply an unauthorized update");
+        };
+        assert_eq!(store.get(&document.id).await.expect("should get"), Some(document.clone()));
+
+        // signed without the stored document's challenge
+        let mut unbound = updated.clone();
+        let unbound_options = ProofOptions {
+            challenge: None,
+            ..options.clone()
+        };
+        unbound.add_proof(&controller, unbound_options).await.expect("should add proof");
+        let Err(Error::InvalidProof(_)) = DidWeb::update_authorized(unbound, &store).await else {
+            panic!("should not apply an update without a challenge");
+        };
+
+        // signed by the controller's capability invocation key
+        updated.add_proof(&controller, options).await.expect("should add proof");
+        DidWeb::update_authorized(updated.clone(), &store).await.expect("should update");
+        assert_eq!(store.get(&document.id).await.expect("should get"), Some(updated.clone()));
+
+        // a later update cannot be rolled back by replaying an earlier one
+        let mut later = updated.clone();
+        later.also_known_as = Some(vec!["https://funder.example.org".into()]);
+        let options = ProofOptions {
+            purpose: KeyPurpose::CapabilityInvocation,
+            verification_method: Some("#key-0".into()),
+            challenge: Some(DidWeb::update_challenge(&updated).expect("should hash")),
+            ..ProofOptions::default()
+        };
+        later.add_proof(&controller, options).await.expect("should add proof");
+        DidWeb::update_authorized(later.clone(), &store).await.expect("should update");
+
+        let Err(Error::InvalidProof(_)) = DidWeb::update_authorized(updated, &store).await else {
+            panic!("should not replay an earlier update");
+        };
+        assert_eq!(store.get(&document.id).await.expect("should get"), Some(later));
+    }
+
+    #[tokio::test]
+    async fn normalize_context() {
+        let url = "https://demo.credibil.io/entity/funder";
+        let document =
+            DidWeb::create(url, &MockOperator, CreateOptions::default()).expect("should create");
+        let store = MockStore::default();
+        store.put(&document.id, &document).await.expect("should store");
+
+        // replace the context with one that has duplicates and is out of order
+        let multikey = Context::from("https://w3id.org/security/multikey/v1");
+        let did = Context::from("https://www.w3.org/ns/did/v1");
+        let mut updated = document.clone();
+        updated.context = vec![multikey.clone(), did.clone(), multikey.clone()];
+        let updated = DidWeb::update(updated, &store).await.expect("should update");
+
+        assert_eq!(updated.context, vec![did, multikey]);
+        let stored = store.get(&document.id).await.expect("should get");
+        assert_eq!(stored, Some(updated));
+    }
+
+    #[tokio::test]
+    async fn object_service_endpoint() {
+        let url = "https://demo.credibil.io/entity/funder";
+        let document =
+            DidWeb::create(url, &MockOperator, CreateOptions::default()).expect("should create");
+        let store = MockStore::default();
+        store.put(&document.id, &document).await.expect("should store");
+
+        // add a DIDComm service with a structured endpoint
+        let endpoint = json!({
+            "uri": "https://demo.credibil.io/didcomm",
+            "accept": ["didcomm/v2", "didcomm/aip2;env=rfc587"],
+            "routingKeys": ["did:example:mediator#key-1"],
+            "meta": {"priority": 1, "region": "au"}
+        });
+        let mut updated = document.clone();
+        updated.service = Some(vec![Service {
+            id: format!("{}#didcomm", document.id),
+            type_: "DIDCommMessaging".into(),
+            service_endpoint: Quota::One(Kind::Object(endpoint.clone())),
+        }]);
+        DidWeb::update(updated, &store).await.expect("should update");
+
+        // the endpoint is preserved through storage and serialization
+        let stored = store.get(&document.id).await.expect("should get").expect("should exist");
+        let serialized = serde_json::to_string(&stored).expect("should serialize");
+        let deserialized: Document = serde_json::from_str(&serialized).expect("should deserialize");
+        assert_eq!(deserialized, stored);
+
+        let service = &deserialized.service.expect("should have service")[0];
+        assert_eq!(service.service_endpoint, Quota::One(Kind::Object(endpoint)));
+    }
+
+    #[derive(Clone)]
+    struct StoredResolver(Document);
+    impl DidResolver for StoredResolver {
+        async fn resolve(&self, _url: &str) -> anyhow::Result<Document> {
+            Ok(self.0.clone())
+        }
+    }
+
+    #[derive(Default)]
+    struct MockStore(Mutex<HashMap<String, Document>>);
+    impl DocumentStore for MockStore {
+        async fn get(&self, did: &str) -> anyhow::Result<Option<Document>> {
+            Ok(self.0.lock().expect("should lock").get(did).cloned())
+        }
+
+        async fn put(&self, did: &str, document: &Document) -> anyhow::Result<()> {
+            self.0.lock().expect("should lock").insert(did.to_string(), document.clone());
+            Ok(())
+        }
+    }
+
     // generate a key pair
     pub fn generate() -> Vec<u8> {
         let mut csprng = OsRng;
@@ -0,0 +1,280 @@
+//! # DID Web Paths
+//!
+//! Maps between `did:web` method-specific identifiers and the HTTPS URLs
+//! where their DID documents are hosted.
+//!
+//! See <https://w3c-ccg.github.io/did-method-web/#read-resolve>
+
+use url::Url;
+
+use crate::error::Error;
+
+/// Convert the URL of a web location to a `did:web` method-specific
+/// identifier.
+///
+/// A port is percent-encoded (`%3A`) and each path segment becomes a
+/// `:`-delimited segment of the identifier. A trailing `did.json` or
+/// `.well-known/did.json` is ignored, so the URL of a hosted DID document
+/// maps back to its DID.
+///
+/// # Errors
+///
+/// Will fail if the URL cannot be parsed, has no host, or contains a `.` or
+/// `..` path segment.
+pub fn encode_web_path(url: &str) -> crate::Result<String> {
+    // reject traversal before the URL parser normalizes it away
+    let raw = url.split_once("://").map_or(url, |(_, rest)| rest);
+    if raw.split(['/', '?', '#']).any(is_traversal) {
+        return Err(Error::InvalidDid(format!("path traversal in url: {url}")));
+    }
+
+    let url = Url::parse(url).map_err(|e| Error::InvalidDid(format!("issue parsing url: {e}")))?;
+    let Some(host) = url.host_str() else {
+        return Err(Error::InvalidDid("no host in url".into()));
+    };
+
+    let mut id = host.to_string();
+    if let Some(port) = url.port() {
+        id = format!("{id}%3A{port}");
+    }
+
+    let path = url.path().trim_end_matches('/');
+    let path = path.strip_suffix("/did.json").unwrap_or(path);
+    let path = path.strip_suffix("/.well-known").unwrap_or(path);
+    for segment in path.split('/').filter(|s| !s.is_empty()) {
+        id = format!("{id}:{}", segment.replace(':', "%3A"));
+    }
+
+    Ok(id)
+}
+
+/// The `did:web` specification's mapping of an identifier to a URL.
+const SPEC_TEMPLATE: &str = "https://{host}/{path}/did.json";
+
+/// URL template placeholders.
+const HOST: &str = "{host}";
+const PATH: &str = "{path}";
+
+/// Convert a `did:web` method-specific identifier to the HTTPS URL of its
+/// DID document.
+///
+/// A percent-encoded port (`%3A`) in the domain is decoded into the URL's
+/// authority. When the identifier has no path, the document is located under
+/// `/.well-known`.
+///
+/// # Errors
+///
+/// Will fail if the identifier has an empty domain or path segment, an
+/// invalid port, a segment that would traverse the path (`.` or `..`), or a
+/// segment that is percent-encoded more than once.
+pub fn decode_web_path(id: &str) -> crate::Result<String> {
+    decode_web_path_with_template(id, SPEC_TEMPLATE)
+}
+
+/// Convert a `did:web` method-specific identifier to the URL of its DID
+/// document using a URL template, for deployments that host documents under
+/// a custom prefix.
+///
+/// `{host}` in the template is replaced with the domain (and any port), and
+/// `{path}` with the identifier's path segments, or `.well-known` when the
+/// identifier has no path. For example, the template
+/// `https://{host}/dids/{path}/did.json` maps `example.com:user:alice` to
+/// `https://example.com/dids/user/alice/did.json`.
+///
+/// # Errors
+///
+/// Will fail if the template is not an `https` URL or is missing the `{host}`
+/// or `{path}` placeholder, or the identifier
+/// has an empty domain or path segment, an invalid port, a segment that would
+/// traverse the path (`.` or `..`), or a segment that is percent-encoded more
+/// than once.
+pub fn decode_web_path_with_template(id: &str, template: &str) -> crate::Result<String> {
+    if !template.starts_with("https://") {
+        return Err(Error::InvalidInput(format!("did:web URL template is not https: {template}")));
+    }
+    if !template.contains(HOST) || !template.contains(PATH) {
+        return Err(Error::InvalidInput(format!(
+            "did:web URL template needs {{host}} and {{path}} placeholders: {template}"
+        )));
+    }
+
+    let mut segments = id.split(':');
+    let domain = segments.next().unwrap_or_default();
+    let decoded = percent_decode(domain, id)?;
+    if decoded.is_empty() || decoded.contains(['/', '\\']) {
+        return Err(Error::InvalidDid(format!("invalid domain in did:web: {id}")));
+    }
+
+    let path = segments.collect::<Vec<_>>();
+    for segment in &path {
+        let decoded = percent_decode(segment, id)?;
+        if decoded.is_empty() || decoded.contains(['/', '\\']) {
+            return Err(Error::InvalidDid(format!("invalid path segment in did:web: {id}")));
+        }
+        if decoded == "." || decoded == ".." {
+            return Err(Error::InvalidDid(format!("path traversal in did:web: {id}")));
+        }
+    }
+
+    // percent-decode the port colon: the port is part of the authority, and
+    // must be given explicitly as there is no fallback to other ports
+    let domain = domain.replace("%3A", ":").replace("%3a", ":");
+    if let Some((host, port)) = domain.split_once(':')
+        && (host.is_empty()
+            || port.is_empty()
+            || !port.bytes().all(|b| b.is_ascii_digit())
+            || port.parse::<u16>().is_err())
+    {
+        return Err(Error::InvalidDid(format!("invalid port in did:web: {id}")));
+    }
+    let path = if path.is_empty() { ".well-known".to_string() } else { path.join("/") };
+
+    Ok(template.replace(HOST, &domain).replace(PATH, &path))
+}
+
+// Percent-decode an identifier segment exactly once. A segment that is still
+// percent-encoded after decoding (such as `%252e`) is rejected, so double
+// encoding cannot be used to smuggle traversal past validation.
+fn percent_decode(segment: &str, id: &str) -> crate::Result<String> {
+    let invalid = || Error::InvalidDid(format!("invalid percent-encoding in did:web: {id}"));
+
+    let mut decoded = vec![];
+    let mut bytes = segment.bytes();
+    while let Some(b) = bytes.next() {
+        if b == b'%' {
+            let hex = [bytes.next().ok_or_else(invalid)?, bytes.next().ok_or_else(invalid)?];
+            let hex = std::str::from_utf8(&hex).map_err(|_| invalid())?;
+            decoded.push(u8::from_str_radix(hex, 16).map_err(|_| invalid())?);
+        } else {
+            decoded.push(b);
+        }
+    }
+    let decoded = String::from_utf8(decoded).map_err(|_| invalid())?;
+
+    if is_encoded(&decoded) {
+        return Err(Error::InvalidDid(format!("double percent-encoding in did:web: {id}")));
+    }
+    Ok(decoded)
+}
+
+// Whether the value contains a percent-encoded octet.
+fn is_encoded(value: &str) -> bool {
+    value.as_bytes().windows(3).any(|w| w[0] == b'%' && w[1..].iter().all(u8::is_ascii_hexdigit))
+}
+
+// A `.` or `..` path segment, including percent-encoded forms.
+fn is_traversal(segment: &str) -> bool {
+    let segment = segment.to_ascii_lowercase().replace("%2e", ".");
+    segment == "." || segment == ".."
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn localhost_port() {
+        let url = decode_web_path("localhost%3A8080").expect("should decode");
+        assert_eq!(url, "https://localhost:8080/.well-known/did.json");
+
+        let id = encode_web_path(&url).expect("should encode");
+        assert_eq!(id, "localhost%3A8080");
+    }
+
+    #[test]
+    fn port_and_path() {
+        let url = decode_web_path("example.com%3A3000:users:alice").expect("should decode");
+        assert_eq!(url, "https://example.com:3000/users/alice/did.json");
+
+        // the port is in the authority, not the path
+        let parsed = Url::parse(&url).expect("should parse");
+        assert_eq!(parsed.host_str(), Some("example.com"));
+        assert_eq!(parsed.port(), Some(3000));
+        assert_eq!(parsed.path(), "/users/alice/did.json");
+
+        let id = encode_web_path(&url).expect("should encode");
+        assert_eq!(id, "example.com%3A3000:users:alice");
+
+        for id in ["example.com%3A", "example.com%3Ahttp", "example.com%3A65536", "%3A3000"] {
+            let Err(Error::InvalidDid(_)) = decode_web_path(id) else {
+                panic!("should reject invalid port in {id}");
+            };
+        }
+    }
+
+    #[test]
+    fn multi_segment() {
+        let url = decode_web_path("w3c-ccg.github.io:user:alice").expect("should decode");
+        assert_eq!(url, "https://w3c-ccg.github.io/user/alice/did.json");
+
+        let id = encode_web_path(&url).expect("should encode");
+        assert_eq!(id, "w3c-ccg.github.io:user:alice");
+        let id = encode_web_path("https://w3c-ccg.github.io/user/alice").expect("should encode");
+        assert_eq!(id, "w3c-ccg.github.io:user:alice");
+    }
+
+    #[test]
+    fn custom_template() {
+        const TEMPLATE: &str = "https://{host}/dids/{path}/did.json";
+
+        let url = decode_web_path_with_template("example.com:user:alice", TEMPLATE)
+            .expect("should decode");
+        assert_eq!(url, "https://example.com/dids/user/alice/did.json");
+        let url =
+            decode_web_path_with_template("localhost%3A8080", TEMPLATE).expect("should decode");
+        assert_eq!(url, "https://localhost:8080/dids/.well-known/did.json");
+
+        // the default template follows the specification
+        let url = decode_web_path_with_template("example.com:user:alice", SPEC_TEMPLATE)
+            .expect("should decode");
+        assert_eq!(url, decode_web_path("example.com:user:alice").expect("should decode"));
+
+        let invalid = |template| {
+            let result = decode_web_path_with_template("example.com", template);
+            matches!(result, Err(Error::InvalidInput(_)))
+        };
+        assert!(invalid("https://example.com/{path}/did.json"), "should require {{host}}");
+        assert!(invalid("https://{host}/did.json"), "should require {{path}}");
+        assert!(invalid("http://{host}/{path}/did.json"), "should require https");
+    }
+
+    #[test]
+    fn traversal() {
+        let Err(Error::InvalidDid(_)) = decode_web_path("example.com:user:..:admin") else {
+            panic!("should reject traversal");
+        };
+        let Err(Error::InvalidDid(_)) = decode_web_path("example.com:%2E%2E") else {
+            panic!("should reject encoded traversal");
+        };
+        let Err(Error::InvalidDid(_)) = decode_web_path("example.com::user") else {
+            panic!("should reject empty segment");
+        };
+        let Err(Error::InvalidDid(_)) = encode_web_path("https://example.com/user/../admin") else {
+            panic!("should reject traversal");
+        };
+    }
+
+    #[test]
+    fn percent_encoding() {
+        // encoded once
+        let url = decode_web_path("example.com%3A3000:user%20name").expect("should decode");
+        assert_eq!(url, "https://example.com:3000/user%20name/did.json");
+
+        // encoded twice
+        let Err(Error::InvalidDid(msg)) = decode_web_path("example.com:%252e%252e") else {
+            panic!("should reject double-encoded traversal");
+        };
+        assert!(msg.starts_with("double percent-encoding"));
+        let Err(Error::InvalidDid(_)) = decode_web_path("example.com%253A3000") else {
+            panic!("should reject double-encoded port");
+        };
+
+        // encoded separators and malformed encoding
+        let Err(Error::InvalidDid(_)) = decode_web_path("example.com:user%2F..") else {
+            panic!("should reject encoded slash");
+        };
+        let Err(Error::InvalidDid(_)) = decode_web_path("example.com:user%2") else {
+            panic!("should reject truncated encoding");
+        };
+    }
+}
@@ -9,38 +9,92 @@
 //! - <https://w3c-ccg.github.io/did-method-key>
 //! - <https://w3c.github.io/did-resolution>
 
-use std::sync::LazyLock;
-
-use regex::Regex;
 use serde_json::json;
+use url::form_urlencoded;
 
 use super::DidWeb;
+use super::fetch::fetch;
+use super::path::{decode_web_path, decode_web_path_with_template, encode_web_path};
 use crate::DidResolver;
+use crate::did_url::method_specific_id;
+use crate::document::{Document, canonical_did};
 use crate::error::Error;
-use crate::resolution::{ContentType, Metadata, Options, Resolved};
-
-static DID_REGEX: LazyLock<Regex> = LazyLock::new(|| {
-    Regex::new("^did:web:(?<identifier>[a-zA-Z1-9.\\-:%]+)$").expect("should compile")
-});
+use crate::resolution::{ContentType, DEFAULT_WEB_TIMEOUT, Metadata, Options, Resolved};
 
 impl DidWeb {
     /// Resolve a `did:web` DID URL to a DID document.
     ///
+    /// A hosted document whose metadata marks it as deactivated resolves with
+    /// no document and `deactivated: true` in the document metadata,
+    /// distinguishing a deactivated DID from one that never existed. This is
+    /// the case whether the host serves a tombstone (a document with no
+    /// verification methods) or a document deactivated with
+    /// [`DidWeb::deactivate`], which keeps its verification methods.
+    ///
     /// # Errors
     ///
     /// Will fail if the DID URL is invalid or the DID document cannot be
-    /// found.
+    /// found, or a version ID is requested and the host does not report
+    /// returning that version.
     pub async fn resolve(
-        did: &str, _: Option<Options>, resolver: impl DidResolver,
+        did: &str, opts: Option<Options>, resolver: impl DidResolver,
     ) -> crate::Result<Resolved> {
         // Steps 1-5. Generate the URL to fetch the DID document.
-        let url = Self::url(did)?;
+        let opts = opts.unwrap_or_default();
+        let mut url = match &opts.web_path_template {
+            Some(template) => Self::url_with_template(did, template)?,
+            None => Self::url(did)?,
+        };
+
+        // request a specific version of the document from the host, if set
+        if opts.version_id.is_some() || opts.version_time.is_some() {
+            let mut query = form_urlencoded::Serializer::new(String::new());
+            if let Some(version_id) = &opts.version_id {
+                query.append_pair("versionId", version_id);
+            }
+            if let Some(version_time) = &opts.version_time {
+                query.append_pair("versionTime", version_time);
+            }
+            url = format!("{url}?{}", query.finish());
+        }
 
         // 6. Perform an HTTP GET request to the URL using an agent that can
         //    successfully negotiate a secure HTTPS connection, which enforces the
         //    security requirements as described in 2.6 SecOps and privacy
         //    considerations.
-        let document = resolver.resolve(&url).await.map_err(Error::Other)?;
+        //
+        //    Each attempt is bounded by the timeout, and network failures are
+        //    retried according to the retry policy.
+        let timeout = opts.web_timeout.unwrap_or(DEFAULT_WEB_TIMEOUT);
+        let retry = opts.web_retry.unwrap_or_default();
+        let document = fetch(resolver, &url, timeout, &retry).await?;
+
+        // the host must confirm it returned the requested version, as hosts
+        // serving a static document ignore the version parameters
+        let mut document_metadata = document.did_document_metadata.clone();
+        if let Some(version_id) = &opts.version_id {
+            let returned = document_metadata.as_ref().and_then(|md| md.version_id.as_ref());
+            if returned != Some(version_id) {
+                return Err(Error::NotFound(format!("version {version_id} not found at {url}")));
+            }
+        }
+
+        // report the canonical form of the requested DID, if different
+        if let Some(canonical_id) = canonical_did(did)
+            && canonical_id != did
+        {
+            let metadata = document_metadata.get_or_insert_default();
+            metadata.canonical_id = Some(canonical_id);
+            metadata.equivalent_id.get_or_insert_default().push(did.to_string());
+        }
+
+        // a deactivated document has no content beyond its deactivation
+        let document = if is_deactivated(&document) {
+            document_metadata.get_or_insert_default().deactivated = Some(true);
+            None
+        } else {
+            Some(document)
+        };
 
         // TODO: implement security requirement:
         // 7. When performing the DNS resolution during the HTTP GET request, the client
@@ -61,7 +115,8 @@ impl DidWeb {
                 })),
                 ..Metadata::default()
             },
-            document: Some(document),
+            document,
+            document_metadata,
             ..Resolved::default()
         })
     }
@@ -73,41 +128,58 @@ impl DidWeb {
     ///
     /// Will fail if the DID URL is not a valid `did:web` URL.
     pub fn url(did: &str) -> crate::Result<String> {
-        let Some(caps) = DID_REGEX.captures(did) else {
-            return Err(Error::InvalidDid("DID is not a valid did:web".to_string()));
-        };
-        let identifier = &caps["identifier"];
-
-        // 1. Replace ":" with "/" in the method specific identifier to obtain the fully
-        //    qualified domain name and optional path.
-        let domain = identifier.replace(':', "/");
+        let identifier = method_specific_id(did, "web")?;
 
-        // 2. If the domain contains a port percent decode the colon.
-        let domain = domain.replace("%3A", ":");
-
-        // 3. Generate an HTTPS URL to the expected location of the DID document by
-        //    prepending https://.
-        let mut url = format!("https://{domain}");
-
-        // 4. If no path has been specified in the URL, append /.well-known.
-        if !identifier.contains(':') {
-            url = format!("{url}/.well-known");
-        }
+        // Steps 1-5. Map the method-specific identifier to the URL of the
+        // document, decoding any port and appending /.well-known when no path
+        // has been specified.
+        decode_web_path(identifier)
+    }
 
-        // 5. Append /did.json to complete the URL.
-        url = format!("{url}/did.json");
+    /// Convert a `did:web` URL to the URL of its DID document using a URL
+    /// template. See [`decode_web_path_with_template`].
+    ///
+    /// # Errors
+    ///
+    /// Will fail if the DID URL is not a valid `did:web` URL or the template
+    /// is invalid.
+    pub fn url_with_template(did: &str, template: &str) -> crate::Result<String> {
+        decode_web_path_with_template(method_specific_id(did, "web")?, template)
+    }
 
-        Ok(url)
+    /// Convert the HTTPS URL where a DID document is (or will be) hosted to
+    /// its `did:web` DID. The inverse of [`DidWeb::url`].
+    ///
+    /// Both the base URL (e.g. `https://example.com/user/alice`) and the
+    /// document URL (e.g. `https://example.com/user/alice/did.json`) map to
+    /// the same DID.
+    ///
+    /// # Errors
+    ///
+    /// Will fail if the URL is not a valid web location for a DID document.
+    pub fn url_to_did(url: &str) -> crate::Result<String> {
+        Ok(format!("did:web:{}", encode_web_path(url)?))
     }
 }
 
+// Whether the hosted document's metadata marks it as deactivated.
+fn is_deactivated(document: &Document) -> bool {
+    document.did_document_metadata.as_ref().and_then(|md| md.deactivated) == Some(true)
+}
+
 #[cfg(test)]
 mod test {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
     use anyhow::anyhow;
     use insta::assert_json_snapshot as assert_snapshot;
 
     use super::*;
-    use crate::document::Document;
+    use crate::document::{DocumentMetadata, VerificationMethod};
+    use crate::resolution::RetryPolicy;
+    use crate::testing::MockWebHost;
 
     #[derive(Clone)]
     struct MockResolver;
@@ -127,10 +199,379 @@ mod test {
         assert_snapshot!("metadata", resolved.metadata);
     }
 
+    #[tokio::test]
+    async fn resolve_port_and_path() {
+        const DID: &str = "did:web:example.com%3A3000:users:alice";
+
+        let mut document: Document =
+            serde_json::from_slice(include_bytes!("did-ecdsa.json")).expect("should parse");
+        document.id = DID.into();
+        let host = MockWebHost::new();
+        host.register_at("https://example.com:3000/users/alice/did.json", document);
+
+        let resolved = DidWeb::resolve(DID, None, host).await.expect("should resolve");
+        assert_eq!(resolved.document.expect("should have document").id, DID);
+    }
+
+    #[tokio::test]
+    async fn unsupported_method_type() {
+        const DID: &str = "did:web:example.com";
+
+        let bls = json!({
+            "id": "did:web:example.com#bls",
+            "type": "Bls12381G2Key2020",
+            "controller": DID,
+            "publicKeyBase58": "25EEkQtcLKsEzQ6JTo9cg4W7NHpaurn4Wg6LaNPFq6JQXnrP91SDviUz7KrJVMJd"
+        });
+        let document: Document = serde_json::from_value(json!({
+            "@context": ["https://www.w3.org/ns/did/v1"],
+            "id": DID,
+            "verificationMethod": [{
+                "id": "did:web:example.com#key-0",
+                "type": "Multikey",
+                "controller": DID,
+                "publicKeyMultibase": "z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK"
+            }, bls],
+            "assertionMethod": ["did:web:example.com#key-0", "did:web:example.com#bls"]
+        }))
+        .expect("should deserialize");
+        let host = MockWebHost::new();
+        host.register(DID, document).expect("should register");
+
+        let resolved = DidWeb::resolve(DID, None, host).await.expect("should resolve");
+        let document = resolved.document.expect("should have document");
+
+        // the unsupported method is kept as read
+        let methods = document.verification_method.as_ref().expect("should have methods");
+        assert_eq!(methods[1].method_type.kind(), "Bls12381G2Key2020");
+        assert!(!methods[1].method_type.is_supported());
+        let serialized = serde_json::to_value(&document).expect("should serialize");
+        assert_eq!(serialized["verificationMethod"][1], bls);
+
+        // and skipped when collecting keys
+        let keys = document.all_public_keys().expect("should convert keys");
+        assert_eq!(keys.len(), 1);
+        assert_eq!(keys[0].0, "did:web:example.com#key-0");
+        assert!(document.public_key_for_kid("#bls").is_none());
+
+        // a supported type missing its key material is not read as unsupported
+        let malformed = serde_json::from_value::<VerificationMethod>(json!({
+            "id": "did:web:example.com#key-1",
+            "type": "Multikey",
+            "controller": DID
+        }));
+        let err = malformed.expect_err("should reject malformed method");
+        assert!(err.to_string().contains("publicKeyMultibase"));
+    }
+
+    #[tokio::test]
+    async fn resolve_version() {
+        const DID_URL: &str = "did:web:demo.credibil.io";
+
+        let opts = Options {
+            version_id: Some("1".into()),
+            ..Options::default()
+        };
+        let v1 =
+            DidWeb::resolve(DID_URL, Some(opts), VersionResolver).await.expect("should resolve");
+        let opts = Options {
+            version_time: Some("2025-01-01T00:00:00Z".into()),
+            ..Options::default()
+        };
+        let v2 =
+            DidWeb::resolve(DID_URL, Some(opts), VersionResolver).await.expect("should resolve");
+
+        assert_ne!(v1.document, v2.document);
+        let v1_metadata = v1.document_metadata.expect("should have metadata");
+        assert_eq!(v1_metadata.version_id.as_deref(), Some("1"));
+        let v2_metadata = v2.document_metadata.expect("should have metadata");
+        assert_eq!(v2_metadata.version_id.as_deref(), Some("2"));
+        assert!(v2_metadata.next_update.is_none());
+    }
+
+    #[tokio::test]
+    async fn unconfirmed_version() {
+        const DID_URL: &str = "did:web:demo.credibil.io";
+        let document: Document =
+            serde_json::from_slice(include_bytes!("did-ecdsa.json")).expect("should parse");
+        let host = MockWebHost::new();
+        host.register(DID_URL, document).expect("should register");
+
+        // a static host ignores the version requested
+        let opts = Options {
+            version_id: Some("1".into()),
+            ..Options::default()
+        };
+        let Err(Error::NotFound(_)) = DidWeb::resolve(DID_URL, Some(opts), host).await else {
+            panic!("should not report an unconfirmed version");
+        };
+    }
+
+    // Serves timestamped snapshots of the DID document by version.
+    #[derive(Clone)]
+    struct VersionResolver;
+    impl DidResolver for VersionResolver {
+        async fn resolve(&self, url: &str) -> anyhow::Result<Document> {
+            let mut document: Document =
+                serde_json::from_slice(include_bytes!("did-ecdsa.json"))
+                    .map_err(|e| anyhow!("issue deserializing document: {e}"))?;
+
+            let mut metadata = DocumentMetadata::default();
+            match url.split_once('?').map(|(_, query)| query) {
+                Some("versionId=1") => {
+                    document.also_known_as = Some(vec!["https://v1.credibil.io".into()]);
+                    metadata.version_id = Some("1".into());
+                    metadata.next_update = Some("2024-06-01T00:00:00Z".parse()?);
+                }
+                Some("versionTime=2025-01-01T00%3A00%3A00Z") => {
+                    document.also_known_as = Some(vec!["https://v2.credibil.io".into()]);
+                    metadata.version_id = Some("2".into());
+                }
+                _ => return Err(anyhow!("version not found")),
+            }
+            document.did_document_metadata = Some(metadata);
+
+            Ok(document)
+        }
+    }
+
+    #[tokio::test]
+    async fn path_template() {
+        const DID_URL: &str = "did:web:demo.credibil.io:user:alice";
+        const TEMPLATE: &str = "https://{host}/dids/{path}/did.json";
+        let document: Document =
+            serde_json::from_slice(include_bytes!("did-ecdsa.json")).expect("should parse");
+
+        let host = MockWebHost::new();
+        host.register(DID_URL, document.clone()).expect("should register");
+        DidWeb::resolve(DID_URL, None, host).await.expect("should resolve");
+
+        let opts = Options {
+            web_path_template: Some(TEMPLATE.into()),
+            ..Options::default()
+        };
+        let host = MockWebHost::new();
+        host.register_at("https://demo.credibil.io/dids/user/alice/did.json", document);
+        DidWeb::resolve(DID_URL, Some(opts.clone()), host).await.expect("should resolve");
+
+        // the default location is not used with a template
+        let host = MockWebHost::new();
+        host.register(DID_URL, Document::default()).expect("should register");
+        let Err(Error::NotFound(_)) = DidWeb::resolve(DID_URL, Some(opts), host).await else {
+            panic!("should not resolve");
+        };
+    }
+
+    #[derive(Clone)]
+    struct FailingResolver(fn() -> Error);
+    impl DidResolver for FailingResolver {
+        async fn resolve(&self, _url: &str) -> anyhow::Result<Document> {
+            Err((self.0)().into())
+        }
+    }
+
+    #[derive(Clone)]
+    struct NotFound;
+    impl DidResolver for NotFound {
+        async fn resolve(&self, _url: &str) -> anyhow::Result<Document> {
+            Err(anyhow!("document not found"))
+        }
+    }
+
+    #[tokio::test]
+    async fn resolver_errors() {
+        const DID_URL: &str = "did:web:demo.credibil.io";
+
+        // 404
+        let resolver = FailingResolver(|| Error::NotFound("404 Not Found".into()));
+        let Err(Error::NotFound(msg)) = DidWeb::resolve(DID_URL, None, resolver).await else {
+            panic!("should be not found");
+        };
+        assert_eq!(msg, "404 Not Found");
+
+        // connection error, with the source preserved
+        let resolver = FailingResolver(|| {
+            let io = std::io::Error::new(std::io::ErrorKind::ConnectionRefused, "refused");
+            Error::NetworkError(io.into())
+        });
+        let Err(err) = DidWeb::resolve(DID_URL, None, resolver).await else {
+            panic!("should fail");
+        };
+        assert_eq!(err.code(), "networkError");
+        let source = std::error::Error::source(&err).expect("should have source");
+        let io = source.downcast_ref::<std::io::Error>().expect("should be io error");
+        assert_eq!(io.kind(), std::io::ErrorKind::ConnectionRefused);
+
+        // unregistered DIDs are not found
+        let Err(Error::NotFound(msg)) = DidWeb::resolve(DID_URL, None, MockWebHost::new()).await
+        else {
+            panic!("should be not found");
+        };
+        assert_eq!(msg, "https://demo.credibil.io/.well-known/did.json returned 404 Not Found");
+
+        // unclassified errors are reported as not found
+        let Err(Error::NotFound(_)) = DidWeb::resolve(DID_URL, None, NotFound).await else {
+            panic!("should be not found");
+        };
+    }
+
+    // Simulates a host that is unreachable for a number of requests.
+    #[derive(Clone)]
+    struct FlakyHost {
+        host: MockWebHost,
+        failures: usize,
+        requests: Arc<AtomicUsize>,
+    }
+    impl DidResolver for FlakyHost {
+        async fn resolve(&self, url: &str) -> anyhow::Result<Document> {
+            if self.requests.fetch_add(1, Ordering::SeqCst) < self.failures {
+                return Err(Error::NetworkError(anyhow!("connection reset")).into());
+            }
+            self.host.resolve(url).await
+        }
+    }
+
+    #[derive(Clone)]
+    struct Unresponsive;
+    impl DidResolver for Unresponsive {
+        async fn resolve(&self, _url: &str) -> anyhow::Result<Document> {
+            std::future::pending().await
+        }
+    }
+
+    #[tokio::test]
+    async fn timeout_and_retry() {
+        const DID_URL: &str = "did:web:demo.credibil.io";
+
+        let opts = Options {
+            web_timeout: Some(Duration::from_millis(20)),
+            web_retry: Some(RetryPolicy {
+                retries: 2,
+                backoff: Duration::from_millis(1),
+            }),
+            ..Options::default()
+        };
+        let flaky = |failures| {
+            let host = MockWebHost::new();
+            host.register(DID_URL, Document::default()).expect("should register");
+            FlakyHost {
+                host,
+                failures,
+                requests: Arc::new(AtomicUsize::new(0)),
+            }
+        };
+
+        // a request that does not complete in time is a network error
+        let Err(Error::NetworkError(e)) =
+            DidWeb::resolve(DID_URL, Some(opts.clone()), Unresponsive).await
+        else {
+            panic!("should time out");
+        };
+        assert!(e.to_string().contains("timed out"));
+
+        // transient failures are retried
+        let host = flaky(2);
+        DidWeb::resolve(DID_URL, Some(opts.clone()), host.clone()).await.expect("should resolve");
+        assert_eq!(host.requests.load(Ordering::SeqCst), 3);
+
+        // the last failure is returned once retries are exhausted
+        let host = flaky(3);
+        let Err(Error::NetworkError(_)) =
+            DidWeb::resolve(DID_URL, Some(opts.clone()), host.clone()).await
+        else {
+            panic!("should fail");
+        };
+        assert_eq!(host.requests.load(Ordering::SeqCst), 3);
+
+        // missing documents are not retried
+        let host = FlakyHost {
+            host: MockWebHost::new(),
+            ..flaky(0)
+        };
+        let Err(Error::NotFound(_)) = DidWeb::resolve(DID_URL, Some(opts), host.clone()).await
+        else {
+            panic!("should be not found");
+        };
+        assert_eq!(host.requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn retry_backoff() {
+        let retry = RetryPolicy::default();
+        assert_eq!(retry.backoff(0), Duration::from_millis(500));
+        assert_eq!(retry.backoff(1), Duration::from_secs(1));
+        assert_eq!(retry.backoff(2), Duration::from_secs(2));
+    }
+
+    #[tokio::test]
+    async fn tombstone() {
+        const DID_URL: &str = "did:web:demo.credibil.io";
+
+        // a hosted tombstone is reported as deactivated
+        let host = MockWebHost::new();
+        let tombstone = Document {
+            id: DID_URL.into(),
+            did_document_metadata: Some(DocumentMetadata {
+                deactivated: Some(true),
+                ..DocumentMetadata::default()
+            }),
+            ..Document::default()
+        };
+        host.register(DID_URL, tombstone).expect("should register");
+
+        let resolved = DidWeb::resolve(DID_URL, None, host).await.expect("should resolve");
+        assert!(resolved.document.is_none());
+        let metadata = resolved.document_metadata.expect("should have metadata");
+        assert_eq!(metadata.deactivated, Some(true));
+
+        // a DID that never existed is not found
+        let Err(Error::NotFound(_)) = DidWeb::resolve(DID_URL, None, MockWebHost::new()).await
+        else {
+            panic!("should be not found");
+        };
+    }
+
+    #[tokio::test]
+    async fn canonical_id() {
+        const DID_URL: &str = "did:web:Demo.Credibil.io";
+
+        let resolved = DidWeb::resolve(DID_URL, None, MockResolver).await.expect("should resolve");
+        let metadata = resolved.document_metadata.expect("should have metadata");
+        assert_eq!(metadata.canonical_id.as_deref(), Some("did:web:demo.credibil.io"));
+        assert_eq!(metadata.equivalent_id, Some(vec![DID_URL.to_string()]));
+
+        let document = resolved.document.expect("should have document");
+        assert_eq!(document.canonical_id().as_deref(), Some("did:web:demo.credibil.io"));
+
+        // the canonical form is not reported when requested
+        let resolved = DidWeb::resolve("did:web:demo.credibil.io", None, MockResolver)
+            .await
+            .expect("should resolve");
+        assert!(resolved.document_metadata.is_none_or(|md| md.canonical_id.is_none()));
+    }
+
     #[test]
     fn should_construct_url() {
         let did = "did:web:domain.with-hypens.computer";
         let url = DidWeb::url(did).expect("should construct URL");
         assert_eq!(url, "https://domain.with-hypens.computer/.well-known/did.json");
     }
+
+    #[test]
+    fn url_to_did() {
+        // root
+        let did = DidWeb::url_to_did("https://demo.credibil.io").expect("should convert");
+        assert_eq!(did, "did:web:demo.credibil.io");
+        let url = DidWeb::url(&did).expect("should construct URL");
+        assert_eq!(url, "https://demo.credibil.io/.well-known/did.json");
+        assert_eq!(DidWeb::url_to_did(&url).expect("should convert"), did);
+
+        // nested path
+        let did =
+            DidWeb::url_to_did("https://demo.credibil.io/entity/funder").expect("should convert");
+        assert_eq!(did, "did:web:demo.credibil.io:entity:funder");
+        let url = DidWeb::url(&did).expect("should construct URL");
+        assert_eq!(url, "https://demo.credibil.io/entity/funder/did.json");
+        assert_eq!(DidWeb::url_to_did(&url).expect("should convert"), did);
+    }
 }